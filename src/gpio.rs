@@ -22,7 +22,9 @@
 //!
 //! To ensure fast performance, RPPAL controls the GPIO peripheral by directly
 //! accessing the registers through either `/dev/gpiomem` or `/dev/mem`. GPIO interrupts
-//! are configured using the `gpiochip` character device.
+//! are configured using the `gpiochip` character device. RPPAL uses the character device's
+//! v2 ABI when it's available (Linux 5.10 or later), and automatically falls back to the
+//! deprecated v1 ABI on older kernels.
 //!
 //! ## Pins
 //!
@@ -38,12 +40,37 @@
 //! (or a derived [`InputPin`], [`OutputPin`] or [`IoPin`]) goes out of scope, it can be
 //! retrieved again through another [`Gpio::get`] call.
 //!
+//! [`Gpio::get`] only keeps track of pins retrieved within the current process. Call
+//! [`Gpio::set_conflict_detection`] to have [`Gpio::get`] return `Err(`[`Error::PinInUse`]`)`
+//! for pins that are requested by another process or kernel driver, or that are configured
+//! for one of their alternate functions. [`Gpio::line_info`] returns the name, consumer and
+//! configuration of any GPIO line.
+//!
+//! Pins can also be retrieved by the line names assigned in the device tree, such as
+//! `GPIO17` or `ID_SDA`, through [`Gpio::get_by_name`]. [`Gpio::lines`] lists all lines.
+//!
+//! [`Gpio::get_physical`] retrieves a pin by its physical pin number on the GPIO header.
+//! The [`header`] module translates between BCM GPIO pin numbers, physical pin numbers
+//! and wiringPi pin numbers for each Raspberry Pi model.
+//!
+//! [`alt_function`] looks up which peripheral [`Function`] is assigned to a pin in one of its
+//! alternate function modes, such as [`Function::Pwm0Channel0`] for GPIO 18 in [`Alt5`] mode, and
+//! [`function_pins`] finds all pins and modes that provide a specific function.
+//!
 //! By default, pins are reset to their original state when they go out of scope.
 //! Use [`InputPin::set_reset_on_drop(false)`], [`OutputPin::set_reset_on_drop(false)`]
 //! or [`IoPin::set_reset_on_drop(false)`], respectively, to disable this behavior.
 //! Note that `drop` methods aren't called when a process is abnormally terminated (for
 //! instance when a `SIGINT` signal isn't caught).
 //!
+//! Lines that are shared by multiple devices, such as interrupt lines or 1-Wire buses,
+//! can be driven with an open-drain output retrieved through [`Pin::into_output_open_drain`].
+//!
+//! Inverted signals, such as LEDs connected to 3.3 V or buttons connected to ground, can
+//! be handled by configuring a pin as active-low through [`InputPin::set_active_low`],
+//! [`OutputPin::set_active_low`] or [`IoPin::set_active_low`]. All logic levels, including
+//! those delivered by interrupts, are then inverted.
+//!
 //! ## Interrupts
 //!
 //! [`InputPin`] supports both synchronous and asynchronous interrupt handlers.
@@ -56,6 +83,49 @@
 //!
 //! Asynchronous interrupt triggers are configured using [`InputPin::set_async_interrupt`]. The
 //! specified callback function will be executed on a separate thread when a trigger event occurs.
+//! All asynchronous interrupt triggers share a single dispatcher thread. Callbacks can be moved to
+//! a thread pool by configuring an executor with [`Gpio::set_callback_executor`].
+//!
+//! If you need to know exactly when a trigger event occurred, for instance to measure pulse
+//! widths, use [`InputPin::poll_event`], [`Gpio::poll_events`] or
+//! [`InputPin::set_async_event_interrupt`] instead. These return an [`Event`] containing a
+//! timestamp recorded by the kernel, and a sequence number. [`InputPin::read_events`] and
+//! [`Gpio::read_events`] drain all queued events at once, for signals that trigger more
+//! frequently than individual events can be polled.
+//!
+//! [`InputPin::measure_pulse`] uses the event timestamps to measure the duration of a single
+//! pulse. [`InputPin::frequency_counter`] returns a [`FrequencyCounter`], which continuously
+//! measures the frequency, period and duty cycle of a signal in the background.
+//!
+//! When the optional `async` feature is enabled, [`InputPin::events`] returns a `Stream` of
+//! trigger events, which can be awaited using any async runtime. All streams share a single
+//! background thread.
+//!
+//! Contact bounce on mechanical switches can be filtered out by configuring a debounce
+//! period with [`InputPin::set_debounce`] before setting an interrupt trigger.
+//!
+//! For tight control loops where the latency of the kernel's interrupt handling is too
+//! high, [`InputPin::set_edge_detect`] configures the GPIO peripheral's edge and level
+//! detect registers directly, which can then be polled with [`InputPin::take_edge_detected`].
+//! Detected events are also signaled to the kernel, which disables the GPIO interrupt
+//! for all pins if they're left unhandled, so event detection can't be used while any
+//! process relies on interrupt triggers.
+//!
+//! ## Ports
+//!
+//! [`OutputPort`] and [`InputPort`] combine multiple pins into a single port, which is
+//! written or read as a single value. With the default backend, all pins in a port are
+//! updated or read with as few register accesses as possible, rather than one pin at a time,
+//! which is useful for parallel buses.
+//!
+//! ## Pad configuration
+//!
+//! The drive strength, slew rate limiting and input hysteresis of the GPIO pads
+//! are configured per [`Bank`] through [`Gpio::set_pad_config`]. Increasing the
+//! drive strength helps maintain valid logic levels when driving long cables or
+//! LEDs, while slew rate limiting reduces ringing. Each setting affects all pins within
+//! the bank. The pad control registers are only accessible through `/dev/mem`, which
+//! requires superuser privileges.
 //!
 //! ## Software-based PWM
 //!
@@ -71,6 +141,106 @@
 //! function call overhead, typical jitter is expected to be up to 10 µs on debug builds, and up to
 //! 2 µs on release builds.
 //!
+//! By default, each pin uses a dedicated PWM thread. When driving a large number of pins, select
+//! [`PwmBackend::Scheduler`] with [`OutputPin::set_pwm_backend`] to service all pins from a
+//! single shared thread instead. Edges that fall within the resolution configured through
+//! [`SchedulerConfig`] are grouped, and written to the GPIO registers simultaneously.
+//!
+//! Alternatively, the PWM signal can be generated through DMA by selecting
+//! [`PwmBackend::Dma`] with [`OutputPin::set_pwm_backend`]. Similar to ServoBlaster and
+//! pigpio, a DMA channel continuously writes to the GPIO set/clear registers, paced by either
+//! the PWM or the PCM peripheral's FIFO. All pins share a single cycle, which is divided into
+//! steps of equal length as configured through [`DmaConfig`]. Pulse widths are rounded to the
+//! nearest step, and periods are rounded to the nearest value that evenly divides the cycle.
+//! Once configured, the PWM signal doesn't require any CPU time, and isn't affected by
+//! scheduling/preemption.
+//!
+//! DMA-based PWM requires superuser privileges, and is only available for push-pull outputs on
+//! GPIO 0-31 when using the native backend. While any pins are driven by DMA, the selected
+//! pacing peripheral can't be used for other purposes. Pacing through the PWM peripheral
+//! disables the hardware [`Pwm`] channels and analog audio output, and pacing through the PCM
+//! peripheral disables I2S audio. Make sure the selected DMA channel isn't in use by the kernel
+//! or the firmware.
+//!
+//! ## Waveforms
+//!
+//! [`OutputPin::send_waveform`] generates an arbitrary sequence of output states with precise
+//! durations, which can be used for IR remote control codes, bursts of stepper motor pulses or
+//! similar protocols. [`OutputPin::send_waveform_repeat`] repeats a waveform in the background,
+//! and returns a [`WaveformHandle`] that can be used to wait for the waveform to complete, or
+//! stop it early.
+//!
+//! Waveforms are generated using the same timing approach as software-based PWM, on a dedicated
+//! thread. If [`PwmBackend::Dma`] is selected, waveforms are generated through DMA instead. DMA
+//! waveforms require exclusive access to the DMA channel, and can't be used while any pins are
+//! driven by DMA-based PWM.
+//!
+//! ## Stepper motors
+//!
+//! The [`stepper`] module controls stepper motor drivers with a STEP/DIR interface, such as
+//! the A4988, DRV8825 and TMC2208. A [`Stepper`] accelerates and decelerates the motor using
+//! a trapezoidal or S-curve motion profile, keeps track of its position, and generates the
+//! step pulses in the background.
+//!
+//! ## Character device only mode
+//!
+//! In environments where neither `/dev/gpiomem` nor `/dev/mem` are accessible, such as
+//! containers that only have access to `/dev/gpiochipN`, [`Gpio::with_cdev_only`]
+//! constructs a [`Gpio`] instance that accesses the pins exclusively through the
+//! `gpiochip` character device. Reading and writing logic levels, switching between
+//! [`Input`] and [`Output`] mode and configuring the built-in pull-up/pull-down resistors
+//! are supported, but each operation requires a system call, which is considerably
+//! slower than direct register access. Alternate function modes can't be selected, and
+//! configuring pull-up/pull-down resistors requires Linux 5.5 or later.
+//!
+//! A pin is requested from the character device when it's retrieved through [`Gpio::get`],
+//! which returns an error if the line is already in use by another process, and released
+//! when it goes out of scope. Changing the pin's mode or resistors reconfigures the line
+//! without releasing it. On Linux 5.4 and earlier, the line is briefly released and
+//! requested again instead.
+//!
+//! ## GPIO chips
+//!
+//! Besides the SoC's GPIO pins, the kernel may expose additional GPIO chips through
+//! `/dev/gpiochipN`, such as the firmware-controlled GPIO expander on the Raspberry Pi 3 B,
+//! 3 B+ and 4 B, or I2C GPIO expanders with a kernel driver. [`Chip::list`] enumerates all
+//! available chips, and [`Chip::open`] opens a chip by its path, name or label. Lines
+//! retrieved through [`Chip::get`] are accessed through the character device in the same
+//! way as [character device only mode], and can be converted to an [`InputPin`],
+//! [`OutputPin`] or [`IoPin`] as usual.
+//!
+//! ## Simulation
+//!
+//! Code that uses the GPIO peripheral can be tested on systems without a Raspberry Pi
+//! by constructing a [`Gpio`] instance through [`Gpio::with_backend`] with the
+//! [`Simulated`] backend. A [`Simulator`] replaces the GPIO registers with an in-memory
+//! register file, and allows external signals to be applied to input pins.
+//! Any resulting level changes trigger (a)synchronous interrupts as usual.
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use rppal::gpio::{Backend, Gpio, Level, Simulator, Trigger};
+//!
+//! # fn main() -> rppal::gpio::Result<()> {
+//! let simulator = Simulator::new();
+//! let gpio = Gpio::with_backend(Backend::Simulated(simulator.clone()))?;
+//!
+//! let mut output = gpio.get(23)?.into_output();
+//! output.set_high();
+//! assert_eq!(simulator.level(23)?, Level::High);
+//!
+//! let mut input = gpio.get(24)?.into_input();
+//! input.set_interrupt(Trigger::RisingEdge)?;
+//! simulator.set_level(24, Level::High)?;
+//! assert_eq!(
+//!     input.poll_interrupt(false, Some(Duration::from_secs(1)))?,
+//!     Some(Level::High)
+//! );
+//! # Ok(())
+//! # }
+//! ```
+//!
 //! ## Examples
 //!
 //! Basic example:
@@ -119,13 +289,67 @@
 //! [raspberrypi/linux#2289]: https://github.com/raspberrypi/linux/issues/2289
 //! [`Gpio`]: struct.Gpio.html
 //! [`Gpio::get`]: struct.Gpio.html#method.get
+//! [`Gpio::with_backend`]: struct.Gpio.html#method.with_backend
+//! [`Gpio::with_cdev_only`]: struct.Gpio.html#method.with_cdev_only
+//! [`Chip::list`]: struct.Chip.html#method.list
+//! [`PwmBackend::Scheduler`]: enum.PwmBackend.html#variant.Scheduler
+//! [`SchedulerConfig`]: struct.SchedulerConfig.html
+//! [`PwmBackend::Dma`]: enum.PwmBackend.html#variant.Dma
+//! [`OutputPin::set_pwm_backend`]: struct.OutputPin.html#method.set_pwm_backend
+//! [`DmaConfig`]: struct.DmaConfig.html
+//! [`OutputPin::send_waveform`]: struct.OutputPin.html#method.send_waveform
+//! [`OutputPin::send_waveform_repeat`]: struct.OutputPin.html#method.send_waveform_repeat
+//! [`WaveformHandle`]: struct.WaveformHandle.html
+//! [`stepper`]: stepper/index.html
+//! [`Stepper`]: stepper/struct.Stepper.html
+//! [`InputPin::measure_pulse`]: struct.InputPin.html#method.measure_pulse
+//! [`InputPin::frequency_counter`]: struct.InputPin.html#method.frequency_counter
+//! [`FrequencyCounter`]: struct.FrequencyCounter.html
+//! [`Chip::open`]: struct.Chip.html#method.open
+//! [`Chip::get`]: struct.Chip.html#method.get
+//! [character device only mode]: #character-device-only-mode
+//! [`Input`]: enum.Mode.html#variant.Input
+//! [`Output`]: enum.Mode.html#variant.Output
+//! [`Simulated`]: enum.Backend.html#variant.Simulated
+//! [`Simulator`]: struct.Simulator.html
 //! [`Gpio::poll_interrupts`]: struct.Gpio.html#method.poll_interrupts
 //! [`Pin`]: struct.Pin.html
+//! [`Pin::into_output_open_drain`]: struct.Pin.html#method.into_output_open_drain
+//! [`Gpio::set_conflict_detection`]: struct.Gpio.html#method.set_conflict_detection
+//! [`Gpio::line_info`]: struct.Gpio.html#method.line_info
+//! [`Gpio::get_by_name`]: struct.Gpio.html#method.get_by_name
+//! [`Gpio::lines`]: struct.Gpio.html#method.lines
+//! [`Gpio::get_physical`]: struct.Gpio.html#method.get_physical
+//! [`header`]: header/index.html
+//! [`alt_function`]: fn.alt_function.html
+//! [`function_pins`]: fn.function_pins.html
+//! [`Function`]: enum.Function.html
+//! [`Function::Pwm0Channel0`]: enum.Function.html#variant.Pwm0Channel0
+//! [`Alt5`]: enum.Mode.html#variant.Alt5
+//! [`Error::PinInUse`]: enum.Error.html#variant.PinInUse
+//! [`InputPin::set_active_low`]: struct.InputPin.html#method.set_active_low
+//! [`OutputPin::set_active_low`]: struct.OutputPin.html#method.set_active_low
+//! [`IoPin::set_active_low`]: struct.IoPin.html#method.set_active_low
 //! [`InputPin`]: struct.InputPin.html
 //! [`InputPin::set_reset_on_drop(false)`]: struct.InputPin.html#method.set_reset_on_drop
 //! [`InputPin::set_interrupt`]: struct.InputPin.html#method.set_interrupt
 //! [`InputPin::poll_interrupt`]: struct.InputPin.html#method.poll_interrupt
 //! [`InputPin::set_async_interrupt`]: struct.InputPin.html#method.set_async_interrupt
+//! [`Gpio::set_callback_executor`]: struct.Gpio.html#method.set_callback_executor
+//! [`OutputPort`]: struct.OutputPort.html
+//! [`InputPort`]: struct.InputPort.html
+//! [`Bank`]: enum.Bank.html
+//! [`Gpio::set_pad_config`]: struct.Gpio.html#method.set_pad_config
+//! [`InputPin::poll_event`]: struct.InputPin.html#method.poll_event
+//! [`Gpio::poll_events`]: struct.Gpio.html#method.poll_events
+//! [`InputPin::set_async_event_interrupt`]: struct.InputPin.html#method.set_async_event_interrupt
+//! [`Event`]: struct.Event.html
+//! [`InputPin::read_events`]: struct.InputPin.html#method.read_events
+//! [`InputPin::set_debounce`]: struct.InputPin.html#method.set_debounce
+//! [`InputPin::set_edge_detect`]: struct.InputPin.html#method.set_edge_detect
+//! [`InputPin::take_edge_detected`]: struct.InputPin.html#method.take_edge_detected
+//! [`InputPin::events`]: struct.InputPin.html#method.events
+//! [`Gpio::read_events`]: struct.Gpio.html#method.read_events
 //! [`OutputPin`]: struct.OutputPin.html
 //! [`OutputPin::set_reset_on_drop(false)`]: struct.OutputPin.html#method.set_reset_on_drop
 //! [`IoPin`]: struct.IoPin.html
@@ -136,7 +360,6 @@ use std::error;
 use std::fmt;
 use std::io;
 use std::ops::Not;
-use std::os::unix::io::AsRawFd;
 use std::result;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, Weak};
@@ -144,20 +367,50 @@ use std::time::Duration;
 
 use lazy_static::lazy_static;
 
+mod backend;
+mod chip;
+mod counter;
+mod dma;
 mod epoll;
+mod function;
 #[cfg(feature = "hal")]
 mod hal;
 #[cfg(feature = "hal-unproven")]
 mod hal_unproven;
+pub mod header;
 mod interrupt;
 mod ioctl;
 mod mem;
+mod pads;
 mod pin;
+mod port;
+mod scheduler;
+mod sim;
 mod soft_pwm;
+pub mod stepper;
+#[cfg(feature = "async")]
+mod stream;
+#[cfg(test)]
+mod tests;
+mod waveform;
 
 use crate::system;
 
+use self::backend::GpioBackend;
+
+pub use self::chip::{Chip, ChipInfo};
+pub use self::counter::FrequencyCounter;
+pub use self::dma::{DmaConfig, DmaPacing};
+pub use self::function::{alt_function, function_pins, Function};
+pub use self::pads::{Bank, DriveStrength, PadConfig};
 pub use self::pin::{InputPin, IoPin, OutputPin, Pin};
+pub use self::port::{InputPort, OutputPort};
+pub use self::scheduler::SchedulerConfig;
+pub use self::sim::Simulator;
+pub use self::soft_pwm::PwmBackend;
+#[cfg(feature = "async")]
+pub use self::stream::EventStream;
+pub use self::waveform::WaveformHandle;
 
 /// Errors that can occur when accessing the GPIO peripheral.
 #[derive(Debug)]
@@ -196,6 +449,38 @@ pub enum Error {
     Io(io::Error),
     /// Thread panicked.
     ThreadPanic,
+    /// Operation not supported.
+    ///
+    /// The selected [`Backend`] doesn't support the requested operation. For
+    /// instance, the [`Cdev`] backend can't access the GPIO registers directly.
+    ///
+    /// [`Backend`]: enum.Backend.html
+    /// [`Cdev`]: enum.Backend.html#variant.Cdev
+    NotSupported,
+    /// Pin is in use by another consumer.
+    ///
+    /// Returned by [`Gpio::get`] when conflict detection is enabled, and the pin is either
+    /// requested by another process or kernel driver, or configured for one of its
+    /// alternate functions. With the [`Cdev`] backend, this is also returned when the
+    /// line can't be requested because it's in use. `consumer` contains the consumer's label, or the pin's
+    /// current mode for alternate functions, and may be empty if it's unknown.
+    ///
+    /// [`Gpio::get`]: struct.Gpio.html#method.get
+    /// [`Cdev`]: enum.Backend.html#variant.Cdev
+    PinInUse { pin: u8, consumer: String },
+    /// Line name not found.
+    ///
+    /// None of the GPIO lines has the name specified in a call to [`Gpio::get_by_name`].
+    ///
+    /// [`Gpio::get_by_name`]: struct.Gpio.html#method.get_by_name
+    LineNotFound(String),
+    /// Physical pin not available.
+    ///
+    /// The physical pin number specified in a call to [`Gpio::get_physical`] doesn't
+    /// exist on the GPIO header, or isn't a GPIO pin.
+    ///
+    /// [`Gpio::get_physical`]: struct.Gpio.html#method.get_physical
+    PhysicalPinNotAvailable(u8),
 }
 
 impl fmt::Display for Error {
@@ -206,6 +491,18 @@ impl fmt::Display for Error {
             Error::PermissionDenied(ref path) => write!(f, "Permission denied: {}", path),
             Error::Io(ref err) => write!(f, "I/O error: {}", err),
             Error::ThreadPanic => write!(f, "Thread panicked"),
+            Error::NotSupported => write!(f, "Operation not supported by backend"),
+            Error::PinInUse { pin, ref consumer } => {
+                if consumer.is_empty() {
+                    write!(f, "Pin {} is in use", pin)
+                } else {
+                    write!(f, "Pin {} is in use by {}", pin, consumer)
+                }
+            }
+            Error::LineNotFound(ref name) => write!(f, "Line not found: {}", name),
+            Error::PhysicalPinNotAvailable(pin) => {
+                write!(f, "Physical pin {} is not available", pin)
+            }
         }
     }
 }
@@ -302,6 +599,33 @@ impl fmt::Display for PullUpDown {
     }
 }
 
+/// Output drive modes.
+///
+/// A [`PushPull`] output actively drives the line both high and low. An [`OpenDrain`]
+/// output only actively drives the line low, and releases it otherwise, which allows
+/// multiple devices to share a line that's pulled high by an external resistor. An
+/// [`OpenSource`] output only actively drives the line high.
+///
+/// [`PushPull`]: #variant.PushPull
+/// [`OpenDrain`]: #variant.OpenDrain
+/// [`OpenSource`]: #variant.OpenSource
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Drive {
+    PushPull,
+    OpenDrain,
+    OpenSource,
+}
+
+impl fmt::Display for Drive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Drive::PushPull => write!(f, "PushPull"),
+            Drive::OpenDrain => write!(f, "OpenDrain"),
+            Drive::OpenSource => write!(f, "OpenSource"),
+        }
+    }
+}
+
 /// Interrupt trigger conditions.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum Trigger {
@@ -322,22 +646,266 @@ impl fmt::Display for Trigger {
     }
 }
 
+/// Event detection conditions.
+///
+/// `Detect` selects which of the GPIO peripheral's event detect registers are enabled
+/// for a pin configured through [`InputPin::set_edge_detect`].
+///
+/// Synchronous edge detection samples the pin using the system clock, which filters out
+/// glitches, but also ignores pulses shorter than a few clock cycles. Asynchronous edge
+/// detection isn't sampled, and detects very short pulses. Level detection keeps
+/// reporting an event for as long as the pin remains at the selected level.
+///
+/// [`InputPin::set_edge_detect`]: struct.InputPin.html#method.set_edge_detect
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Detect {
+    /// Event detection disabled.
+    Disabled,
+    /// Synchronous rising edge detection (`GPRENn`).
+    RisingEdge,
+    /// Synchronous falling edge detection (`GPFENn`).
+    FallingEdge,
+    /// Synchronous rising and falling edge detection (`GPRENn` and `GPFENn`).
+    BothEdges,
+    /// Asynchronous rising edge detection (`GPARENn`).
+    AsyncRisingEdge,
+    /// Asynchronous falling edge detection (`GPAFENn`).
+    AsyncFallingEdge,
+    /// Asynchronous rising and falling edge detection (`GPARENn` and `GPAFENn`).
+    AsyncBothEdges,
+    /// High level detection (`GPHENn`).
+    High,
+    /// Low level detection (`GPLENn`).
+    Low,
+}
+
+impl fmt::Display for Detect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Detect::Disabled => write!(f, "Disabled"),
+            Detect::RisingEdge => write!(f, "RisingEdge"),
+            Detect::FallingEdge => write!(f, "FallingEdge"),
+            Detect::BothEdges => write!(f, "BothEdges"),
+            Detect::AsyncRisingEdge => write!(f, "AsyncRisingEdge"),
+            Detect::AsyncFallingEdge => write!(f, "AsyncFallingEdge"),
+            Detect::AsyncBothEdges => write!(f, "AsyncBothEdges"),
+            Detect::High => write!(f, "High"),
+            Detect::Low => write!(f, "Low"),
+        }
+    }
+}
+
+/// Interrupt trigger event.
+///
+/// `Event`s are returned by [`InputPin::poll_event`] and [`Gpio::poll_events`], and
+/// passed to the callback configured through [`InputPin::set_async_event_interrupt`].
+///
+/// The timestamp is recorded by the kernel when the edge is detected, which makes it
+/// considerably more accurate than a timestamp retrieved in user space after the event
+/// has been read. Subtracting the timestamps of two consecutive events on the same pin
+/// results in the pulse width.
+///
+/// [`InputPin::poll_event`]: struct.InputPin.html#method.poll_event
+/// [`Gpio::poll_events`]: struct.Gpio.html#method.poll_events
+/// [`InputPin::set_async_event_interrupt`]: struct.InputPin.html#method.set_async_event_interrupt
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Event {
+    /// BCM GPIO pin number.
+    pub pin: u8,
+    /// Edge that triggered the event, either [`RisingEdge`] or [`FallingEdge`].
+    ///
+    /// [`RisingEdge`]: enum.Trigger.html#variant.RisingEdge
+    /// [`FallingEdge`]: enum.Trigger.html#variant.FallingEdge
+    pub trigger: Trigger,
+    /// Time at which the edge was detected, based on `CLOCK_MONOTONIC`. Linux kernels prior
+    /// to 5.7 use `CLOCK_REALTIME` instead.
+    pub timestamp: Duration,
+    /// Sequence number, which starts at 1 and is incremented for every event on this pin
+    /// after the interrupt trigger is configured. On Linux 5.10 or later, a gap between
+    /// two consecutive sequence numbers indicates the kernel dropped events because they
+    /// weren't read in time.
+    pub seqno: u32,
+}
+
+/// Summary of the events returned by [`InputPin::read_events`] and [`Gpio::read_events`].
+///
+/// [`InputPin::read_events`]: struct.InputPin.html#method.read_events
+/// [`Gpio::read_events`]: struct.Gpio.html#method.read_events
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct EventsRead {
+    /// Number of events stored in the buffer.
+    pub count: usize,
+    /// Number of events the kernel discarded since the previous read, because its event
+    /// queue overflowed before they could be read. Dropped events can only be detected on
+    /// Linux 5.10 or later.
+    pub lost: u32,
+    /// `true` if more events are waiting that didn't fit in the buffer.
+    pub pending: bool,
+}
+
+/// Information about a GPIO line, as reported by the `gpiochip` character device.
+///
+/// `LineInfo` is returned by [`Gpio::line_info`].
+///
+/// [`Gpio::line_info`]: struct.Gpio.html#method.line_info
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LineInfo {
+    /// BCM GPIO pin number.
+    pub pin: u8,
+    /// Line name, as specified in the device tree. Empty if the line is unnamed.
+    pub name: String,
+    /// Label of the process or kernel driver that requested the line. Empty if the
+    /// line isn't requested, or the consumer didn't specify a label.
+    pub consumer: String,
+    /// Line direction, either [`Input`] or [`Output`].
+    ///
+    /// [`Input`]: enum.Mode.html#variant.Input
+    /// [`Output`]: enum.Mode.html#variant.Output
+    pub direction: Mode,
+    /// `true` if the line is in use by another process or kernel driver.
+    pub used: bool,
+    /// `true` if the line is configured as active-low.
+    pub active_low: bool,
+    /// Output drive mode.
+    pub drive: Drive,
+    /// Bias setting. `None` if the kernel doesn't report the bias (Linux 5.4 or earlier).
+    pub bias: Option<PullUpDown>,
+}
+
+impl Trigger {
+    // Swaps the edges, for pins configured as active-low
+    pub(crate) fn invert(self) -> Trigger {
+        match self {
+            Trigger::RisingEdge => Trigger::FallingEdge,
+            Trigger::FallingEdge => Trigger::RisingEdge,
+            trigger => trigger,
+        }
+    }
+}
+
+impl Detect {
+    // Swaps the edges and levels, for pins configured as active-low
+    pub(crate) fn invert(self) -> Detect {
+        match self {
+            Detect::RisingEdge => Detect::FallingEdge,
+            Detect::FallingEdge => Detect::RisingEdge,
+            Detect::AsyncRisingEdge => Detect::AsyncFallingEdge,
+            Detect::AsyncFallingEdge => Detect::AsyncRisingEdge,
+            Detect::High => Detect::Low,
+            Detect::Low => Detect::High,
+            detect => detect,
+        }
+    }
+}
+
+impl Event {
+    // Logic level after the edge
+    pub(crate) fn level(&self) -> Level {
+        match self.trigger {
+            Trigger::RisingEdge => Level::High,
+            _ => Level::Low,
+        }
+    }
+}
+
+/// GPIO backends.
+///
+/// The backend determines how a [`Gpio`] instance accesses the GPIO pins. Use
+/// [`Gpio::with_backend`] to construct a [`Gpio`] instance with a specific backend.
+///
+/// [`Gpio`]: struct.Gpio.html
+/// [`Gpio::with_backend`]: struct.Gpio.html#method.with_backend
+#[derive(Debug, Clone)]
+pub enum Backend {
+    /// Accesses the GPIO registers through `/dev/gpiomem` or `/dev/mem`, and configures
+    /// interrupts through the `gpiochip` character device. This is the default
+    /// backend used by [`Gpio::new`].
+    ///
+    /// [`Gpio::new`]: struct.Gpio.html#method.new
+    Native,
+    /// Accesses the GPIO pins exclusively through the `gpiochip` character device,
+    /// without requiring access to `/dev/gpiomem` or `/dev/mem`.
+    ///
+    /// More information can be found [here].
+    ///
+    /// [here]: index.html#character-device-only-mode
+    Cdev,
+    /// Accesses an in-memory simulation of the GPIO peripheral.
+    Simulated(Simulator),
+}
+
 // Store Gpio's state separately, so we can conveniently share it through
 // a cloned Arc.
 pub(crate) struct GpioState {
-    gpio_mem: mem::GpioMem,
-    cdev: std::fs::File,
+    backend: Arc<dyn GpioBackend>,
     sync_interrupts: Mutex<interrupt::EventLoop>,
     pins_taken: [AtomicBool; pin::MAX],
+    conflict_detection: AtomicBool,
+    async_interrupts: Mutex<Weak<interrupt::Dispatcher>>,
+    callback_executor: Arc<Mutex<Option<interrupt::Executor>>>,
+    #[cfg(feature = "async")]
+    reactor: Mutex<Weak<stream::Reactor>>,
+}
+
+impl GpioState {
+    fn new(backend: Arc<dyn GpioBackend>) -> Result<GpioState> {
+        let sync_interrupts = Mutex::new(interrupt::EventLoop::new(backend.clone(), pin::MAX)?);
+        let pins_taken = init_array!(AtomicBool::new(false), pin::MAX);
+
+        Ok(GpioState {
+            backend,
+            sync_interrupts,
+            pins_taken,
+            conflict_detection: AtomicBool::new(false),
+            async_interrupts: Mutex::new(Weak::new()),
+            callback_executor: Arc::new(Mutex::new(None)),
+            #[cfg(feature = "async")]
+            reactor: Mutex::new(Weak::new()),
+        })
+    }
+
+    // Returns the dispatcher shared by all asynchronous interrupt triggers, which
+    // is started when the first trigger is configured, and stopped after the last
+    // one is removed.
+    fn async_interrupts(&self) -> Result<Arc<interrupt::Dispatcher>> {
+        let mut weak_dispatcher = self.async_interrupts.lock().unwrap();
+
+        if let Some(dispatcher) = weak_dispatcher.upgrade() {
+            return Ok(dispatcher);
+        }
+
+        let dispatcher = Arc::new(interrupt::Dispatcher::new(self.callback_executor.clone())?);
+        *weak_dispatcher = Arc::downgrade(&dispatcher);
+
+        Ok(dispatcher)
+    }
+
+    // Returns the reactor shared by all EventStreams, which is started when
+    // the first stream is created, and stopped after the last one is dropped.
+    #[cfg(feature = "async")]
+    fn reactor(&self) -> Result<Arc<stream::Reactor>> {
+        let mut weak_reactor = self.reactor.lock().unwrap();
+
+        if let Some(reactor) = weak_reactor.upgrade() {
+            return Ok(reactor);
+        }
+
+        let reactor = Arc::new(stream::Reactor::new()?);
+        *weak_reactor = Arc::downgrade(&reactor);
+
+        Ok(reactor)
+    }
 }
 
 impl fmt::Debug for GpioState {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("EventLoop")
-            .field("gpio_mem", &self.gpio_mem)
-            .field("cdev", &self.cdev)
+        f.debug_struct("GpioState")
+            .field("backend", &self.backend)
             .field("sync_interrupts", &self.sync_interrupts)
             .field("pins_taken", &format_args!("{{ .. }}"))
+            .field("conflict_detection", &self.conflict_detection)
+            .field("async_interrupts", &self.async_interrupts)
+            .field("callback_executor", &format_args!("{{ .. }}"))
             .finish()
     }
 }
@@ -357,8 +925,53 @@ pub struct Gpio {
 
 impl Gpio {
     /// Constructs a new `Gpio`.
+    ///
+    /// `new` uses the [`Native`] backend, which directly accesses the GPIO registers.
+    ///
+    /// [`Native`]: enum.Backend.html#variant.Native
     pub fn new() -> Result<Gpio> {
-        let mut static_state = GPIO_STATE.lock().unwrap();
+        Gpio::with_backend(Backend::Native)
+    }
+
+    /// Constructs a new `Gpio` that accesses the GPIO pins exclusively through the
+    /// `gpiochip` character device.
+    ///
+    /// `with_cdev_only` is a convenience method that calls [`with_backend`] with the
+    /// [`Cdev`] backend. More information can be found [here].
+    ///
+    /// [`with_backend`]: #method.with_backend
+    /// [`Cdev`]: enum.Backend.html#variant.Cdev
+    /// [here]: index.html#character-device-only-mode
+    pub fn with_cdev_only() -> Result<Gpio> {
+        Gpio::with_backend(Backend::Cdev)
+    }
+
+    /// Constructs a new `Gpio` using the specified backend.
+    ///
+    /// The [`Native`] and [`Cdev`] backends both access the Raspberry Pi's GPIO peripheral,
+    /// and share the same state. If a `Gpio` instance using either of these backends
+    /// already exists, the existing backend is used. All instances constructed with
+    /// the same [`Simulator`] share the simulator's state.
+    ///
+    /// [`Native`]: enum.Backend.html#variant.Native
+    /// [`Cdev`]: enum.Backend.html#variant.Cdev
+    /// [`Simulator`]: struct.Simulator.html
+    pub fn with_backend(backend: Backend) -> Result<Gpio> {
+        match backend {
+            Backend::Native => Gpio::shared(&GPIO_STATE, || Ok(Arc::new(backend::Native::open()?))),
+            Backend::Cdev => Gpio::shared(&GPIO_STATE, || Ok(Arc::new(backend::Cdev::open()?))),
+            Backend::Simulated(simulator) => {
+                let sim_backend = simulator.inner.clone();
+                Gpio::shared(&simulator.inner.gpio_state, || Ok(sim_backend))
+            }
+        }
+    }
+
+    fn shared<F>(state: &Mutex<Weak<GpioState>>, open_backend: F) -> Result<Gpio>
+    where
+        F: FnOnce() -> Result<Arc<dyn GpioBackend>>,
+    {
+        let mut static_state = state.lock().unwrap();
 
         // Clone a strong reference if a GpioState instance already exists, otherwise
         // initialize it here so we can return any relevant errors.
@@ -367,18 +980,7 @@ impl Gpio {
                 inner: state.clone(),
             })
         } else {
-            let gpio_mem = mem::GpioMem::open()?;
-            let cdev = ioctl::find_gpiochip()?;
-            let sync_interrupts =
-                Mutex::new(interrupt::EventLoop::new(cdev.as_raw_fd(), pin::MAX)?);
-            let pins_taken = init_array!(AtomicBool::new(false), pin::MAX);
-
-            let gpio_state = Arc::new(GpioState {
-                gpio_mem,
-                cdev,
-                sync_interrupts,
-                pins_taken,
-            });
+            let gpio_state = Arc::new(GpioState::new(open_backend()?)?);
 
             // Store a weak reference to our state. This gets dropped when
             // all Gpio and Pin instances go out of scope.
@@ -396,11 +998,24 @@ impl Gpio {
     /// (or a derived [`InputPin`], [`OutputPin`] or [`IoPin`]) goes out of scope, it
     /// can be retrieved again through another `get` call.
     ///
+    /// If conflict detection is enabled through [`set_conflict_detection`], `get` also
+    /// verifies the pin isn't requested by another process or kernel driver, and isn't
+    /// configured for one of its alternate functions, and returns
+    /// `Err(`[`Error::PinInUse`]`)` if it is.
+    ///
+    /// With the [`Cdev`] backend, `get` requests the line through the `gpiochip` character
+    /// device, which keeps it reserved until the [`Pin`] goes out of scope. If the line is
+    /// already requested by another process, `get` returns `Err(`[`Error::PinInUse`]`)`
+    /// regardless of conflict detection.
+    ///
     /// [`Pin`]: struct.Pin.html
+    /// [`Cdev`]: enum.Backend.html#variant.Cdev
     /// [`InputPin`]: struct.InputPin.html
     /// [`OutputPin`]: struct.OutputPin.html
     /// [`IoPin`]: struct.IoPin.html
     /// [`Error::PinNotAvailable`]: enum.Error.html#variant.PinNotAvailable
+    /// [`set_conflict_detection`]: #method.set_conflict_detection
+    /// [`Error::PinInUse`]: enum.Error.html#variant.PinInUse
     pub fn get(&self, pin: u8) -> Result<Pin> {
         if pin as usize >= pin::MAX {
             return Err(Error::PinNotAvailable(pin));
@@ -409,13 +1024,169 @@ impl Gpio {
         // Returns true if the pin is already taken, otherwise atomically sets it to true here
         if self.inner.pins_taken[pin as usize].compare_and_swap(false, true, Ordering::SeqCst) {
             // Pin is taken
-            Err(Error::PinNotAvailable(pin))
+            return Err(Error::PinNotAvailable(pin));
+        }
+
+        let claimed = if self.inner.conflict_detection.load(Ordering::SeqCst) {
+            self.check_conflicts(pin)
         } else {
-            // Return an owned Pin
-            Ok(Pin::new(pin, self.inner.clone()))
+            Ok(())
+        }
+        .and_then(|_| self.inner.backend.claim(pin));
+
+        if let Err(e) = claimed {
+            self.inner.pins_taken[pin as usize].store(false, Ordering::SeqCst);
+
+            return Err(e);
+        }
+
+        // Return an owned Pin
+        Ok(Pin::new(pin, self.inner.clone()))
+    }
+
+    // Checks whether the pin is in use outside of this process, either through
+    // the gpiochip character device or by a peripheral using an alternate function.
+    fn check_conflicts(&self, pin: u8) -> Result<()> {
+        match self.inner.backend.line_info(pin) {
+            Ok(ref line_info) if line_info.used => {
+                return Err(Error::PinInUse {
+                    pin,
+                    consumer: line_info.consumer.clone(),
+                });
+            }
+            Ok(_) | Err(Error::NotSupported) => (),
+            Err(e) => return Err(e),
+        }
+
+        match self.inner.backend.mode(pin) {
+            Mode::Input | Mode::Output => Ok(()),
+            mode => Err(Error::PinInUse {
+                pin,
+                consumer: format!("{}", mode),
+            }),
+        }
+    }
+
+    /// Returns information about the GPIO line associated with the specified BCM GPIO pin
+    /// number, as reported by the `gpiochip` character device.
+    ///
+    /// `line_info` doesn't require the pin to be retrieved through [`get`] first, and
+    /// can be used to find out which process or kernel driver is using a pin.
+    ///
+    /// If the GPIO peripheral doesn't expose a pin with the specified number, `line_info`
+    /// returns `Err(`[`Error::PinNotAvailable`]`)`.
+    ///
+    /// [`get`]: #method.get
+    /// [`Error::PinNotAvailable`]: enum.Error.html#variant.PinNotAvailable
+    pub fn line_info(&self, pin: u8) -> Result<LineInfo> {
+        if pin as usize >= pin::MAX {
+            return Err(Error::PinNotAvailable(pin));
+        }
+
+        self.inner.backend.line_info(pin)
+    }
+
+    /// Returns a [`Pin`] for the GPIO line with the specified name.
+    ///
+    /// Line names are assigned by the device tree, and typically match the
+    /// names used in the board's schematics, such as `GPIO17`, `ID_SDA` or
+    /// `STATUS_LED`. Names are case-sensitive.
+    ///
+    /// If none of the lines has the specified name, `get_by_name` returns
+    /// `Err(`[`Error::LineNotFound`]`)`. Otherwise, it behaves the same as [`get`].
+    ///
+    /// [`Pin`]: struct.Pin.html
+    /// [`Error::LineNotFound`]: enum.Error.html#variant.LineNotFound
+    /// [`get`]: #method.get
+    pub fn get_by_name(&self, name: &str) -> Result<Pin> {
+        self.get(self.find_line(name, pin::MAX as u32)?)
+    }
+
+    /// Returns a [`Pin`] for the specified physical pin number on the GPIO header.
+    ///
+    /// The physical pin number is translated to a BCM GPIO pin number through
+    /// [`Header`], based on the Raspberry Pi model the code is running on. Returns
+    /// `Err(`[`Error::UnknownModel`]`)` if the model can't be identified. Otherwise,
+    /// it behaves the same as [`get_physical_with`].
+    ///
+    /// [`Pin`]: struct.Pin.html
+    /// [`Header`]: header/struct.Header.html
+    /// [`Error::UnknownModel`]: enum.Error.html#variant.UnknownModel
+    /// [`get_physical_with`]: #method.get_physical_with
+    pub fn get_physical(&self, pin: u8) -> Result<Pin> {
+        self.get_physical_with(&header::Header::new()?, pin)
+    }
+
+    /// Returns a [`Pin`] for the specified physical pin number on the GPIO header
+    /// described by `header`.
+    ///
+    /// If the physical pin doesn't exist or isn't a GPIO pin, `get_physical_with`
+    /// returns `Err(`[`Error::PhysicalPinNotAvailable`]`)`. Otherwise, it behaves the
+    /// same as [`get`].
+    ///
+    /// [`Pin`]: struct.Pin.html
+    /// [`Error::PhysicalPinNotAvailable`]: enum.Error.html#variant.PhysicalPinNotAvailable
+    /// [`get`]: #method.get
+    pub fn get_physical_with(&self, header: &header::Header, pin: u8) -> Result<Pin> {
+        match header.physical_to_bcm(pin) {
+            Some(bcm_pin) => self.get(bcm_pin),
+            None => Err(Error::PhysicalPinNotAvailable(pin)),
+        }
+    }
+
+    /// Returns information about all GPIO lines, ordered by BCM GPIO pin number.
+    ///
+    /// `lines` is equivalent to calling [`line_info`] for each pin.
+    ///
+    /// [`line_info`]: #method.line_info
+    pub fn lines(&self) -> Result<Vec<LineInfo>> {
+        self.lines_up_to(pin::MAX as u32)
+    }
+
+    fn lines_up_to(&self, count: u32) -> Result<Vec<LineInfo>> {
+        (0..count.min(pin::MAX as u32))
+            .map(|pin| self.inner.backend.line_info(pin as u8))
+            .collect()
+    }
+
+    // Returns the pin number of the first line with the specified name
+    fn find_line(&self, name: &str, count: u32) -> Result<u8> {
+        match self
+            .lines_up_to(count)?
+            .into_iter()
+            .find(|line_info| line_info.name == name)
+        {
+            Some(line_info) => Ok(line_info.pin),
+            None => Err(Error::LineNotFound(name.to_owned())),
         }
     }
 
+    /// Enables or disables conflict detection for [`get`].
+    ///
+    /// When conflict detection is enabled, [`get`] returns `Err(`[`Error::PinInUse`]`)`
+    /// if the requested pin is in use by another process or kernel driver, or if it's
+    /// configured for one of its alternate functions, instead of silently
+    /// reconfiguring the pin.
+    ///
+    /// The setting is shared by all `Gpio` instances using the same backend.
+    ///
+    /// By default, conflict detection is disabled.
+    ///
+    /// [`get`]: #method.get
+    /// [`Error::PinInUse`]: enum.Error.html#variant.PinInUse
+    pub fn set_conflict_detection(&self, conflict_detection: bool) {
+        self.inner
+            .conflict_detection
+            .store(conflict_detection, Ordering::SeqCst);
+    }
+
+    /// Returns `true` if conflict detection is enabled for [`get`].
+    ///
+    /// [`get`]: #method.get
+    pub fn conflict_detection(&self) -> bool {
+        self.inner.conflict_detection.load(Ordering::SeqCst)
+    }
+
     /// Blocks until an interrupt is triggered on any of the specified pins, or until a timeout occurs.
     ///
     /// Only pins that have been previously configured for synchronous interrupts using [`InputPin::set_interrupt`]
@@ -448,6 +1219,127 @@ impl Gpio {
         reset: bool,
         timeout: Option<Duration>,
     ) -> Result<Option<(&'a InputPin, Level)>> {
+        let opt = (*self.inner.sync_interrupts.lock().unwrap()).poll(pins, reset, timeout)?;
+
+        Ok(opt.map(|(pin, event)| (pin, event.level())))
+    }
+
+    /// Blocks until an interrupt is triggered on any of the specified pins, or until a timeout occurs.
+    ///
+    /// `poll_events` behaves the same as [`poll_interrupts`], but returns an [`Event`] containing
+    /// the trigger's timestamp and sequence number instead of the pin's logic level.
+    ///
+    /// Both methods share the same cached events, which means an event that's returned by one
+    /// won't be returned again by the other.
+    ///
+    /// [`poll_interrupts`]: #method.poll_interrupts
+    /// [`Event`]: struct.Event.html
+    pub fn poll_events<'a>(
+        &self,
+        pins: &[&'a InputPin],
+        reset: bool,
+        timeout: Option<Duration>,
+    ) -> Result<Option<(&'a InputPin, Event)>> {
         (*self.inner.sync_interrupts.lock().unwrap()).poll(pins, reset, timeout)
     }
+
+    /// Reads all queued interrupt trigger events for the specified pins, without blocking.
+    ///
+    /// Only pins that have been previously configured for synchronous interrupts using
+    /// [`InputPin::set_interrupt`] can be read.
+    ///
+    /// Unlike [`poll_events`], which returns a single event, `read_events` drains as many
+    /// queued events as fit in `events`, which makes it suitable for counting edges on
+    /// high-rate signals. Events are stored grouped by pin, in the order the pins are
+    /// specified, and chronologically within each pin. Any event cached by a previous call
+    /// to [`poll_events`] or [`poll_interrupts`] is included.
+    ///
+    /// The returned [`EventsRead`] contains the number of events that were stored, the number
+    /// of events the kernel dropped because its event queue overflowed, and whether more
+    /// events are waiting that didn't fit in `events`.
+    ///
+    /// [`InputPin::set_interrupt`]: struct.InputPin.html#method.set_interrupt
+    /// [`poll_events`]: #method.poll_events
+    /// [`poll_interrupts`]: #method.poll_interrupts
+    /// [`EventsRead`]: struct.EventsRead.html
+    pub fn read_events(&self, pins: &[&InputPin], events: &mut [Event]) -> Result<EventsRead> {
+        (*self.inner.sync_interrupts.lock().unwrap()).read_events(pins, events)
+    }
+
+    /// Configures an executor for asynchronous interrupt callbacks.
+    ///
+    /// By default, the callbacks for all asynchronous interrupt triggers configured through
+    /// [`InputPin::set_async_interrupt`] and [`InputPin::set_async_event_interrupt`] are executed
+    /// on a single dispatcher thread, which means a slow callback delays any other callbacks.
+    ///
+    /// When an executor is configured, the dispatcher thread passes each callback invocation
+    /// to `executor` as a boxed closure instead, which can submit it to a thread pool. Callbacks
+    /// for the same pin never run concurrently, but might execute out of order if the thread
+    /// pool uses multiple threads.
+    ///
+    /// The executor is shared by all `Gpio` instances that share the same state.
+    ///
+    /// ```
+    /// use std::thread;
+    ///
+    /// use rppal::gpio::{Backend, Gpio, Simulator, Trigger};
+    ///
+    /// # fn main() -> rppal::gpio::Result<()> {
+    /// let gpio = Gpio::with_backend(Backend::Simulated(Simulator::new()))?;
+    ///
+    /// // Execute each callback on a new thread. A thread pool is more efficient.
+    /// gpio.set_callback_executor(|callback| {
+    ///     thread::spawn(callback);
+    /// });
+    ///
+    /// let mut pin = gpio.get(23)?.into_input();
+    /// pin.set_async_interrupt(Trigger::RisingEdge, |level| println!("{}", level))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`InputPin::set_async_interrupt`]: struct.InputPin.html#method.set_async_interrupt
+    /// [`InputPin::set_async_event_interrupt`]: struct.InputPin.html#method.set_async_event_interrupt
+    pub fn set_callback_executor<E>(&self, executor: E)
+    where
+        E: Fn(Box<dyn FnOnce() + Send>) + Send + Sync + 'static,
+    {
+        *self.inner.callback_executor.lock().unwrap() = Some(Arc::new(executor));
+    }
+
+    /// Removes a previously configured executor for asynchronous interrupt callbacks.
+    ///
+    /// Any subsequent callbacks are executed on the dispatcher thread.
+    pub fn clear_callback_executor(&self) {
+        *self.inner.callback_executor.lock().unwrap() = None;
+    }
+
+    /// Returns the pad configuration for the specified bank.
+    ///
+    /// The pad control registers can only be accessed through `/dev/mem`, which
+    /// requires superuser privileges. If `/dev/mem` can't be opened, `pad_config` returns
+    /// `Err(`[`Error::PermissionDenied`]`)`.
+    ///
+    /// More information can be found [here].
+    ///
+    /// [`Error::PermissionDenied`]: enum.Error.html#variant.PermissionDenied
+    /// [here]: index.html#pad-configuration
+    pub fn pad_config(&self, bank: Bank) -> Result<PadConfig> {
+        self.inner.backend.pad_config(bank)
+    }
+
+    /// Changes the pad configuration for the specified bank.
+    ///
+    /// The new configuration applies to all pins within the bank, including pins
+    /// that are in use by other processes or peripherals, and persists until it's
+    /// changed again or the Raspberry Pi is rebooted.
+    ///
+    /// The pad control registers can only be accessed through `/dev/mem`, which
+    /// requires superuser privileges. If `/dev/mem` can't be opened, `set_pad_config`
+    /// returns `Err(`[`Error::PermissionDenied`]`)`.
+    ///
+    /// [`Error::PermissionDenied`]: enum.Error.html#variant.PermissionDenied
+    pub fn set_pad_config(&self, bank: Bank, config: PadConfig) -> Result<()> {
+        self.inner.backend.set_pad_config(bank, config)
+    }
 }
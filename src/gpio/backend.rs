@@ -0,0 +1,778 @@
+// Copyright (c) 2017-2019 Rene van der Meer
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL
+// THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use std::fmt;
+use std::fs::File;
+use std::os::unix::io::AsRawFd;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::gpio::ioctl;
+use crate::gpio::mem::GpioMem;
+use crate::gpio::pads::PadsMem;
+use crate::gpio::{
+    pin, Bank, Detect, Drive, Error, Level, LineInfo, Mode, PadConfig, PullUpDown, Result, Trigger,
+};
+
+// Provides register-level access to the GPIO pins, and creates event fds for
+// interrupt triggers. All Pin instances access the hardware (or a simulation of it)
+// through the GpioBackend stored in GpioState.
+pub(crate) trait GpioBackend: fmt::Debug + Send + Sync {
+    fn mode(&self, pin: u8) -> Mode;
+    fn set_mode(&self, pin: u8, mode: Mode);
+    fn level(&self, pin: u8) -> Level;
+    fn set_high(&self, pin: u8);
+    fn set_low(&self, pin: u8);
+    // Returns the levels of the pins in mask as a bitmask, where bit n
+    // corresponds to GPIO n.
+    fn levels(&self, mask: u64) -> u64 {
+        (0..pin::MAX as u8)
+            .filter(|pin| mask & (1 << pin) > 0)
+            .filter(|&pin| self.level(pin) == Level::High)
+            .fold(0, |levels, pin| levels | (1 << pin))
+    }
+    // Sets the pins in mask to the corresponding bits in levels, using the
+    // least amount of register writes possible.
+    fn set_levels(&self, mask: u64, levels: u64) {
+        for pin in (0..pin::MAX as u8).filter(|pin| mask & (1 << pin) > 0) {
+            if levels & (1 << pin) > 0 {
+                self.set_high(pin);
+            } else {
+                self.set_low(pin);
+            }
+        }
+    }
+    // Sets the pin's output state for the specified drive mode. Open-drain and
+    // open-source outputs are emulated by switching the pin to Output mode while
+    // it actively drives the line, and back to Input mode to release the line.
+    //
+    // The BCM283x/BCM2711 GPIO block has no open-drain or open-source output
+    // drivers. When a line is requested with GPIOHANDLE_REQUEST_OPEN_DRAIN or
+    // GPIOHANDLE_REQUEST_OPEN_SOURCE, gpiolib falls back to the same direction
+    // switching, so the Native backend doesn't request a line handle for these
+    // modes. That would also claim the line through the character device, which
+    // Native doesn't do for any other register-level operation.
+    fn write(&self, pin: u8, level: Level, drive: Drive) {
+        match (drive, level) {
+            (Drive::PushPull, Level::High) => self.set_high(pin),
+            (Drive::PushPull, Level::Low) => self.set_low(pin),
+            (Drive::OpenDrain, Level::Low) => {
+                // Set the output latch first, so the line doesn't glitch
+                self.set_low(pin);
+                self.set_mode(pin, Mode::Output);
+            }
+            (Drive::OpenSource, Level::High) => {
+                self.set_high(pin);
+                self.set_mode(pin, Mode::Output);
+            }
+            _ => self.set_mode(pin, Mode::Input),
+        }
+    }
+    // Returns the output state last set through write.
+    fn output(&self, pin: u8, drive: Drive) -> Level {
+        match drive {
+            Drive::PushPull => self.level(pin),
+            Drive::OpenDrain if self.mode(pin) == Mode::Output => Level::Low,
+            Drive::OpenSource if self.mode(pin) != Mode::Output => Level::Low,
+            _ => Level::High,
+        }
+    }
+    fn set_pullupdown(&self, pin: u8, pud: PullUpDown);
+    // Returns None if the resistor state can't be read back.
+    fn pullupdown(&self, pin: u8) -> Option<PullUpDown>;
+    // Returns an event source that produces an event whenever the specified
+    // trigger condition occurs. Backends that can't debounce the input in the
+    // kernel ignore the debounce period, which is reflected by
+    // EventSource::debounced().
+    fn event_request(
+        &self,
+        pin: u8,
+        trigger: Trigger,
+        debounce: Option<Duration>,
+    ) -> Result<ioctl::EventSource>;
+    // Configures the edge detect registers, which can only be accessed by
+    // backends with direct register access.
+    fn set_edge_detect(&self, _pin: u8, _detect: Detect) -> Result<()> {
+        Err(Error::NotSupported)
+    }
+    // Returns true if an edge was detected since the last call, and clears the event.
+    fn take_edge_detected(&self, _pin: u8) -> bool {
+        false
+    }
+    // Returns the line's status as reported by the gpiochip character device.
+    fn line_info(&self, _pin: u8) -> Result<LineInfo> {
+        Err(Error::NotSupported)
+    }
+    // Called when a Pin is retrieved, before it's returned to the caller. Backends
+    // that need exclusive access to the line request it here, so any errors can
+    // be reported.
+    fn claim(&self, _pin: u8) -> Result<()> {
+        Ok(())
+    }
+    // Called when pins are combined into a port. Backends that access the lines
+    // through a request combine them into a single request here, so they can be
+    // accessed simultaneously through levels and set_levels.
+    fn combine(&self, _mask: u64) -> Result<()> {
+        Ok(())
+    }
+    // Called when a port is dropped or split up into its individual pins.
+    fn split(&self, _mask: u64) {}
+    // Called when a Pin goes out of scope, after its mode has been reset.
+    fn release(&self, _pin: u8) {}
+    // Returns true if the GPSET0/GPCLR0 registers can be written by the DMA
+    // controller, which requires the pins to be controlled through the native
+    // GPIO peripheral.
+    fn supports_dma(&self) -> bool {
+        false
+    }
+    // The pad control registers are only accessible through /dev/mem, so they're
+    // mapped on demand rather than when the backend is opened.
+    fn pad_config(&self, bank: Bank) -> Result<PadConfig> {
+        Ok(PadsMem::open()?.config(bank))
+    }
+    fn set_pad_config(&self, bank: Bank, config: PadConfig) -> Result<()> {
+        PadsMem::open()?.set_config(bank, config);
+
+        Ok(())
+    }
+}
+
+// Accesses the GPIO registers through /dev/gpiomem or /dev/mem, and requests
+// interrupt events through the gpiochip character device.
+#[derive(Debug)]
+pub(crate) struct Native {
+    gpio_mem: GpioMem,
+    cdev: File,
+    abi: ioctl::Abi,
+}
+
+impl Native {
+    pub(crate) fn open() -> Result<Native> {
+        let gpio_mem = GpioMem::open()?;
+        let cdev = ioctl::find_gpiochip()?;
+        let abi = ioctl::Abi::detect(cdev.as_raw_fd());
+
+        Ok(Native {
+            gpio_mem,
+            cdev,
+            abi,
+        })
+    }
+}
+
+impl GpioBackend for Native {
+    #[inline(always)]
+    fn mode(&self, pin: u8) -> Mode {
+        self.gpio_mem.mode(pin)
+    }
+
+    #[inline(always)]
+    fn set_mode(&self, pin: u8, mode: Mode) {
+        self.gpio_mem.set_mode(pin, mode)
+    }
+
+    #[inline(always)]
+    fn level(&self, pin: u8) -> Level {
+        self.gpio_mem.level(pin)
+    }
+
+    #[inline(always)]
+    fn set_high(&self, pin: u8) {
+        self.gpio_mem.set_high(pin)
+    }
+
+    #[inline(always)]
+    fn set_low(&self, pin: u8) {
+        self.gpio_mem.set_low(pin)
+    }
+
+    #[inline(always)]
+    fn levels(&self, mask: u64) -> u64 {
+        self.gpio_mem.levels() & mask
+    }
+
+    #[inline(always)]
+    fn set_levels(&self, mask: u64, levels: u64) {
+        self.gpio_mem.set_levels(mask, levels)
+    }
+
+    fn set_pullupdown(&self, pin: u8, pud: PullUpDown) {
+        self.gpio_mem.set_pullupdown(pin, pud)
+    }
+
+    fn pullupdown(&self, pin: u8) -> Option<PullUpDown> {
+        self.gpio_mem.pullupdown(pin)
+    }
+
+    fn event_request(
+        &self,
+        pin: u8,
+        trigger: Trigger,
+        debounce: Option<Duration>,
+    ) -> Result<ioctl::EventSource> {
+        let mut settings = ioctl::LineSettings::new(ioctl::Direction::Input);
+        settings.trigger = trigger;
+        settings.debounce = debounce;
+
+        ioctl::EventSource::new(self.cdev.as_raw_fd(), self.abi, pin, &settings)
+    }
+
+    fn set_edge_detect(&self, pin: u8, detect: Detect) -> Result<()> {
+        self.gpio_mem.set_edge_detect(pin, detect);
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn take_edge_detected(&self, pin: u8) -> bool {
+        self.gpio_mem.take_edge_detected(pin)
+    }
+
+    fn line_info(&self, pin: u8) -> Result<LineInfo> {
+        Ok(ioctl::LineInfo::for_pin(self.cdev.as_raw_fd(), self.abi, pin)?.info(pin))
+    }
+
+    fn supports_dma(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug)]
+struct CdevLine {
+    settings: ioctl::LineSettings,
+    // Output level that's applied when the line is requested as an output
+    output: Level,
+    // Lines that are combined into a port share a single multi-line handle
+    handle: Option<Arc<ioctl::LineHandle>>,
+    // Position of the line within the handle
+    offset: usize,
+}
+
+impl CdevLine {
+    fn new() -> CdevLine {
+        CdevLine {
+            settings: ioctl::LineSettings::new(ioctl::Direction::AsIs),
+            output: Level::Low,
+            handle: None,
+            offset: 0,
+        }
+    }
+
+    fn is_combined(&self) -> bool {
+        match self.handle {
+            Some(ref handle) => handle.lines() > 1,
+            None => false,
+        }
+    }
+
+    fn shares_handle(&self, handle: &Arc<ioctl::LineHandle>) -> bool {
+        match self.handle {
+            Some(ref line_handle) => Arc::ptr_eq(line_handle, handle),
+            None => false,
+        }
+    }
+
+    fn level(&self) -> Option<Level> {
+        let levels = self.handle.as_ref()?.levels().ok()?;
+
+        if levels & (1 << self.offset) > 0 {
+            Some(Level::High)
+        } else {
+            Some(Level::Low)
+        }
+    }
+
+    // Sets the output latch, and drives the line if it's requested as an output.
+    // Setting the level of a requested output line only fails if the request is
+    // no longer valid, in which case the latched level is applied the next time
+    // the line is configured.
+    fn set_output(&mut self, level: Level) {
+        self.output = level;
+
+        if let Some(ref handle) = self.handle {
+            if self.settings.direction == ioctl::Direction::Output {
+                handle
+                    .set_levels(1 << self.offset, (level as u64) << self.offset)
+                    .ok();
+            }
+        }
+    }
+}
+
+// Returns the handle shared by all lines in mask, if they're combined into a
+// single multi-line handle.
+fn shared_handle(lines: &[CdevLine], mask: u64) -> Option<Arc<ioctl::LineHandle>> {
+    let mut pins = (0..pin::MAX).filter(|pin| mask & (1 << pin) > 0);
+    let handle = lines[pins.next()?].handle.clone()?;
+
+    if pins.all(|pin| lines[pin].shares_handle(&handle)) {
+        Some(handle)
+    } else {
+        None
+    }
+}
+
+// Accesses the GPIO pins exclusively through the gpiochip character device, for
+// systems where /dev/gpiomem and /dev/mem aren't accessible. Lines are requested
+// as-is when a pin is retrieved, which reports any conflicts with other processes
+// through Gpio::get, and released when the pin goes out of scope. Changes to a
+// pin's mode or pull-up/pull-down resistors reconfigure the line in place. The
+// character device can't select any of the alternate functions, so those modes
+// are ignored.
+//
+// Pins that are combined into a port are requested again as a single multi-line
+// handle, so they can be read or written with a single ioctl. Reconfiguring one
+// of the pins splits the handle up again.
+//
+// The GpioBackend trait doesn't return errors for register-level operations. If
+// the kernel rejects a new configuration, the line keeps its previous settings,
+// which is reflected by mode() and pullupdown().
+#[derive(Debug)]
+pub(crate) struct Cdev {
+    cdev: File,
+    abi: ioctl::Abi,
+    lines: Mutex<Vec<CdevLine>>,
+}
+
+impl Cdev {
+    pub(crate) fn open() -> Result<Cdev> {
+        Ok(Cdev::new(ioctl::find_gpiochip()?))
+    }
+
+    pub(crate) fn new(cdev: File) -> Cdev {
+        let abi = ioctl::Abi::detect(cdev.as_raw_fd());
+
+        let mut lines = Vec::with_capacity(pin::MAX);
+        for _ in 0..pin::MAX {
+            lines.push(CdevLine::new());
+        }
+
+        Cdev {
+            cdev,
+            abi,
+            lines: Mutex::new(lines),
+        }
+    }
+
+    fn query_line_info(&self, pin: u8) -> Result<ioctl::LineInfo> {
+        ioctl::LineInfo::for_pin(self.cdev.as_raw_fd(), self.abi, pin)
+    }
+
+    fn request(
+        &self,
+        pin: u8,
+        settings: &ioctl::LineSettings,
+        output: Level,
+    ) -> Result<Arc<ioctl::LineHandle>> {
+        Ok(Arc::new(ioctl::LineHandle::new(
+            self.cdev.as_raw_fd(),
+            self.abi,
+            &[pin],
+            settings,
+            output as u64,
+        )?))
+    }
+
+    // Requests each line that shares a multi-line handle with the specified
+    // line separately again. Lines that can't be requested are left unclaimed.
+    fn split_line(&self, lines: &mut [CdevLine], pin: u8) {
+        let handle = match lines[pin as usize].handle.clone() {
+            Some(ref handle) if handle.lines() > 1 => handle.clone(),
+            _ => return,
+        };
+
+        let pins: Vec<usize> = (0..lines.len())
+            .filter(|&pin| lines[pin].shares_handle(&handle))
+            .collect();
+
+        // Release the multi-line handle before requesting the lines again
+        drop(handle);
+        for &pin in &pins {
+            lines[pin].handle = None;
+        }
+
+        for &pin in &pins {
+            let line = &mut lines[pin];
+
+            line.handle = self.request(pin as u8, &line.settings, line.output).ok();
+            line.offset = 0;
+        }
+    }
+
+    // Applies the new settings to the line. The line is reconfigured without
+    // releasing it if the kernel supports it (Linux 5.5 or later), otherwise it's
+    // released and requested again. On failure, the previous settings are restored.
+    fn configure(
+        &self,
+        lines: &mut [CdevLine],
+        pin: u8,
+        settings: ioctl::LineSettings,
+    ) -> Result<()> {
+        self.split_line(lines, pin);
+
+        let line = &mut lines[pin as usize];
+
+        if let Some(ref handle) = line.handle {
+            if handle.set_config(&settings, line.output as u64).is_ok() {
+                line.settings = settings;
+
+                return Ok(());
+            }
+        }
+
+        // Release the line before requesting it again
+        line.handle = None;
+        match self.request(pin, &settings, line.output) {
+            Ok(handle) => {
+                line.handle = Some(handle);
+                line.settings = settings;
+
+                Ok(())
+            }
+            Err(e) => {
+                line.handle = self.request(pin, &line.settings, line.output).ok();
+
+                Err(e)
+            }
+        }
+    }
+}
+
+impl GpioBackend for Cdev {
+    fn mode(&self, pin: u8) -> Mode {
+        let direction = self.lines.lock().unwrap()[pin as usize].settings.direction;
+
+        match direction {
+            ioctl::Direction::Input => Mode::Input,
+            ioctl::Direction::Output => Mode::Output,
+            ioctl::Direction::AsIs => match self.query_line_info(pin) {
+                Ok(ref line_info) if line_info.is_output() => Mode::Output,
+                _ => Mode::Input,
+            },
+        }
+    }
+
+    fn set_mode(&self, pin: u8, mode: Mode) {
+        let direction = match mode {
+            Mode::Input => ioctl::Direction::Input,
+            Mode::Output => ioctl::Direction::Output,
+            _ => return,
+        };
+
+        let lines = &mut self.lines.lock().unwrap();
+        let mut settings = lines[pin as usize].settings;
+        settings.direction = direction;
+
+        // On failure, the line keeps its previous direction
+        self.configure(lines, pin, settings).ok();
+    }
+
+    fn level(&self, pin: u8) -> Level {
+        let line = &self.lines.lock().unwrap()[pin as usize];
+
+        if line.handle.is_some() {
+            return line.level().unwrap_or(Level::Low);
+        }
+
+        // Lines that aren't claimed by a Pin are temporarily requested without
+        // changing their direction
+        let levels = self
+            .request(
+                pin,
+                &ioctl::LineSettings::new(ioctl::Direction::AsIs),
+                Level::Low,
+            )
+            .and_then(|handle| handle.levels());
+
+        match levels {
+            Ok(levels) if levels & 1 > 0 => Level::High,
+            _ => Level::Low,
+        }
+    }
+
+    fn set_high(&self, pin: u8) {
+        self.lines.lock().unwrap()[pin as usize].set_output(Level::High);
+    }
+
+    fn set_low(&self, pin: u8) {
+        self.lines.lock().unwrap()[pin as usize].set_output(Level::Low);
+    }
+
+    // Combined lines are read with a single ioctl
+    fn levels(&self, mask: u64) -> u64 {
+        let lines = self.lines.lock().unwrap();
+
+        let handle_levels = shared_handle(&lines, mask).and_then(|handle| handle.levels().ok());
+        if let Some(handle_levels) = handle_levels {
+            return (0..pin::MAX)
+                .filter(|pin| mask & (1 << pin) > 0)
+                .filter(|&pin| handle_levels & (1 << lines[pin].offset) > 0)
+                .fold(0, |levels, pin| levels | (1 << pin));
+        }
+
+        (0..pin::MAX)
+            .filter(|pin| mask & (1 << pin) > 0)
+            .filter(|&pin| lines[pin].level() == Some(Level::High))
+            .fold(0, |levels, pin| levels | (1 << pin))
+    }
+
+    // Combined output lines are written with a single ioctl
+    fn set_levels(&self, mask: u64, levels: u64) {
+        let mut lines = self.lines.lock().unwrap();
+
+        let pins: Vec<usize> = (0..pin::MAX).filter(|pin| mask & (1 << pin) > 0).collect();
+        let outputs = pins
+            .iter()
+            .all(|&pin| lines[pin].settings.direction == ioctl::Direction::Output);
+
+        match shared_handle(&lines, mask) {
+            Some(ref handle) if outputs => {
+                let mut handle_mask = 0;
+                let mut handle_levels = 0;
+
+                for &pin in &pins {
+                    let line = &mut lines[pin];
+
+                    line.output = if levels & (1 << pin) > 0 {
+                        Level::High
+                    } else {
+                        Level::Low
+                    };
+
+                    handle_mask |= 1 << line.offset;
+                    handle_levels |= (line.output as u64) << line.offset;
+                }
+
+                handle.set_levels(handle_mask, handle_levels).ok();
+            }
+            _ => {
+                for &pin in &pins {
+                    lines[pin].set_output(if levels & (1 << pin) > 0 {
+                        Level::High
+                    } else {
+                        Level::Low
+                    });
+                }
+            }
+        }
+    }
+
+    // The character device supports open-drain and open-source outputs natively
+    fn write(&self, pin: u8, level: Level, drive: Drive) {
+        if drive == Drive::PushPull {
+            match level {
+                Level::High => self.set_high(pin),
+                Level::Low => self.set_low(pin),
+            }
+
+            return;
+        }
+
+        let lines = &mut self.lines.lock().unwrap();
+        let line = &mut lines[pin as usize];
+
+        if line.handle.is_some()
+            && line.settings.direction == ioctl::Direction::Output
+            && line.settings.drive == drive
+        {
+            line.set_output(level);
+            return;
+        }
+
+        line.output = level;
+        let mut settings = line.settings;
+        settings.direction = ioctl::Direction::Output;
+        settings.drive = drive;
+
+        // On failure, the line keeps its previous direction and drive mode
+        self.configure(lines, pin, settings).ok();
+    }
+
+    fn output(&self, pin: u8, drive: Drive) -> Level {
+        if drive == Drive::PushPull {
+            self.level(pin)
+        } else {
+            self.lines.lock().unwrap()[pin as usize].output
+        }
+    }
+
+    fn set_pullupdown(&self, pin: u8, pud: PullUpDown) {
+        let lines = &mut self.lines.lock().unwrap();
+        let mut settings = lines[pin as usize].settings;
+
+        // Bias can only be configured in combination with a direction
+        if settings.direction == ioctl::Direction::AsIs {
+            settings.direction = match self.query_line_info(pin) {
+                Ok(ref line_info) if line_info.is_output() => ioctl::Direction::Output,
+                _ => ioctl::Direction::Input,
+            };
+        }
+
+        settings.bias = Some(pud);
+
+        // On failure, the line keeps its previous bias, which is reported by pullupdown()
+        self.configure(lines, pin, settings).ok();
+    }
+
+    fn pullupdown(&self, pin: u8) -> Option<PullUpDown> {
+        let bias = self.lines.lock().unwrap()[pin as usize].settings.bias;
+
+        bias.or_else(|| {
+            self.query_line_info(pin)
+                .ok()
+                .and_then(|line_info| line_info.bias())
+        })
+    }
+
+    fn event_request(
+        &self,
+        pin: u8,
+        trigger: Trigger,
+        debounce: Option<Duration>,
+    ) -> Result<ioctl::EventSource> {
+        let lines = &mut self.lines.lock().unwrap();
+        self.split_line(lines, pin);
+
+        let line = &mut lines[pin as usize];
+
+        let mut settings = line.settings;
+        settings.direction = ioctl::Direction::Input;
+        settings.trigger = trigger;
+        settings.debounce = debounce;
+
+        // A line can only be requested once, so we release our own handle, and
+        // read the line's level through the event source instead. If the event
+        // request fails, the line is claimed again with its previous settings.
+        line.handle = None;
+        let event_source =
+            match ioctl::EventSource::new(self.cdev.as_raw_fd(), self.abi, pin, &settings) {
+                Ok(event_source) => event_source,
+                Err(e) => {
+                    line.handle = self.request(pin, &line.settings, line.output).ok();
+
+                    return Err(e);
+                }
+            };
+
+        line.settings.direction = ioctl::Direction::Input;
+        line.handle = Some(Arc::new(ioctl::LineHandle::from_event_source(
+            &event_source,
+        )?));
+
+        Ok(event_source)
+    }
+
+    fn line_info(&self, pin: u8) -> Result<LineInfo> {
+        Ok(self.query_line_info(pin)?.info(pin))
+    }
+
+    fn claim(&self, pin: u8) -> Result<()> {
+        let line = &mut self.lines.lock().unwrap()[pin as usize];
+
+        match self.request(
+            pin,
+            &ioctl::LineSettings::new(ioctl::Direction::AsIs),
+            Level::Low,
+        ) {
+            Ok(handle) => {
+                *line = CdevLine::new();
+                line.handle = Some(handle);
+
+                Ok(())
+            }
+            Err(Error::Io(ref e)) if e.raw_os_error() == Some(libc::EBUSY) => {
+                Err(Error::PinInUse {
+                    pin,
+                    consumer: self
+                        .query_line_info(pin)
+                        .map(|line_info| line_info.info(pin).consumer)
+                        .unwrap_or_default(),
+                })
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // Lines with different settings can't share a handle, in which case they're
+    // left as-is, and accessed one at a time.
+    fn combine(&self, mask: u64) -> Result<()> {
+        let lines = &mut self.lines.lock().unwrap();
+
+        let pins: Vec<u8> = (0..pin::MAX as u8)
+            .filter(|pin| mask & (1 << pin) > 0)
+            .collect();
+        if pins.len() < 2 {
+            return Ok(());
+        }
+
+        let settings = lines[pins[0] as usize].settings;
+        if pins.iter().any(|&pin| {
+            let line = &lines[pin as usize];
+
+            line.settings != settings || line.handle.is_none() || line.is_combined()
+        }) {
+            return Ok(());
+        }
+
+        let levels = pins.iter().enumerate().fold(0, |levels, (idx, &pin)| {
+            levels | ((lines[pin as usize].output as u64) << idx)
+        });
+
+        // Release the individual handles before requesting the lines together
+        for &pin in &pins {
+            lines[pin as usize].handle = None;
+        }
+
+        match ioctl::LineHandle::new(self.cdev.as_raw_fd(), self.abi, &pins, &settings, levels) {
+            Ok(handle) => {
+                let handle = Arc::new(handle);
+                for (idx, &pin) in pins.iter().enumerate() {
+                    lines[pin as usize].handle = Some(handle.clone());
+                    lines[pin as usize].offset = idx;
+                }
+
+                Ok(())
+            }
+            Err(e) => {
+                for &pin in &pins {
+                    let line = &mut lines[pin as usize];
+                    line.handle = self.request(pin, &line.settings, line.output).ok();
+                }
+
+                Err(e)
+            }
+        }
+    }
+
+    fn split(&self, mask: u64) {
+        let lines = &mut self.lines.lock().unwrap();
+
+        for pin in (0..pin::MAX as u8).filter(|pin| mask & (1 << pin) > 0) {
+            self.split_line(lines, pin);
+        }
+    }
+
+    fn release(&self, pin: u8) {
+        let lines = &mut self.lines.lock().unwrap();
+
+        self.split_line(lines, pin);
+        lines[pin as usize] = CdevLine::new();
+    }
+}
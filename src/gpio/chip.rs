@@ -0,0 +1,214 @@
+// Copyright (c) 2017-2019 Rene van der Meer
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL
+// THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use std::collections::HashMap;
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::sync::{Arc, Mutex, Weak};
+
+use lazy_static::lazy_static;
+use libc::ENOENT;
+
+use crate::gpio::backend::Cdev;
+use crate::gpio::{ioctl, Backend, Error, Gpio, GpioState, LineInfo, Pin, Result};
+
+// Share state between Chip instances that access the same gpiochip device, so
+// pins can't be retrieved more than once. The SoC's GPIO pins share their state
+// with Gpio instead.
+lazy_static! {
+    static ref CHIP_STATES: Mutex<HashMap<String, Weak<GpioState>>> = Mutex::new(HashMap::new());
+}
+
+/// Information about a GPIO chip, as reported by the `gpiochip` character device.
+///
+/// `ChipInfo` is returned by [`Chip::list`] and [`Chip::info`].
+///
+/// [`Chip::list`]: struct.Chip.html#method.list
+/// [`Chip::info`]: struct.Chip.html#method.info
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ChipInfo {
+    /// Path to the character device, for instance `/dev/gpiochip0`.
+    pub path: String,
+    /// Chip name, for instance `gpiochip0`.
+    pub name: String,
+    /// Chip label, which identifies the driver. The SoC's GPIO pins are labeled
+    /// `pinctrl-bcm2835`, and the lines controlled by the firmware's GPIO expander
+    /// are labeled `raspberrypi-exp-gpio`.
+    pub label: String,
+    /// Number of GPIO lines.
+    pub lines: u32,
+}
+
+impl ChipInfo {
+    fn from_ioctl(path: &str, chip_info: &ioctl::ChipInfo) -> ChipInfo {
+        ChipInfo {
+            path: path.to_owned(),
+            name: chip_info.name(),
+            label: chip_info.label(),
+            lines: chip_info.lines,
+        }
+    }
+}
+
+/// Provides access to the GPIO lines of any GPIO chip exposed through the `gpiochip`
+/// character device.
+///
+/// Besides the SoC's GPIO pins, the kernel exposes additional GPIO chips, such as the
+/// firmware-controlled GPIO expander found on the Raspberry Pi 3 B, 3 B+ and 4 B, which
+/// controls the activity LED and camera power, and I2C or SPI GPIO expanders with a
+/// kernel driver. `Chip` hands out [`Pin`]s for these lines, which are accessed
+/// exclusively through the character device, similar to the [`Cdev`] backend.
+///
+/// Lines are identified by their offset on the chip. Only the first 54 lines of a chip
+/// are accessible.
+///
+/// [`Pin`]: struct.Pin.html
+/// [`Cdev`]: enum.Backend.html#variant.Cdev
+#[derive(Clone, Debug)]
+pub struct Chip {
+    info: ChipInfo,
+    gpio: Gpio,
+}
+
+impl Chip {
+    /// Returns information about all GPIO chips exposed through `/dev/gpiochipN`,
+    /// sorted by their chip number.
+    pub fn list() -> Result<Vec<ChipInfo>> {
+        let mut chips = Vec::new();
+
+        for path in ioctl::gpiochip_paths()? {
+            let cdev = ioctl::open_gpiochip(&path)?;
+            let chip_info = ioctl::ChipInfo::new(cdev.as_raw_fd())?;
+
+            chips.push(ChipInfo::from_ioctl(&path, &chip_info));
+        }
+
+        Ok(chips)
+    }
+
+    /// Opens the specified GPIO chip.
+    ///
+    /// `chip` can either be the path to the character device (`/dev/gpiochip1`), the
+    /// chip's name (`gpiochip1`) or its label (`raspberrypi-exp-gpio`). If no matching
+    /// chip is found, `open` returns an [`Error::Io`] with `ErrorKind::NotFound`.
+    ///
+    /// Opening the chip that controls the SoC's GPIO pins is equivalent to
+    /// constructing a [`Gpio`] instance with the [`Cdev`] backend, and the
+    /// pins share their state with any existing [`Gpio`] instances.
+    ///
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    /// [`Gpio`]: struct.Gpio.html
+    /// [`Cdev`]: enum.Backend.html#variant.Cdev
+    pub fn open(chip: &str) -> Result<Chip> {
+        let path = if chip.starts_with('/') {
+            chip.to_owned()
+        } else {
+            match Chip::list()?
+                .into_iter()
+                .find(|info| info.name == chip || info.label == chip)
+            {
+                Some(info) => info.path,
+                None => return Err(Error::Io(io::Error::from_raw_os_error(ENOENT))),
+            }
+        };
+
+        let cdev = ioctl::open_gpiochip(&path)?;
+        let chip_info = ioctl::ChipInfo::new(cdev.as_raw_fd())?;
+        let info = ChipInfo::from_ioctl(&path, &chip_info);
+
+        if chip_info.is_bcm2835() {
+            return Ok(Chip {
+                info,
+                gpio: Gpio::with_backend(Backend::Cdev)?,
+            });
+        }
+
+        let mut states = CHIP_STATES.lock().unwrap();
+
+        let state = match states.get(&info.name).and_then(|state| state.upgrade()) {
+            Some(state) => state,
+            None => {
+                let state = Arc::new(GpioState::new(Arc::new(Cdev::new(cdev)))?);
+
+                // Remove any states that belong to chips that are no longer in use
+                states.retain(|_, state| state.upgrade().is_some());
+                states.insert(info.name.clone(), Arc::downgrade(&state));
+
+                state
+            }
+        };
+
+        Ok(Chip {
+            info,
+            gpio: Gpio { inner: state },
+        })
+    }
+
+    /// Returns information about the GPIO chip.
+    pub fn info(&self) -> &ChipInfo {
+        &self.info
+    }
+
+    /// Returns a [`Pin`] for the specified line offset.
+    ///
+    /// `get` behaves the same as [`Gpio::get`]. If the line is already in use, or the
+    /// chip doesn't expose a line with the specified offset, `get` returns
+    /// `Err(`[`Error::PinNotAvailable`]`)`.
+    ///
+    /// [`Pin`]: struct.Pin.html
+    /// [`Gpio::get`]: struct.Gpio.html#method.get
+    /// [`Error::PinNotAvailable`]: enum.Error.html#variant.PinNotAvailable
+    pub fn get(&self, line: u8) -> Result<Pin> {
+        if u32::from(line) >= self.info.lines {
+            return Err(Error::PinNotAvailable(line));
+        }
+
+        self.gpio.get(line)
+    }
+
+    /// Returns a [`Pin`] for the line with the specified name.
+    ///
+    /// `get_by_name` behaves the same as [`Gpio::get_by_name`].
+    ///
+    /// [`Pin`]: struct.Pin.html
+    /// [`Gpio::get_by_name`]: struct.Gpio.html#method.get_by_name
+    pub fn get_by_name(&self, name: &str) -> Result<Pin> {
+        self.gpio.get(self.gpio.find_line(name, self.info.lines)?)
+    }
+
+    /// Returns information about all lines, ordered by their offset.
+    pub fn lines(&self) -> Result<Vec<LineInfo>> {
+        self.gpio.lines_up_to(self.info.lines)
+    }
+
+    /// Returns information about the specified line.
+    ///
+    /// If the chip doesn't expose a line with the specified offset, `line_info`
+    /// returns `Err(`[`Error::PinNotAvailable`]`)`.
+    ///
+    /// [`Error::PinNotAvailable`]: enum.Error.html#variant.PinNotAvailable
+    pub fn line_info(&self, line: u8) -> Result<LineInfo> {
+        if u32::from(line) >= self.info.lines {
+            return Err(Error::PinNotAvailable(line));
+        }
+
+        self.gpio.line_info(line)
+    }
+}
@@ -0,0 +1,258 @@
+// Copyright (c) 2017-2019 Rene van der Meer
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL
+// THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::interrupt::AsyncInterrupt;
+use super::pin::InputPin;
+use super::soft_pwm::invalid_input;
+use super::{Event, Level, Result, Trigger};
+
+// Maximum number of rising edges stored for the frequency calculation
+const MAX_EDGES: usize = 1024;
+
+const NANOS_PER_SEC: f64 = 1_000_000_000.0;
+
+fn as_secs(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / NANOS_PER_SEC
+}
+
+#[derive(Debug)]
+struct State {
+    window: Duration,
+    // Timestamps of the rising edges that occurred within the window
+    edges: VecDeque<Duration>,
+    period: Option<Duration>,
+    pulse_width: Option<Duration>,
+    count: u64,
+    // Time at which the most recent rising edge was received, used to detect
+    // a signal that's no longer active
+    received: Option<Instant>,
+}
+
+impl State {
+    fn new(window: Duration) -> State {
+        State {
+            window,
+            edges: VecDeque::with_capacity(MAX_EDGES),
+            period: None,
+            pulse_width: None,
+            count: 0,
+            received: None,
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = State::new(self.window);
+    }
+
+    fn push(&mut self, event: Event) {
+        if event.level() == Level::Low {
+            // Pulse width is measured from the most recent rising edge
+            if let Some(&rising_edge) = self.edges.back() {
+                self.pulse_width = event.timestamp.checked_sub(rising_edge);
+            }
+
+            return;
+        }
+
+        if let Some(&rising_edge) = self.edges.back() {
+            self.period = event.timestamp.checked_sub(rising_edge);
+        }
+
+        self.count += 1;
+        self.received = Some(Instant::now());
+        self.edges.push_back(event.timestamp);
+
+        // Keep at least two edges, so signals with a period longer than the
+        // window can still be measured
+        while self.edges.len() > MAX_EDGES
+            || (self.edges.len() > 2
+                && event
+                    .timestamp
+                    .checked_sub(self.edges[0])
+                    .unwrap_or_default()
+                    > self.window)
+        {
+            self.edges.pop_front();
+        }
+    }
+
+    // Returns true if no rising edges were received for longer than the window, or
+    // twice the most recent period, whichever is longer
+    fn stale(&self) -> bool {
+        let timeout = match self.period {
+            Some(period) => self.window.max(period * 2),
+            None => self.window,
+        };
+
+        match self.received {
+            Some(received) => received.elapsed() > timeout,
+            None => true,
+        }
+    }
+}
+
+/// Measures the frequency, period and duty cycle of a signal on an [`InputPin`].
+///
+/// `FrequencyCounter` is returned by [`InputPin::frequency_counter`]. Rising and
+/// falling edges are captured through an asynchronous interrupt trigger, and the
+/// measurements are based on the timestamps of the interrupt trigger events, which
+/// aren't affected by any delays in processing the events.
+///
+/// The interrupt trigger is removed when the `FrequencyCounter` goes out of scope.
+///
+/// [`InputPin`]: struct.InputPin.html
+/// [`InputPin::frequency_counter`]: struct.InputPin.html#method.frequency_counter
+#[derive(Debug)]
+pub struct FrequencyCounter<'a> {
+    interrupt: AsyncInterrupt,
+    state: Arc<Mutex<State>>,
+    // Mutably borrows the pin, so its interrupt triggers can't be reconfigured
+    // while the counter is active
+    phantom: PhantomData<&'a mut InputPin>,
+}
+
+impl<'a> FrequencyCounter<'a> {
+    pub(crate) fn new(pin: &'a mut InputPin, window: Duration) -> Result<FrequencyCounter<'a>> {
+        if window == Duration::new(0, 0) {
+            return Err(invalid_input("Window must be greater than zero"));
+        }
+
+        pin.clear_interrupt()?;
+        pin.clear_async_interrupt()?;
+
+        let state = Arc::new(Mutex::new(State::new(window)));
+        let callback_state = state.clone();
+
+        let interrupt = AsyncInterrupt::new(
+            pin.pin.gpio_state.async_interrupts()?,
+            pin.pin.gpio_state.backend.clone(),
+            pin.pin(),
+            Trigger::Both,
+            pin.debounce(),
+            pin.active_low(),
+            move |event| callback_state.lock().unwrap().push(event),
+        )?;
+
+        Ok(FrequencyCounter {
+            interrupt,
+            state,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Returns the average frequency in hertz (Hz) of the rising edges that
+    /// occurred within the window.
+    ///
+    /// Returns `0.0` if fewer than two rising edges were detected, or if the signal
+    /// is no longer active.
+    pub fn frequency(&self) -> f64 {
+        let state = self.state.lock().unwrap();
+
+        if state.edges.len() < 2 || state.stale() {
+            return 0.0;
+        }
+
+        // Timestamps can go backwards if they're based on the realtime clock
+        let span = match state.edges[state.edges.len() - 1].checked_sub(state.edges[0]) {
+            Some(span) => as_secs(span),
+            None => return 0.0,
+        };
+
+        if span > 0.0 {
+            (state.edges.len() - 1) as f64 / span
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns the duration between the two most recent rising edges.
+    ///
+    /// Returns `None` if fewer than two rising edges were detected, or if the
+    /// signal is no longer active.
+    pub fn period(&self) -> Option<Duration> {
+        let state = self.state.lock().unwrap();
+
+        if state.stale() {
+            None
+        } else {
+            state.period
+        }
+    }
+
+    /// Returns the duration between the most recent rising edge and the falling
+    /// edge that followed it.
+    ///
+    /// Returns `None` if no complete pulse was detected, or if the signal is no
+    /// longer active.
+    pub fn pulse_width(&self) -> Option<Duration> {
+        let state = self.state.lock().unwrap();
+
+        if state.stale() {
+            None
+        } else {
+            state.pulse_width
+        }
+    }
+
+    /// Returns the duty cycle as a floating point value between `0.0` (0%) and
+    /// `1.0` (100%), based on the most recent period and pulse width.
+    ///
+    /// Returns `None` if either the period or the pulse width isn't available.
+    pub fn duty_cycle(&self) -> Option<f64> {
+        let period = as_secs(self.period()?);
+        let pulse_width = as_secs(self.pulse_width()?);
+
+        if period > 0.0 {
+            Some((pulse_width / period).min(1.0))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the total number of rising edges detected since the counter was
+    /// created, or since the last call to [`reset`].
+    ///
+    /// [`reset`]: #method.reset
+    pub fn count(&self) -> u64 {
+        self.state.lock().unwrap().count
+    }
+
+    /// Returns the window over which the average frequency is calculated.
+    pub fn window(&self) -> Duration {
+        self.state.lock().unwrap().window
+    }
+
+    /// Clears all measurements and resets the edge count to zero.
+    pub fn reset(&mut self) {
+        self.state.lock().unwrap().reset();
+    }
+
+    /// Removes the interrupt trigger, and stops the counter.
+    ///
+    /// `stop` is called automatically when `FrequencyCounter` goes out of scope.
+    /// Calling `stop` explicitly allows any errors to be handled.
+    pub fn stop(mut self) -> Result<()> {
+        self.interrupt.stop()
+    }
+}
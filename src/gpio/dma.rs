@@ -0,0 +1,1112 @@
+// Copyright (c) 2017-2019 Rene van der Meer
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL
+// THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+// DMA-based PWM, similar to the approach used by ServoBlaster and pigpio.
+//
+// A single DMA channel continuously loops through a ring of control blocks that covers
+// one cycle. Each step within the cycle consists of three control blocks. The first two
+// write a bitmask to GPSET0 and GPCLR0, and the third writes a dummy word to the PWM or
+// PCM FIFO. The DMA controller waits for a DREQ from the FIFO before it executes the
+// third control block, which paces the ring at exactly one step per FIFO word.
+//
+// All pins driven by DMA share the same ring. A pin's edges are added by setting its
+// bit in the set and clear masks for the relevant steps, which the DMA controller
+// picks up during the next cycle without any CPU involvement.
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::mem;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+use libc::{self, c_int, c_ulong, c_void, off_t, size_t, MAP_FAILED, MAP_SHARED, O_SYNC};
+use libc::{PROT_READ, PROT_WRITE};
+
+use crate::gpio::soft_pwm::invalid_input;
+use crate::gpio::{Drive, Error, GpioState, Level, Result};
+use crate::system::{DeviceInfo, SoC};
+
+const PATH_DEV_MEM: &str = "/dev/mem";
+const PATH_DEV_VCIO: &str = "/dev/vcio";
+
+const PAGE_SIZE: usize = 4096;
+
+// Peripheral offsets, relative to the peripheral base address
+const DMA_OFFSET: u32 = 0x7000;
+const CLK_OFFSET: u32 = 0x10_1000;
+const PCM_OFFSET: u32 = 0x20_3000;
+const PWM_OFFSET: u32 = 0x20_c000;
+
+// Peripheral addresses as seen by the DMA controller
+const BUS_PERIPHERAL_BASE: u32 = 0x7e00_0000;
+const BUS_GPSET0: u32 = BUS_PERIPHERAL_BASE + 0x20_001c;
+const BUS_GPCLR0: u32 = BUS_PERIPHERAL_BASE + 0x20_0028;
+const BUS_PCM_FIFO: u32 = BUS_PERIPHERAL_BASE + PCM_OFFSET + 0x04;
+const BUS_PWM_FIF1: u32 = BUS_PERIPHERAL_BASE + PWM_OFFSET + 0x18;
+
+// DMA channel registers (word offsets)
+const DMA_CHANNEL_SIZE: usize = 0x100 / 4;
+const DMA_CS: usize = 0x00;
+const DMA_CONBLK_AD: usize = 0x01;
+const DMA_DEBUG: usize = 0x08;
+
+const DMA_CS_RESET: u32 = 1 << 31;
+const DMA_CS_WAIT_FOR_OUTSTANDING_WRITES: u32 = 1 << 28;
+const DMA_CS_PANIC_PRIORITY_SHIFT: u32 = 20;
+const DMA_CS_PRIORITY_SHIFT: u32 = 16;
+const DMA_CS_INT: u32 = 1 << 2;
+const DMA_CS_END: u32 = 1 << 1;
+const DMA_CS_ACTIVE: u32 = 1;
+const DMA_DEBUG_CLEAR_ERRORS: u32 = 0b111;
+
+const DMA_TI_NO_WIDE_BURSTS: u32 = 1 << 26;
+const DMA_TI_PERMAP_SHIFT: u32 = 16;
+const DMA_TI_DEST_DREQ: u32 = 1 << 6;
+const DMA_TI_WAIT_RESP: u32 = 1 << 3;
+
+// DREQ peripheral mappings
+const DREQ_PCM_TX: u32 = 2;
+const DREQ_PWM: u32 = 5;
+
+// Clock manager registers (word offsets)
+const CM_PCMCTL: usize = 0x98 / 4;
+const CM_PCMDIV: usize = 0x9c / 4;
+const CM_PWMCTL: usize = 0xa0 / 4;
+const CM_PWMDIV: usize = 0xa4 / 4;
+
+// Writes are ignored unless the password is set in bits 31:24.
+const CM_PASSWD: u32 = 0x5a << 24;
+const CM_CTL_KILL: u32 = 1 << 5;
+const CM_CTL_ENAB: u32 = 1 << 4;
+const CM_CTL_SRC_PLLD: u32 = 6;
+const CM_DIV_DIVI_SHIFT: u32 = 12;
+
+// Number of words the DMA controller writes to the FIFO before the pacer stops
+// requesting more data, based on the configured DREQ thresholds
+const PWM_FIFO_WORDS: usize = 15;
+const PCM_FIFO_WORDS: usize = 30;
+
+// Both pacing clocks run at 10 MHz, and each FIFO word takes 10 clock cycles
+// per microsecond of step duration.
+const PACING_CLOCK_MHZ: u32 = 10;
+
+// PWM registers (word offsets)
+const PWM_CTL: usize = 0x00;
+const PWM_STA: usize = 0x01;
+const PWM_DMAC: usize = 0x02;
+const PWM_RNG1: usize = 0x04;
+
+const PWM_CTL_CLRF1: u32 = 1 << 6;
+const PWM_CTL_USEF1: u32 = 1 << 5;
+const PWM_CTL_MODE1: u32 = 1 << 1;
+const PWM_CTL_PWEN1: u32 = 1;
+const PWM_DMAC_ENAB: u32 = 1 << 31;
+const PWM_DMAC_THRESHOLD: u32 = (15 << 8) | 15;
+
+// PCM registers (word offsets)
+const PCM_CS: usize = 0x00;
+const PCM_MODE: usize = 0x02;
+const PCM_TXC: usize = 0x04;
+const PCM_DREQ: usize = 0x05;
+const PCM_INTEN: usize = 0x06;
+const PCM_INTSTC: usize = 0x07;
+
+const PCM_CS_DMAEN: u32 = 1 << 9;
+const PCM_CS_RXCLR: u32 = 1 << 4;
+const PCM_CS_TXCLR: u32 = 1 << 3;
+const PCM_CS_TXON: u32 = 1 << 2;
+const PCM_CS_EN: u32 = 1;
+const PCM_MODE_FLEN_SHIFT: u32 = 10;
+const PCM_TXC_CH1WEX: u32 = 1 << 31;
+const PCM_TXC_CH1EN: u32 = 1 << 30;
+const PCM_DREQ_THRESHOLD: u32 = (16 << 24) | (30 << 8);
+
+// Mailbox property interface
+const MBOX_REQUEST: u32 = 0;
+const MBOX_SUCCESS: u32 = 0x8000_0000;
+const MBOX_TAG_ALLOCATE_MEMORY: u32 = 0x0003_000c;
+const MBOX_TAG_LOCK_MEMORY: u32 = 0x0003_000d;
+const MBOX_TAG_UNLOCK_MEMORY: u32 = 0x0003_000e;
+const MBOX_TAG_RELEASE_MEMORY: u32 = 0x0003_000f;
+
+// DMA memory needs to bypass the CPU caches. The BCM2835 uses the L1 non-allocating
+// alias, while the BCM2836 and later use the direct (uncached) alias.
+const MEM_FLAG_DIRECT: u32 = 1 << 2;
+const MEM_FLAG_L1_NONALLOCATING: u32 = (1 << 2) | (2 << 2);
+const BUS_ALIAS_MASK: u32 = 0xc000_0000;
+
+// _IOWR(100, 0, char *)
+const IOCTL_MBOX_PROPERTY: c_ulong =
+    (3 << 30) | ((mem::size_of::<*mut u8>() as c_ulong) << 16) | (100 << 8);
+
+// Each step consists of a GPSET0 write, a GPCLR0 write and a paced FIFO write
+const CBS_PER_STEP: usize = 3;
+const CB_WORDS: usize = 8;
+
+const DEFAULT_CHANNEL: u8 = 14;
+const DEFAULT_CHANNEL_BCM2711: u8 = 7;
+// DMA channels 11-14 on the BCM2711 are DMA4 engines, which use a different
+// control block layout.
+const MAX_CHANNEL: u8 = 14;
+const MAX_CHANNEL_BCM2711: u8 = 10;
+
+const MAX_STEP_MICROS: u64 = 100;
+const MAX_STEPS: u64 = 100_000;
+
+// Lite channels can transfer up to 65535 bytes per control block
+const MAX_CB_WORDS: usize = 16_383;
+// Limits the DMA memory used by a single waveform to 2 MiB
+const MAX_WAVEFORM_CBS: usize = 65_535;
+const WAVEFORM_MASK_OFFSET: usize = 0;
+const WAVEFORM_DUMMY_OFFSET: usize = 1;
+const WAVEFORM_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Peripherals that can pace the DMA controller for DMA-based PWM.
+///
+/// The selected peripheral is reserved for pacing while any pins are driven by
+/// DMA, and can't be used for other purposes at the same time.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum DmaPacing {
+    /// Paced by the PWM peripheral's FIFO. The hardware [`Pwm`] channels and
+    /// analog audio output can't be used simultaneously.
+    ///
+    /// [`Pwm`]: ../pwm/struct.Pwm.html
+    Pwm,
+    /// Paced by the PCM peripheral's FIFO. I2S audio can't be used simultaneously.
+    Pcm,
+}
+
+/// Configuration for DMA-based PWM.
+///
+/// All pins driven by DMA share a single cycle, which is divided into steps of equal
+/// length. The step duration determines the PWM resolution. Pulse widths are rounded
+/// to the nearest step, and periods are rounded to the nearest value that evenly divides
+/// the cycle.
+///
+/// The default configuration uses DMA channel 14 (or 7 on the Raspberry Pi 4 B), pacing
+/// through the PWM peripheral, 10 µs steps and a 20 ms cycle.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct DmaConfig {
+    /// DMA channel, or `None` to use the default channel for the SoC. Channels 0-14 are
+    /// supported on the BCM2835, BCM2836 and BCM2837, and 0-10 on the BCM2711. Make
+    /// sure the selected channel isn't in use by the kernel or the firmware.
+    pub channel: Option<u8>,
+    /// Peripheral that paces the DMA controller.
+    pub pacing: DmaPacing,
+    /// Step duration. Supported values are whole microseconds between 1 µs and 100 µs.
+    pub step: Duration,
+    /// Cycle duration, which needs to be a multiple of `step`.
+    pub cycle: Duration,
+}
+
+impl Default for DmaConfig {
+    fn default() -> DmaConfig {
+        DmaConfig {
+            channel: None,
+            pacing: DmaPacing::Pwm,
+            step: Duration::from_micros(10),
+            cycle: Duration::from_millis(20),
+        }
+    }
+}
+
+fn open_dev(path: &str, flags: c_int) -> Result<File> {
+    match OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(flags)
+        .open(path)
+    {
+        Ok(file) => Ok(file),
+        Err(ref e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            Err(Error::PermissionDenied(String::from(path)))
+        }
+        Err(e) => Err(Error::Io(e)),
+    }
+}
+
+// Memory-mapped peripheral registers or DMA memory
+#[derive(Debug)]
+struct MemMap {
+    ptr: *mut u32,
+    size: usize,
+}
+
+impl MemMap {
+    fn new(mem_file: &File, offset: u32, size: usize) -> Result<MemMap> {
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                size,
+                PROT_READ | PROT_WRITE,
+                MAP_SHARED,
+                mem_file.as_raw_fd(),
+                offset as off_t,
+            )
+        };
+
+        if ptr == MAP_FAILED {
+            return Err(Error::Io(io::Error::last_os_error()));
+        }
+
+        Ok(MemMap {
+            ptr: ptr as *mut u32,
+            size,
+        })
+    }
+
+    #[inline(always)]
+    fn read(&self, offset: usize) -> u32 {
+        unsafe { ptr::read_volatile(self.ptr.add(offset)) }
+    }
+
+    #[inline(always)]
+    fn write(&self, offset: usize, value: u32) {
+        unsafe {
+            ptr::write_volatile(self.ptr.add(offset), value);
+        }
+    }
+}
+
+impl Drop for MemMap {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr as *mut c_void, self.size as size_t);
+        }
+    }
+}
+
+// Allocates physically contiguous, uncached memory from the VideoCore
+// through the mailbox property interface.
+#[derive(Debug)]
+struct Mailbox {
+    vcio: File,
+}
+
+impl Mailbox {
+    fn open() -> Result<Mailbox> {
+        Ok(Mailbox {
+            vcio: open_dev(PATH_DEV_VCIO, 0)?,
+        })
+    }
+
+    fn property(&self, tag: u32, args: &[u32]) -> Result<u32> {
+        // Buffer size, request code, tag, value buffer size, request size,
+        // values and end tag. The response overwrites the first value.
+        let mut buffer = [0u32; 9];
+        let len = 6 + args.len();
+
+        buffer[0] = (len * mem::size_of::<u32>()) as u32;
+        buffer[1] = MBOX_REQUEST;
+        buffer[2] = tag;
+        buffer[3] = mem::size_of_val(args) as u32;
+        buffer[4] = mem::size_of_val(args) as u32;
+        buffer[5..5 + args.len()].copy_from_slice(args);
+
+        if unsafe {
+            libc::ioctl(
+                self.vcio.as_raw_fd(),
+                IOCTL_MBOX_PROPERTY as _,
+                buffer.as_mut_ptr(),
+            )
+        } == -1
+        {
+            return Err(Error::Io(io::Error::last_os_error()));
+        }
+
+        if buffer[1] != MBOX_SUCCESS {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Mailbox request failed",
+            )));
+        }
+
+        Ok(buffer[5])
+    }
+}
+
+// Uncached memory that's accessible by both the CPU and the DMA controller
+#[derive(Debug)]
+struct DmaMem {
+    mailbox: Mailbox,
+    handle: u32,
+    bus_addr: u32,
+    map: Option<MemMap>,
+}
+
+impl DmaMem {
+    fn new(mem_file: &File, size: usize, soc: SoC) -> Result<DmaMem> {
+        let mailbox = Mailbox::open()?;
+
+        let flags = if soc == SoC::Bcm2835 {
+            MEM_FLAG_L1_NONALLOCATING
+        } else {
+            MEM_FLAG_DIRECT
+        };
+
+        let handle = mailbox.property(
+            MBOX_TAG_ALLOCATE_MEMORY,
+            &[size as u32, PAGE_SIZE as u32, flags],
+        )?;
+
+        if handle == 0 {
+            return Err(Error::Io(io::Error::from_raw_os_error(libc::ENOMEM)));
+        }
+
+        let mut dma_mem = DmaMem {
+            mailbox,
+            handle,
+            bus_addr: 0,
+            map: None,
+        };
+
+        dma_mem.bus_addr = dma_mem.mailbox.property(MBOX_TAG_LOCK_MEMORY, &[handle])?;
+        dma_mem.map = Some(MemMap::new(
+            mem_file,
+            dma_mem.bus_addr & !BUS_ALIAS_MASK,
+            size,
+        )?);
+
+        Ok(dma_mem)
+    }
+
+    #[inline(always)]
+    fn map(&self) -> &MemMap {
+        self.map.as_ref().unwrap()
+    }
+
+    // Returns the bus address of the specified word
+    #[inline(always)]
+    fn bus(&self, offset: usize) -> u32 {
+        self.bus_addr + (offset * mem::size_of::<u32>()) as u32
+    }
+}
+
+impl Drop for DmaMem {
+    fn drop(&mut self) {
+        self.map = None;
+
+        if self.bus_addr != 0 {
+            let _ = self
+                .mailbox
+                .property(MBOX_TAG_UNLOCK_MEMORY, &[self.handle]);
+        }
+
+        let _ = self
+            .mailbox
+            .property(MBOX_TAG_RELEASE_MEMORY, &[self.handle]);
+    }
+}
+
+// Set while the DMA channel and pacing peripheral are in use by either the
+// PWM engine or a waveform
+static DMA_RESERVED: AtomicBool = AtomicBool::new(false);
+
+// Checks whether the DMA controller can drive the specified pin. The DMA controller
+// writes directly to GPSET0 and GPCLR0, which only covers push-pull outputs on
+// GPIO 0-31 using the native backend.
+fn check_pin(gpio_state: &GpioState, pin: u8, drive: Drive) -> Result<()> {
+    if !gpio_state.backend.supports_dma() || pin >= 32 || drive != Drive::PushPull {
+        return Err(Error::NotSupported);
+    }
+
+    Ok(())
+}
+
+fn step_micros(config: &DmaConfig) -> Result<u64> {
+    let step_micros = config.step.as_micros() as u64;
+    if config.step != Duration::from_micros(step_micros)
+        || step_micros == 0
+        || step_micros > MAX_STEP_MICROS
+    {
+        return Err(invalid_input("Unsupported DMA step duration"));
+    }
+
+    Ok(step_micros)
+}
+
+// Writes a control block that transfers len bytes from source to dest, and then
+// continues with the control block at next (or stops if next is 0)
+fn write_cb(mem: &DmaMem, offset: usize, ti: u32, source: u32, dest: u32, len: u32, next: u32) {
+    let map = mem.map();
+
+    map.write(offset, ti);
+    map.write(offset + 1, source);
+    map.write(offset + 2, dest);
+    map.write(offset + 3, len);
+    map.write(offset + 4, 0);
+    map.write(offset + 5, next);
+    map.write(offset + 6, 0);
+    map.write(offset + 7, 0);
+}
+
+// Owns the DMA channel and the peripheral that paces it
+#[derive(Debug)]
+struct DmaController {
+    config: DmaConfig,
+    soc: SoC,
+    channel: usize,
+    step_micros: u32,
+    divi: u32,
+    started: bool,
+    mem_file: File,
+    dma: MemMap,
+    clk: MemMap,
+    pacer: MemMap,
+}
+
+impl DmaController {
+    fn new(config: DmaConfig) -> Result<DmaController> {
+        let device_info = DeviceInfo::new().map_err(|_| Error::UnknownModel)?;
+        let soc = device_info.soc();
+
+        let (default_channel, max_channel, plld_mhz) = match soc {
+            SoC::Bcm2711 => (DEFAULT_CHANNEL_BCM2711, MAX_CHANNEL_BCM2711, 750),
+            _ => (DEFAULT_CHANNEL, MAX_CHANNEL, 500),
+        };
+
+        let channel = config.channel.unwrap_or(default_channel);
+        if channel > max_channel {
+            return Err(invalid_input("Unsupported DMA channel"));
+        }
+
+        let step_micros = step_micros(&config)?;
+
+        let mem_file = open_dev(PATH_DEV_MEM, O_SYNC)?;
+        let peripheral_base = device_info.peripheral_base();
+
+        let dma = MemMap::new(&mem_file, peripheral_base + DMA_OFFSET, PAGE_SIZE)?;
+        let clk = MemMap::new(&mem_file, peripheral_base + CLK_OFFSET, PAGE_SIZE)?;
+        let pacer_offset = match config.pacing {
+            DmaPacing::Pwm => PWM_OFFSET,
+            DmaPacing::Pcm => PCM_OFFSET,
+        };
+        let pacer = MemMap::new(&mem_file, peripheral_base + pacer_offset, PAGE_SIZE)?;
+
+        if DMA_RESERVED.swap(true, Ordering::SeqCst) {
+            return Err(Error::Io(io::Error::from_raw_os_error(libc::EBUSY)));
+        }
+
+        Ok(DmaController {
+            config,
+            soc,
+            channel: channel as usize * DMA_CHANNEL_SIZE,
+            step_micros: step_micros as u32,
+            divi: plld_mhz / PACING_CLOCK_MHZ,
+            started: false,
+            mem_file,
+            dma,
+            clk,
+            pacer,
+        })
+    }
+
+    // Allocates DMA memory for the specified number of words
+    fn alloc(&self, words: usize) -> Result<DmaMem> {
+        let size = (words * mem::size_of::<u32>() + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
+
+        DmaMem::new(&self.mem_file, size, self.soc)
+    }
+
+    #[inline(always)]
+    fn step(&self) -> Duration {
+        Duration::from_micros(u64::from(self.step_micros))
+    }
+
+    // Rounds the duration to the nearest number of steps
+    fn to_steps(&self, duration: Duration) -> usize {
+        let step_ns = self.step().as_nanos();
+
+        ((duration.as_nanos() + step_ns / 2) / step_ns) as usize
+    }
+
+    // Returns the FIFO bus address, and the transfer information for a control block
+    // that's paced by the FIFO
+    fn fifo(&self) -> (u32, u32) {
+        let (fifo, permap) = match self.config.pacing {
+            DmaPacing::Pwm => (BUS_PWM_FIF1, DREQ_PWM),
+            DmaPacing::Pcm => (BUS_PCM_FIFO, DREQ_PCM_TX),
+        };
+
+        (
+            fifo,
+            DMA_TI_NO_WIDE_BURSTS
+                | DMA_TI_WAIT_RESP
+                | DMA_TI_DEST_DREQ
+                | (permap << DMA_TI_PERMAP_SHIFT),
+        )
+    }
+
+    // Number of words that fit in the FIFO before the pacer stops requesting data
+    fn fifo_words(&self) -> usize {
+        match self.config.pacing {
+            DmaPacing::Pwm => PWM_FIFO_WORDS,
+            DmaPacing::Pcm => PCM_FIFO_WORDS,
+        }
+    }
+
+    fn start(&mut self, first_cb: u32) {
+        self.stop_dma();
+        self.init_pacer();
+        self.start_dma(first_cb);
+        self.started = true;
+    }
+
+    fn stop(&mut self) {
+        if self.started {
+            self.stop_dma();
+            self.stop_pacer();
+            self.started = false;
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.dma.read(self.channel + DMA_CS) & DMA_CS_ACTIVE > 0
+    }
+
+    fn init_clock(&self, ctl: usize, div: usize) {
+        self.clk.write(ctl, CM_PASSWD | CM_CTL_KILL);
+        thread::sleep(Duration::from_micros(10));
+        self.clk
+            .write(div, CM_PASSWD | (self.divi << CM_DIV_DIVI_SHIFT));
+        thread::sleep(Duration::from_micros(10));
+        self.clk.write(ctl, CM_PASSWD | CM_CTL_SRC_PLLD);
+        thread::sleep(Duration::from_micros(10));
+        self.clk
+            .write(ctl, CM_PASSWD | CM_CTL_SRC_PLLD | CM_CTL_ENAB);
+        thread::sleep(Duration::from_micros(10));
+    }
+
+    fn init_pacer(&self) {
+        // Number of pacing clock cycles per FIFO word
+        let bits = self.step_micros * PACING_CLOCK_MHZ;
+
+        match self.config.pacing {
+            DmaPacing::Pwm => {
+                self.pacer.write(PWM_CTL, 0);
+                thread::sleep(Duration::from_micros(10));
+                self.init_clock(CM_PWMCTL, CM_PWMDIV);
+                self.pacer.write(PWM_STA, !0);
+                self.pacer.write(PWM_RNG1, bits);
+                self.pacer
+                    .write(PWM_DMAC, PWM_DMAC_ENAB | PWM_DMAC_THRESHOLD);
+                self.pacer.write(PWM_CTL, PWM_CTL_CLRF1);
+                thread::sleep(Duration::from_micros(10));
+                self.pacer
+                    .write(PWM_CTL, PWM_CTL_USEF1 | PWM_CTL_MODE1 | PWM_CTL_PWEN1);
+            }
+            DmaPacing::Pcm => {
+                self.pacer.write(PCM_CS, 0);
+                thread::sleep(Duration::from_micros(10));
+                self.init_clock(CM_PCMCTL, CM_PCMDIV);
+                self.pacer.write(PCM_CS, PCM_CS_EN);
+                self.pacer.write(PCM_TXC, PCM_TXC_CH1WEX | PCM_TXC_CH1EN);
+                self.pacer
+                    .write(PCM_MODE, (bits - 1) << PCM_MODE_FLEN_SHIFT);
+                self.pacer
+                    .write(PCM_CS, PCM_CS_EN | PCM_CS_TXCLR | PCM_CS_RXCLR);
+                thread::sleep(Duration::from_micros(10));
+                self.pacer.write(PCM_DREQ, PCM_DREQ_THRESHOLD);
+                self.pacer.write(PCM_INTEN, 0);
+                self.pacer.write(PCM_INTSTC, !0);
+                self.pacer.write(PCM_CS, PCM_CS_EN | PCM_CS_DMAEN);
+                thread::sleep(Duration::from_micros(10));
+                self.pacer
+                    .write(PCM_CS, PCM_CS_EN | PCM_CS_DMAEN | PCM_CS_TXON);
+            }
+        }
+    }
+
+    fn stop_pacer(&self) {
+        match self.config.pacing {
+            DmaPacing::Pwm => self.pacer.write(PWM_CTL, 0),
+            DmaPacing::Pcm => self.pacer.write(PCM_CS, 0),
+        }
+    }
+
+    fn start_dma(&self, first_cb: u32) {
+        self.dma.write(self.channel + DMA_CONBLK_AD, first_cb);
+        self.dma.write(
+            self.channel + DMA_CS,
+            DMA_CS_WAIT_FOR_OUTSTANDING_WRITES
+                | (15 << DMA_CS_PANIC_PRIORITY_SHIFT)
+                | (15 << DMA_CS_PRIORITY_SHIFT)
+                | DMA_CS_ACTIVE,
+        );
+    }
+
+    fn stop_dma(&self) {
+        self.dma.write(self.channel + DMA_CS, DMA_CS_RESET);
+        thread::sleep(Duration::from_micros(10));
+        self.dma
+            .write(self.channel + DMA_CS, DMA_CS_INT | DMA_CS_END);
+        self.dma
+            .write(self.channel + DMA_DEBUG, DMA_DEBUG_CLEAR_ERRORS);
+    }
+}
+
+impl Drop for DmaController {
+    fn drop(&mut self) {
+        self.stop();
+
+        DMA_RESERVED.store(false, Ordering::SeqCst);
+    }
+}
+
+// Runs the control block ring shared by all DMA-driven pins
+#[derive(Debug)]
+pub(crate) struct DmaEngine {
+    // The DMA controller has to be stopped before the ring is released, so
+    // controller needs to be declared before mem.
+    controller: DmaController,
+    steps: usize,
+    mem: DmaMem,
+    // Serializes mask updates
+    lock: Mutex<()>,
+}
+
+// Share a single engine between all DMA-driven pins
+lazy_static! {
+    static ref DMA_ENGINE: Mutex<Weak<DmaEngine>> = Mutex::new(Weak::new());
+}
+
+impl DmaEngine {
+    // Returns the running engine, or starts a new one
+    fn shared(config: DmaConfig) -> Result<Arc<DmaEngine>> {
+        let mut weak_engine = DMA_ENGINE.lock().unwrap();
+
+        if let Some(engine) = weak_engine.upgrade() {
+            if engine.controller.config != config {
+                return Err(invalid_input(
+                    "DMA-based PWM is already active with a different configuration",
+                ));
+            }
+
+            return Ok(engine);
+        }
+
+        let engine = Arc::new(DmaEngine::new(config)?);
+        *weak_engine = Arc::downgrade(&engine);
+
+        Ok(engine)
+    }
+
+    fn new(config: DmaConfig) -> Result<DmaEngine> {
+        let step_micros = step_micros(&config)?;
+
+        // The cycle needs to consist of a whole number of steps
+        let steps = match config.cycle.as_micros() as u64 / step_micros {
+            steps @ 2..=MAX_STEPS if config.cycle == Duration::from_micros(steps * step_micros) => {
+                steps as usize
+            }
+            _ => return Err(invalid_input("Unsupported DMA cycle duration")),
+        };
+
+        let mut controller = DmaController::new(config)?;
+
+        // Control blocks, followed by the set masks, clear masks and the dummy
+        // word that's written to the FIFO
+        let mem = controller.alloc(steps * (CBS_PER_STEP * CB_WORDS + 2) + 1)?;
+
+        DmaEngine::init_ring(&controller, &mem, steps);
+        controller.start(mem.bus(0));
+
+        Ok(DmaEngine {
+            controller,
+            steps,
+            mem,
+            lock: Mutex::new(()),
+        })
+    }
+
+    #[inline(always)]
+    fn set_mask_offset(steps: usize, step: usize) -> usize {
+        steps * CBS_PER_STEP * CB_WORDS + step
+    }
+
+    #[inline(always)]
+    fn clear_mask_offset(steps: usize, step: usize) -> usize {
+        steps * (CBS_PER_STEP * CB_WORDS + 1) + step
+    }
+
+    #[inline(always)]
+    fn dummy_offset(steps: usize) -> usize {
+        steps * (CBS_PER_STEP * CB_WORDS + 2)
+    }
+
+    fn init_ring(controller: &DmaController, mem: &DmaMem, steps: usize) {
+        let cb_count = steps * CBS_PER_STEP;
+        let (fifo, paced_ti) = controller.fifo();
+
+        for step in 0..steps {
+            mem.map().write(DmaEngine::set_mask_offset(steps, step), 0);
+            mem.map()
+                .write(DmaEngine::clear_mask_offset(steps, step), 0);
+
+            let cbs = [
+                (
+                    DMA_TI_NO_WIDE_BURSTS | DMA_TI_WAIT_RESP,
+                    mem.bus(DmaEngine::set_mask_offset(steps, step)),
+                    BUS_GPSET0,
+                ),
+                (
+                    DMA_TI_NO_WIDE_BURSTS | DMA_TI_WAIT_RESP,
+                    mem.bus(DmaEngine::clear_mask_offset(steps, step)),
+                    BUS_GPCLR0,
+                ),
+                (paced_ti, mem.bus(DmaEngine::dummy_offset(steps)), fifo),
+            ];
+
+            for (idx, &(ti, source, dest)) in cbs.iter().enumerate() {
+                let cb = step * CBS_PER_STEP + idx;
+                let next = (cb + 1) % cb_count;
+
+                write_cb(
+                    mem,
+                    cb * CB_WORDS,
+                    ti,
+                    source,
+                    dest,
+                    mem::size_of::<u32>() as u32,
+                    mem.bus(next * CB_WORDS),
+                );
+            }
+        }
+
+        mem.map().write(DmaEngine::dummy_offset(steps), 0);
+    }
+
+    // Rounds the period to the nearest number of steps that evenly divides the cycle
+    fn period_steps(&self, period: Duration) -> usize {
+        let target = self.controller.to_steps(period);
+
+        (1..=self.steps)
+            .filter(|&divisor| self.steps / divisor * divisor == self.steps)
+            .min_by_key(|&divisor| (divisor as i64 - target as i64).abs())
+            .unwrap_or(self.steps)
+    }
+
+    // Updates the pin's bit in the set and clear masks for every step. Each mask
+    // is written at most once, so the DMA controller never sees an intermediate state.
+    fn update_pin<F>(&self, pin: u8, edges: F)
+    where
+        F: Fn(usize) -> (bool, bool),
+    {
+        let _lock = self.lock.lock().unwrap();
+
+        let mem = self.mem.map();
+        let bit = 1 << pin;
+
+        for step in 0..self.steps {
+            let (set, clear) = edges(step);
+
+            for &(offset, enabled) in [
+                (DmaEngine::set_mask_offset(self.steps, step), set),
+                (DmaEngine::clear_mask_offset(self.steps, step), clear),
+            ]
+            .iter()
+            {
+                let mask = mem.read(offset);
+                let new_mask = if enabled { mask | bit } else { mask & !bit };
+
+                if new_mask != mask {
+                    mem.write(offset, new_mask);
+                }
+            }
+        }
+    }
+}
+
+// The memory-mapped registers are only accessed through volatile reads and writes,
+// and mask updates are serialized through lock.
+unsafe impl Send for DmaEngine {}
+unsafe impl Sync for DmaEngine {}
+
+// Drives a single pin through the shared DMA engine
+#[derive(Debug)]
+pub(crate) struct DmaPwm {
+    engine: Arc<DmaEngine>,
+    pin: u8,
+    drive: Drive,
+    active_low: bool,
+    gpio_state: Arc<GpioState>,
+    active: bool,
+}
+
+impl DmaPwm {
+    pub(crate) fn new(
+        config: DmaConfig,
+        pin: u8,
+        drive: Drive,
+        active_low: bool,
+        gpio_state: Arc<GpioState>,
+        period: Duration,
+        pulse_width: Duration,
+    ) -> Result<DmaPwm> {
+        check_pin(&gpio_state, pin, drive)?;
+
+        let mut dma_pwm = DmaPwm {
+            engine: DmaEngine::shared(config)?,
+            pin,
+            drive,
+            active_low,
+            gpio_state,
+            active: true,
+        };
+
+        dma_pwm.reconfigure(period, pulse_width);
+
+        Ok(dma_pwm)
+    }
+
+    pub(crate) fn config(&self) -> DmaConfig {
+        self.engine.controller.config
+    }
+
+    pub(crate) fn reconfigure(&mut self, period: Duration, pulse_width: Duration) {
+        let period_steps = if period > Duration::new(0, 0) {
+            self.engine.period_steps(period)
+        } else {
+            0
+        };
+        let pulse_width_steps = self
+            .engine
+            .controller
+            .to_steps(pulse_width)
+            .min(period_steps);
+
+        let active_low = self.active_low;
+
+        self.engine.update_pin(self.pin, |step| {
+            // A zero period keeps the pin inactive
+            let (active, inactive) = if period_steps == 0 {
+                (false, step == 0)
+            } else {
+                let offset = step % period_steps;
+                (
+                    offset == 0 && pulse_width_steps > 0,
+                    offset == pulse_width_steps % period_steps && pulse_width_steps < period_steps,
+                )
+            };
+
+            if active_low {
+                (inactive, active)
+            } else {
+                (active, inactive)
+            }
+        });
+
+        self.active = true;
+    }
+
+    pub(crate) fn stop(&mut self) -> Result<()> {
+        if !self.active {
+            return Ok(());
+        }
+
+        self.engine.update_pin(self.pin, |_| (false, false));
+        self.active = false;
+
+        // Wait for any in-flight mask writes to complete before setting the
+        // final level
+        thread::sleep(self.engine.controller.step() * 2);
+
+        let inactive = if self.active_low {
+            Level::High
+        } else {
+            Level::Low
+        };
+        self.gpio_state
+            .backend
+            .write(self.pin, inactive, self.drive);
+
+        Ok(())
+    }
+}
+
+impl Drop for DmaPwm {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+// Generates a waveform on a single pin through a chain of control blocks. Unlike
+// the PWM engine, a waveform requires exclusive access to the DMA channel.
+#[derive(Debug)]
+pub(crate) struct DmaWaveform {
+    // The DMA controller has to be stopped before the chain is released, so
+    // controller needs to be declared before _mem.
+    controller: DmaController,
+    _mem: DmaMem,
+}
+
+impl DmaWaveform {
+    pub(crate) fn new(
+        config: DmaConfig,
+        pin: u8,
+        drive: Drive,
+        active_low: bool,
+        gpio_state: &GpioState,
+        waveform: &[(Level, Duration)],
+        count: Option<u32>,
+    ) -> Result<DmaWaveform> {
+        check_pin(gpio_state, pin, drive)?;
+
+        let mut controller = DmaController::new(config)?;
+        let (fifo, paced_ti) = controller.fifo();
+
+        // Convert each entry to a GPSET0/GPCLR0 write, followed by the number of
+        // steps the output state needs to be held. Entries that round down to 0
+        // steps are skipped.
+        let segments: Vec<(u32, usize)> = waveform
+            .iter()
+            .map(|&(level, duration)| {
+                let dest = if (level == Level::High) != active_low {
+                    BUS_GPSET0
+                } else {
+                    BUS_GPCLR0
+                };
+
+                (dest, controller.to_steps(duration))
+            })
+            .filter(|&(_, steps)| steps > 0)
+            .collect();
+
+        // Each segment consists of a single mask write, followed by one or more
+        // paced control blocks
+        let segment_cbs: usize = segments
+            .iter()
+            .map(|&(_, steps)| 2 + (steps - 1) / MAX_CB_WORDS)
+            .sum();
+        // Finite repeats are unrolled, so the waveform stops at the end of the
+        // chain without any intervention. Infinite repeats loop back to the start.
+        let copies = count.unwrap_or(1) as usize;
+
+        // A single control block fills the FIFO before the waveform starts
+        let cb_count = segment_cbs
+            .checked_mul(copies)
+            .and_then(|cbs| cbs.checked_add(1))
+            .filter(|&cbs| cbs <= MAX_WAVEFORM_CBS)
+            .ok_or_else(|| {
+                invalid_input("Waveform is too long for DMA, reduce the repeat count or entries")
+            })?;
+
+        // The pin mask and the dummy word that's written to the FIFO, followed
+        // by the control blocks, which need to be 32-byte aligned
+        let mem = controller.alloc(CB_WORDS * (cb_count + 1))?;
+        mem.map().write(WAVEFORM_MASK_OFFSET, 1 << pin);
+        mem.map().write(WAVEFORM_DUMMY_OFFSET, 0);
+
+        let cb_bus = |cb: usize| mem.bus(CB_WORDS * (cb + 1));
+
+        // Continue with the first segment after the last one if the waveform repeats
+        // indefinitely, otherwise stop
+        let next_cb = |cb: usize| {
+            if cb + 1 < cb_count {
+                cb_bus(cb + 1)
+            } else if count.is_none() && cb_count > 1 {
+                cb_bus(1)
+            } else {
+                0
+            }
+        };
+
+        let fill_words = controller.fifo_words();
+        write_cb(
+            &mem,
+            CB_WORDS,
+            paced_ti,
+            mem.bus(WAVEFORM_DUMMY_OFFSET),
+            fifo,
+            (fill_words * mem::size_of::<u32>()) as u32,
+            next_cb(0),
+        );
+
+        let mut cb = 1;
+        for _ in 0..copies {
+            for &(dest, steps) in &segments {
+                write_cb(
+                    &mem,
+                    CB_WORDS * (cb + 1),
+                    DMA_TI_NO_WIDE_BURSTS | DMA_TI_WAIT_RESP,
+                    mem.bus(WAVEFORM_MASK_OFFSET),
+                    dest,
+                    mem::size_of::<u32>() as u32,
+                    next_cb(cb),
+                );
+                cb += 1;
+
+                // Lite channels are limited to 64 KiB per control block, so longer
+                // delays are split up
+                let mut remaining = steps;
+                while remaining > 0 {
+                    let words = remaining.min(MAX_CB_WORDS);
+
+                    write_cb(
+                        &mem,
+                        CB_WORDS * (cb + 1),
+                        paced_ti,
+                        mem.bus(WAVEFORM_DUMMY_OFFSET),
+                        fifo,
+                        (words * mem::size_of::<u32>()) as u32,
+                        next_cb(cb),
+                    );
+                    cb += 1;
+                    remaining -= words;
+                }
+            }
+        }
+
+        controller.start(cb_bus(0));
+
+        Ok(DmaWaveform {
+            controller,
+            _mem: mem,
+        })
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        !self.controller.is_active()
+    }
+
+    pub(crate) fn wait(&self) {
+        while !self.is_finished() {
+            thread::sleep(WAVEFORM_POLL_INTERVAL);
+        }
+
+        // The last control block completes as soon as the FIFO accepts the final
+        // words, so wait for the FIFO to drain.
+        thread::sleep(self.controller.step() * self.controller.fifo_words() as u32);
+    }
+
+    pub(crate) fn stop(&mut self) {
+        self.controller.stop();
+    }
+}
+
+// The memory-mapped registers are only accessed through volatile reads and writes.
+unsafe impl Send for DmaWaveform {}
+unsafe impl Sync for DmaWaveform {}
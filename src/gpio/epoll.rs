@@ -25,13 +25,43 @@ use std::result;
 use std::time::Duration;
 
 use libc::{
-    self, c_int, c_void, EFD_NONBLOCK, EFD_SEMAPHORE, EPOLL_CTL_ADD, EPOLL_CTL_DEL, EPOLL_CTL_MOD,
+    self, c_int, c_void, nfds_t, pollfd, EFD_NONBLOCK, EFD_SEMAPHORE, EPOLL_CTL_ADD, EPOLL_CTL_DEL,
+    EPOLL_CTL_MOD, POLLIN, POLLPRI,
 };
 
 pub use libc::{epoll_event, EPOLLERR, EPOLLET, EPOLLIN, EPOLLONESHOT, EPOLLOUT, EPOLLPRI};
 
 pub type Result<T> = result::Result<T, io::Error>;
 
+fn timeout_ms(timeout: Option<Duration>) -> c_int {
+    if let Some(duration) = timeout {
+        (duration.as_secs() * 1_000 + u64::from(duration.subsec_millis())) as c_int
+    } else {
+        -1
+    }
+}
+
+// Blocks until any of the specified fds becomes readable, or a timeout occurs.
+// Returns true if at least one of the fds is readable.
+pub fn poll_readable(fds: &[c_int], timeout: Option<Duration>) -> Result<bool> {
+    let mut pollfds: Vec<pollfd> = fds
+        .iter()
+        .map(|&fd| pollfd {
+            fd,
+            events: POLLIN | POLLPRI,
+            revents: 0,
+        })
+        .collect();
+
+    Ok(parse_retval!(unsafe {
+        libc::poll(
+            pollfds.as_mut_ptr(),
+            pollfds.len() as nfds_t,
+            timeout_ms(timeout),
+        )
+    })? > 0)
+}
+
 // We're using EventFd to wake up another thread
 // that's waiting for epoll_wait() to return.
 #[derive(Debug)]
@@ -114,14 +144,13 @@ impl Epoll {
             return Ok(0);
         }
 
-        let timeout = if let Some(duration) = timeout {
-            (duration.as_secs() * 1_000 + u64::from(duration.subsec_millis())) as c_int
-        } else {
-            -1
-        };
-
         Ok(parse_retval!(unsafe {
-            libc::epoll_wait(self.fd, events.as_mut_ptr(), events.len() as c_int, timeout)
+            libc::epoll_wait(
+                self.fd,
+                events.as_mut_ptr(),
+                events.len() as c_int,
+                timeout_ms(timeout),
+            )
         })? as usize)
     }
 }
@@ -0,0 +1,830 @@
+// Copyright (c) 2017-2019 Rene van der Meer
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL
+// THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use std::fmt;
+
+use crate::gpio::Mode::{self, Alt0, Alt1, Alt2, Alt3, Alt4, Alt5};
+use crate::system::SoC;
+
+use self::Function::*;
+
+/// Peripheral functions that can be assigned to a GPIO pin by selecting one of its
+/// alternate function modes.
+///
+/// The variant names are based on the peripheral and signal names used in the
+/// BCM2835 and BCM2711 datasheets. For instance, [`Spi0Mosi`] is the MOSI signal of
+/// the SPI0 peripheral, and [`Uart0Tx`] is the PL011 UART's TXD0 signal. The PWM
+/// variants follow the BCM2711 naming scheme, where [`Pwm0Channel0`] and
+/// [`Pwm0Channel1`] are the two channels of the first PWM block, which the BCM2835
+/// datasheet calls PWM0 and PWM1. [`Pwm1Channel0`] and [`Pwm1Channel1`] belong to the
+/// second PWM block, which is only available on the BCM2711. Variants that contain a number
+/// refer to a single line of a parallel bus, such as [`SmiData`] for the secondary
+/// memory interface, or [`DpiData`] for the parallel display interface.
+///
+/// `Function` might be extended with additional variants in a minor or
+/// patch revision, and must not be exhaustively matched against.
+/// Instead, add a `_` catch-all arm to match future variants.
+///
+/// [`Spi0Mosi`]: #variant.Spi0Mosi
+/// [`Uart0Tx`]: #variant.Uart0Tx
+/// [`Pwm0Channel0`]: #variant.Pwm0Channel0
+/// [`Pwm0Channel1`]: #variant.Pwm0Channel1
+/// [`Pwm1Channel0`]: #variant.Pwm1Channel0
+/// [`Pwm1Channel1`]: #variant.Pwm1Channel1
+/// [`SmiData`]: #variant.SmiData
+/// [`DpiData`]: #variant.DpiData
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Function {
+    I2c0Sda,
+    I2c0Scl,
+    I2c1Sda,
+    I2c1Scl,
+    I2c3Sda,
+    I2c3Scl,
+    I2c4Sda,
+    I2c4Scl,
+    I2c5Sda,
+    I2c5Scl,
+    I2c6Sda,
+    I2c6Scl,
+    Spi0Ce0,
+    Spi0Ce1,
+    Spi0Ce2,
+    Spi0Miso,
+    Spi0Mosi,
+    Spi0Sclk,
+    Spi1Ce0,
+    Spi1Ce1,
+    Spi1Ce2,
+    Spi1Miso,
+    Spi1Mosi,
+    Spi1Sclk,
+    Spi2Ce0,
+    Spi2Ce1,
+    Spi2Ce2,
+    Spi2Miso,
+    Spi2Mosi,
+    Spi2Sclk,
+    Spi3Ce0,
+    Spi3Ce1,
+    Spi3Miso,
+    Spi3Mosi,
+    Spi3Sclk,
+    Spi4Ce0,
+    Spi4Ce1,
+    Spi4Miso,
+    Spi4Mosi,
+    Spi4Sclk,
+    Spi5Ce0,
+    Spi5Ce1,
+    Spi5Miso,
+    Spi5Mosi,
+    Spi5Sclk,
+    Spi6Ce0,
+    Spi6Ce1,
+    Spi6Miso,
+    Spi6Mosi,
+    Spi6Sclk,
+    Uart0Tx,
+    Uart0Rx,
+    Uart0Cts,
+    Uart0Rts,
+    Uart1Tx,
+    Uart1Rx,
+    Uart1Cts,
+    Uart1Rts,
+    Uart2Tx,
+    Uart2Rx,
+    Uart2Cts,
+    Uart2Rts,
+    Uart3Tx,
+    Uart3Rx,
+    Uart3Cts,
+    Uart3Rts,
+    Uart4Tx,
+    Uart4Rx,
+    Uart4Cts,
+    Uart4Rts,
+    Uart5Tx,
+    Uart5Rx,
+    Uart5Cts,
+    Uart5Rts,
+    /// Channel 0 of the first PWM block (PWM0_0), labeled PWM0 on the BCM2835.
+    Pwm0Channel0,
+    /// Channel 1 of the first PWM block (PWM0_1), labeled PWM1 on the BCM2835.
+    Pwm0Channel1,
+    /// Channel 0 of the second PWM block (PWM1_0), only available on the BCM2711.
+    Pwm1Channel0,
+    /// Channel 1 of the second PWM block (PWM1_1), only available on the BCM2711.
+    Pwm1Channel1,
+    Gpclk0,
+    Gpclk1,
+    Gpclk2,
+    PcmClk,
+    PcmDin,
+    PcmDout,
+    PcmFs,
+    BscSlaveCe,
+    BscSlaveMiso,
+    BscSlaveSda,
+    BscSlaveScl,
+    Sd0Clk,
+    Sd0Cmd,
+    Sd0Data(u8),
+    Sd1Clk,
+    Sd1Cmd,
+    Sd1Data(u8),
+    SmiAddress(u8),
+    SmiData(u8),
+    SmiOe,
+    SmiWe,
+    DpiData(u8),
+    DpiDe,
+    DpiHsync,
+    DpiPclk,
+    DpiVsync,
+    ArmRtck,
+    ArmTck,
+    ArmTdi,
+    ArmTdo,
+    ArmTms,
+    ArmTrst,
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Function::I2c0Sda => write!(f, "SDA0"),
+            Function::I2c0Scl => write!(f, "SCL0"),
+            Function::I2c1Sda => write!(f, "SDA1"),
+            Function::I2c1Scl => write!(f, "SCL1"),
+            Function::I2c3Sda => write!(f, "SDA3"),
+            Function::I2c3Scl => write!(f, "SCL3"),
+            Function::I2c4Sda => write!(f, "SDA4"),
+            Function::I2c4Scl => write!(f, "SCL4"),
+            Function::I2c5Sda => write!(f, "SDA5"),
+            Function::I2c5Scl => write!(f, "SCL5"),
+            Function::I2c6Sda => write!(f, "SDA6"),
+            Function::I2c6Scl => write!(f, "SCL6"),
+            Function::Spi0Ce0 => write!(f, "SPI0_CE0_N"),
+            Function::Spi0Ce1 => write!(f, "SPI0_CE1_N"),
+            Function::Spi0Ce2 => write!(f, "SPI0_CE2_N"),
+            Function::Spi0Miso => write!(f, "SPI0_MISO"),
+            Function::Spi0Mosi => write!(f, "SPI0_MOSI"),
+            Function::Spi0Sclk => write!(f, "SPI0_SCLK"),
+            Function::Spi1Ce0 => write!(f, "SPI1_CE0_N"),
+            Function::Spi1Ce1 => write!(f, "SPI1_CE1_N"),
+            Function::Spi1Ce2 => write!(f, "SPI1_CE2_N"),
+            Function::Spi1Miso => write!(f, "SPI1_MISO"),
+            Function::Spi1Mosi => write!(f, "SPI1_MOSI"),
+            Function::Spi1Sclk => write!(f, "SPI1_SCLK"),
+            Function::Spi2Ce0 => write!(f, "SPI2_CE0_N"),
+            Function::Spi2Ce1 => write!(f, "SPI2_CE1_N"),
+            Function::Spi2Ce2 => write!(f, "SPI2_CE2_N"),
+            Function::Spi2Miso => write!(f, "SPI2_MISO"),
+            Function::Spi2Mosi => write!(f, "SPI2_MOSI"),
+            Function::Spi2Sclk => write!(f, "SPI2_SCLK"),
+            Function::Spi3Ce0 => write!(f, "SPI3_CE0_N"),
+            Function::Spi3Ce1 => write!(f, "SPI3_CE1_N"),
+            Function::Spi3Miso => write!(f, "SPI3_MISO"),
+            Function::Spi3Mosi => write!(f, "SPI3_MOSI"),
+            Function::Spi3Sclk => write!(f, "SPI3_SCLK"),
+            Function::Spi4Ce0 => write!(f, "SPI4_CE0_N"),
+            Function::Spi4Ce1 => write!(f, "SPI4_CE1_N"),
+            Function::Spi4Miso => write!(f, "SPI4_MISO"),
+            Function::Spi4Mosi => write!(f, "SPI4_MOSI"),
+            Function::Spi4Sclk => write!(f, "SPI4_SCLK"),
+            Function::Spi5Ce0 => write!(f, "SPI5_CE0_N"),
+            Function::Spi5Ce1 => write!(f, "SPI5_CE1_N"),
+            Function::Spi5Miso => write!(f, "SPI5_MISO"),
+            Function::Spi5Mosi => write!(f, "SPI5_MOSI"),
+            Function::Spi5Sclk => write!(f, "SPI5_SCLK"),
+            Function::Spi6Ce0 => write!(f, "SPI6_CE0_N"),
+            Function::Spi6Ce1 => write!(f, "SPI6_CE1_N"),
+            Function::Spi6Miso => write!(f, "SPI6_MISO"),
+            Function::Spi6Mosi => write!(f, "SPI6_MOSI"),
+            Function::Spi6Sclk => write!(f, "SPI6_SCLK"),
+            Function::Uart0Tx => write!(f, "TXD0"),
+            Function::Uart0Rx => write!(f, "RXD0"),
+            Function::Uart0Cts => write!(f, "CTS0"),
+            Function::Uart0Rts => write!(f, "RTS0"),
+            Function::Uart1Tx => write!(f, "TXD1"),
+            Function::Uart1Rx => write!(f, "RXD1"),
+            Function::Uart1Cts => write!(f, "CTS1"),
+            Function::Uart1Rts => write!(f, "RTS1"),
+            Function::Uart2Tx => write!(f, "TXD2"),
+            Function::Uart2Rx => write!(f, "RXD2"),
+            Function::Uart2Cts => write!(f, "CTS2"),
+            Function::Uart2Rts => write!(f, "RTS2"),
+            Function::Uart3Tx => write!(f, "TXD3"),
+            Function::Uart3Rx => write!(f, "RXD3"),
+            Function::Uart3Cts => write!(f, "CTS3"),
+            Function::Uart3Rts => write!(f, "RTS3"),
+            Function::Uart4Tx => write!(f, "TXD4"),
+            Function::Uart4Rx => write!(f, "RXD4"),
+            Function::Uart4Cts => write!(f, "CTS4"),
+            Function::Uart4Rts => write!(f, "RTS4"),
+            Function::Uart5Tx => write!(f, "TXD5"),
+            Function::Uart5Rx => write!(f, "RXD5"),
+            Function::Uart5Cts => write!(f, "CTS5"),
+            Function::Uart5Rts => write!(f, "RTS5"),
+            Function::Pwm0Channel0 => write!(f, "PWM0_0"),
+            Function::Pwm0Channel1 => write!(f, "PWM0_1"),
+            Function::Pwm1Channel0 => write!(f, "PWM1_0"),
+            Function::Pwm1Channel1 => write!(f, "PWM1_1"),
+            Function::Gpclk0 => write!(f, "GPCLK0"),
+            Function::Gpclk1 => write!(f, "GPCLK1"),
+            Function::Gpclk2 => write!(f, "GPCLK2"),
+            Function::PcmClk => write!(f, "PCM_CLK"),
+            Function::PcmDin => write!(f, "PCM_DIN"),
+            Function::PcmDout => write!(f, "PCM_DOUT"),
+            Function::PcmFs => write!(f, "PCM_FS"),
+            Function::BscSlaveCe => write!(f, "BSCSL_CE"),
+            Function::BscSlaveMiso => write!(f, "BSCSL_MISO"),
+            Function::BscSlaveSda => write!(f, "BSCSL_SDA"),
+            Function::BscSlaveScl => write!(f, "BSCSL_SCL"),
+            Function::Sd0Clk => write!(f, "SD0_CLK"),
+            Function::Sd0Cmd => write!(f, "SD0_CMD"),
+            Function::Sd0Data(idx) => write!(f, "SD0_DAT{}", idx),
+            Function::Sd1Clk => write!(f, "SD1_CLK"),
+            Function::Sd1Cmd => write!(f, "SD1_CMD"),
+            Function::Sd1Data(idx) => write!(f, "SD1_DAT{}", idx),
+            Function::SmiAddress(idx) => write!(f, "SA{}", idx),
+            Function::SmiData(idx) => write!(f, "SD{}", idx),
+            Function::SmiOe => write!(f, "SOE_N"),
+            Function::SmiWe => write!(f, "SWE_N"),
+            Function::DpiData(idx) => write!(f, "DPI_D{}", idx),
+            Function::DpiDe => write!(f, "DPI_DE"),
+            Function::DpiHsync => write!(f, "DPI_HSYNC"),
+            Function::DpiPclk => write!(f, "DPI_PCLK"),
+            Function::DpiVsync => write!(f, "DPI_VSYNC"),
+            Function::ArmRtck => write!(f, "ARM_RTCK"),
+            Function::ArmTck => write!(f, "ARM_TCK"),
+            Function::ArmTdi => write!(f, "ARM_TDI"),
+            Function::ArmTdo => write!(f, "ARM_TDO"),
+            Function::ArmTms => write!(f, "ARM_TMS"),
+            Function::ArmTrst => write!(f, "ARM_TRST"),
+            Function::__Nonexhaustive => write!(f, "__Nonexhaustive"),
+        }
+    }
+}
+
+// Alternate functions for the BCM2835, BCM2836 and BCM2837. GPIO 46-53 are only
+// used internally.
+fn bcm2835_function(pin: u8, mode: Mode) -> Option<Function> {
+    let function = match (pin, mode) {
+        (0, Alt0) => I2c0Sda,
+        (0, Alt1) => SmiAddress(5),
+        (0, Alt2) => DpiPclk,
+        (1, Alt0) => I2c0Scl,
+        (1, Alt1) => SmiAddress(4),
+        (1, Alt2) => DpiDe,
+        (2, Alt0) => I2c1Sda,
+        (2, Alt1) => SmiAddress(3),
+        (2, Alt2) => DpiVsync,
+        (3, Alt0) => I2c1Scl,
+        (3, Alt1) => SmiAddress(2),
+        (3, Alt2) => DpiHsync,
+        (4, Alt0) => Gpclk0,
+        (4, Alt1) => SmiAddress(1),
+        (4, Alt2) => DpiData(0),
+        (4, Alt5) => ArmTdi,
+        (5, Alt0) => Gpclk1,
+        (5, Alt1) => SmiAddress(0),
+        (5, Alt2) => DpiData(1),
+        (5, Alt5) => ArmTdo,
+        (6, Alt0) => Gpclk2,
+        (6, Alt1) => SmiOe,
+        (6, Alt2) => DpiData(2),
+        (6, Alt5) => ArmRtck,
+        (7, Alt0) => Spi0Ce1,
+        (7, Alt1) => SmiWe,
+        (7, Alt2) => DpiData(3),
+        (8, Alt0) => Spi0Ce0,
+        (8, Alt1) => SmiData(0),
+        (8, Alt2) => DpiData(4),
+        (9, Alt0) => Spi0Miso,
+        (9, Alt1) => SmiData(1),
+        (9, Alt2) => DpiData(5),
+        (10, Alt0) => Spi0Mosi,
+        (10, Alt1) => SmiData(2),
+        (10, Alt2) => DpiData(6),
+        (11, Alt0) => Spi0Sclk,
+        (11, Alt1) => SmiData(3),
+        (11, Alt2) => DpiData(7),
+        (12, Alt0) => Pwm0Channel0,
+        (12, Alt1) => SmiData(4),
+        (12, Alt2) => DpiData(8),
+        (12, Alt5) => ArmTms,
+        (13, Alt0) => Pwm0Channel1,
+        (13, Alt1) => SmiData(5),
+        (13, Alt2) => DpiData(9),
+        (13, Alt5) => ArmTck,
+        (14, Alt0) => Uart0Tx,
+        (14, Alt1) => SmiData(6),
+        (14, Alt2) => DpiData(10),
+        (14, Alt5) => Uart1Tx,
+        (15, Alt0) => Uart0Rx,
+        (15, Alt1) => SmiData(7),
+        (15, Alt2) => DpiData(11),
+        (15, Alt5) => Uart1Rx,
+        (16, Alt1) => SmiData(8),
+        (16, Alt2) => DpiData(12),
+        (16, Alt3) => Uart0Cts,
+        (16, Alt4) => Spi1Ce2,
+        (16, Alt5) => Uart1Cts,
+        (17, Alt1) => SmiData(9),
+        (17, Alt2) => DpiData(13),
+        (17, Alt3) => Uart0Rts,
+        (17, Alt4) => Spi1Ce1,
+        (17, Alt5) => Uart1Rts,
+        (18, Alt0) => PcmClk,
+        (18, Alt1) => SmiData(10),
+        (18, Alt2) => DpiData(14),
+        (18, Alt3) => BscSlaveSda,
+        (18, Alt4) => Spi1Ce0,
+        (18, Alt5) => Pwm0Channel0,
+        (19, Alt0) => PcmFs,
+        (19, Alt1) => SmiData(11),
+        (19, Alt2) => DpiData(15),
+        (19, Alt3) => BscSlaveScl,
+        (19, Alt4) => Spi1Miso,
+        (19, Alt5) => Pwm0Channel1,
+        (20, Alt0) => PcmDin,
+        (20, Alt1) => SmiData(12),
+        (20, Alt2) => DpiData(16),
+        (20, Alt3) => BscSlaveMiso,
+        (20, Alt4) => Spi1Mosi,
+        (20, Alt5) => Gpclk0,
+        (21, Alt0) => PcmDout,
+        (21, Alt1) => SmiData(13),
+        (21, Alt2) => DpiData(17),
+        (21, Alt3) => BscSlaveCe,
+        (21, Alt4) => Spi1Sclk,
+        (21, Alt5) => Gpclk1,
+        (22, Alt1) => SmiData(14),
+        (22, Alt2) => DpiData(18),
+        (22, Alt3) => Sd1Clk,
+        (22, Alt4) => ArmTrst,
+        (23, Alt1) => SmiData(15),
+        (23, Alt2) => DpiData(19),
+        (23, Alt3) => Sd1Cmd,
+        (23, Alt4) => ArmRtck,
+        (24, Alt1) => SmiData(16),
+        (24, Alt2) => DpiData(20),
+        (24, Alt3) => Sd1Data(0),
+        (24, Alt4) => ArmTdo,
+        (25, Alt1) => SmiData(17),
+        (25, Alt2) => DpiData(21),
+        (25, Alt3) => Sd1Data(1),
+        (25, Alt4) => ArmTck,
+        (26, Alt2) => DpiData(22),
+        (26, Alt3) => Sd1Data(2),
+        (26, Alt4) => ArmTdi,
+        (27, Alt2) => DpiData(23),
+        (27, Alt3) => Sd1Data(3),
+        (27, Alt4) => ArmTms,
+        (28, Alt0) => I2c0Sda,
+        (28, Alt1) => SmiAddress(5),
+        (28, Alt2) => PcmClk,
+        (29, Alt0) => I2c0Scl,
+        (29, Alt1) => SmiAddress(4),
+        (29, Alt2) => PcmFs,
+        (30, Alt1) => SmiAddress(3),
+        (30, Alt2) => PcmDin,
+        (30, Alt3) => Uart0Cts,
+        (30, Alt5) => Uart1Cts,
+        (31, Alt1) => SmiAddress(2),
+        (31, Alt2) => PcmDout,
+        (31, Alt3) => Uart0Rts,
+        (31, Alt5) => Uart1Rts,
+        (32, Alt0) => Gpclk0,
+        (32, Alt1) => SmiAddress(1),
+        (32, Alt3) => Uart0Tx,
+        (32, Alt5) => Uart1Tx,
+        (33, Alt1) => SmiAddress(0),
+        (33, Alt3) => Uart0Rx,
+        (33, Alt5) => Uart1Rx,
+        (34, Alt0) => Gpclk0,
+        (34, Alt1) => SmiOe,
+        (34, Alt3) => Sd1Clk,
+        (35, Alt0) => Spi0Ce1,
+        (35, Alt1) => SmiWe,
+        (35, Alt3) => Sd1Cmd,
+        (36, Alt0) => Spi0Ce0,
+        (36, Alt1) => SmiData(0),
+        (36, Alt2) => Uart0Tx,
+        (36, Alt3) => Sd1Data(0),
+        (37, Alt0) => Spi0Miso,
+        (37, Alt1) => SmiData(1),
+        (37, Alt2) => Uart0Rx,
+        (37, Alt3) => Sd1Data(1),
+        (38, Alt0) => Spi0Mosi,
+        (38, Alt1) => SmiData(2),
+        (38, Alt2) => Uart0Rts,
+        (38, Alt3) => Sd1Data(2),
+        (39, Alt0) => Spi0Sclk,
+        (39, Alt1) => SmiData(3),
+        (39, Alt2) => Uart0Cts,
+        (39, Alt3) => Sd1Data(3),
+        (40, Alt0) => Pwm0Channel0,
+        (40, Alt1) => SmiData(4),
+        (40, Alt3) => Sd1Data(4),
+        (40, Alt4) => Spi2Miso,
+        (40, Alt5) => Uart1Tx,
+        (41, Alt0) => Pwm0Channel1,
+        (41, Alt1) => SmiData(5),
+        (41, Alt3) => Sd1Data(5),
+        (41, Alt4) => Spi2Mosi,
+        (41, Alt5) => Uart1Rx,
+        (42, Alt0) => Gpclk1,
+        (42, Alt1) => SmiData(6),
+        (42, Alt3) => Sd1Data(6),
+        (42, Alt4) => Spi2Sclk,
+        (42, Alt5) => Uart1Rts,
+        (43, Alt0) => Gpclk2,
+        (43, Alt1) => SmiData(7),
+        (43, Alt3) => Sd1Data(7),
+        (43, Alt4) => Spi2Ce0,
+        (43, Alt5) => Uart1Cts,
+        (44, Alt0) => Gpclk1,
+        (44, Alt1) => I2c0Sda,
+        (44, Alt2) => I2c1Sda,
+        (44, Alt4) => Spi2Ce1,
+        (45, Alt0) => Pwm0Channel1,
+        (45, Alt1) => I2c0Scl,
+        (45, Alt2) => I2c1Scl,
+        (45, Alt4) => Spi2Ce2,
+        _ => return None,
+    };
+
+    Some(function)
+}
+
+// Alternate functions for the BCM2711. GPIO 28-53 are mostly used internally, but
+// GPIO 40 and 41 carry the Pi 4's analog audio through the second PWM block. The
+// Ethernet (MII/RGMII), SD card control and flash signals aren't represented by
+// Function, and aren't listed here, along with GPIO 54-57.
+fn bcm2711_function(pin: u8, mode: Mode) -> Option<Function> {
+    let function = match (pin, mode) {
+        (0, Alt0) => I2c0Sda,
+        (0, Alt1) => SmiAddress(5),
+        (0, Alt2) => DpiPclk,
+        (0, Alt3) => Spi3Ce0,
+        (0, Alt4) => Uart2Tx,
+        (0, Alt5) => I2c6Sda,
+        (1, Alt0) => I2c0Scl,
+        (1, Alt1) => SmiAddress(4),
+        (1, Alt2) => DpiDe,
+        (1, Alt3) => Spi3Miso,
+        (1, Alt4) => Uart2Rx,
+        (1, Alt5) => I2c6Scl,
+        (2, Alt0) => I2c1Sda,
+        (2, Alt1) => SmiAddress(3),
+        (2, Alt2) => DpiVsync,
+        (2, Alt3) => Spi3Mosi,
+        (2, Alt4) => Uart2Cts,
+        (2, Alt5) => I2c3Sda,
+        (3, Alt0) => I2c1Scl,
+        (3, Alt1) => SmiAddress(2),
+        (3, Alt2) => DpiHsync,
+        (3, Alt3) => Spi3Sclk,
+        (3, Alt4) => Uart2Rts,
+        (3, Alt5) => I2c3Scl,
+        (4, Alt0) => Gpclk0,
+        (4, Alt1) => SmiAddress(1),
+        (4, Alt2) => DpiData(0),
+        (4, Alt3) => Spi4Ce0,
+        (4, Alt4) => Uart3Tx,
+        (4, Alt5) => I2c3Sda,
+        (5, Alt0) => Gpclk1,
+        (5, Alt1) => SmiAddress(0),
+        (5, Alt2) => DpiData(1),
+        (5, Alt3) => Spi4Miso,
+        (5, Alt4) => Uart3Rx,
+        (5, Alt5) => I2c3Scl,
+        (6, Alt0) => Gpclk2,
+        (6, Alt1) => SmiOe,
+        (6, Alt2) => DpiData(2),
+        (6, Alt3) => Spi4Mosi,
+        (6, Alt4) => Uart3Cts,
+        (6, Alt5) => I2c4Sda,
+        (7, Alt0) => Spi0Ce1,
+        (7, Alt1) => SmiWe,
+        (7, Alt2) => DpiData(3),
+        (7, Alt3) => Spi4Sclk,
+        (7, Alt4) => Uart3Rts,
+        (7, Alt5) => I2c4Scl,
+        (8, Alt0) => Spi0Ce0,
+        (8, Alt1) => SmiData(0),
+        (8, Alt2) => DpiData(4),
+        (8, Alt3) => BscSlaveCe,
+        (8, Alt4) => Uart4Tx,
+        (8, Alt5) => I2c4Sda,
+        (9, Alt0) => Spi0Miso,
+        (9, Alt1) => SmiData(1),
+        (9, Alt2) => DpiData(5),
+        (9, Alt3) => BscSlaveMiso,
+        (9, Alt4) => Uart4Rx,
+        (9, Alt5) => I2c4Scl,
+        (10, Alt0) => Spi0Mosi,
+        (10, Alt1) => SmiData(2),
+        (10, Alt2) => DpiData(6),
+        (10, Alt3) => BscSlaveSda,
+        (10, Alt4) => Uart4Cts,
+        (10, Alt5) => I2c5Sda,
+        (11, Alt0) => Spi0Sclk,
+        (11, Alt1) => SmiData(3),
+        (11, Alt2) => DpiData(7),
+        (11, Alt3) => BscSlaveScl,
+        (11, Alt4) => Uart4Rts,
+        (11, Alt5) => I2c5Scl,
+        (12, Alt0) => Pwm0Channel0,
+        (12, Alt1) => SmiData(4),
+        (12, Alt2) => DpiData(8),
+        (12, Alt3) => Spi5Ce0,
+        (12, Alt4) => Uart5Tx,
+        (12, Alt5) => I2c5Sda,
+        (13, Alt0) => Pwm0Channel1,
+        (13, Alt1) => SmiData(5),
+        (13, Alt2) => DpiData(9),
+        (13, Alt3) => Spi5Miso,
+        (13, Alt4) => Uart5Rx,
+        (13, Alt5) => I2c5Scl,
+        (14, Alt0) => Uart0Tx,
+        (14, Alt1) => SmiData(6),
+        (14, Alt2) => DpiData(10),
+        (14, Alt3) => Spi5Mosi,
+        (14, Alt4) => Uart5Cts,
+        (14, Alt5) => Uart1Tx,
+        (15, Alt0) => Uart0Rx,
+        (15, Alt1) => SmiData(7),
+        (15, Alt2) => DpiData(11),
+        (15, Alt3) => Spi5Sclk,
+        (15, Alt4) => Uart5Rts,
+        (15, Alt5) => Uart1Rx,
+        (16, Alt1) => SmiData(8),
+        (16, Alt2) => DpiData(12),
+        (16, Alt3) => Uart0Cts,
+        (16, Alt4) => Spi1Ce2,
+        (16, Alt5) => Uart1Cts,
+        (17, Alt1) => SmiData(9),
+        (17, Alt2) => DpiData(13),
+        (17, Alt3) => Uart0Rts,
+        (17, Alt4) => Spi1Ce1,
+        (17, Alt5) => Uart1Rts,
+        (18, Alt0) => PcmClk,
+        (18, Alt1) => SmiData(10),
+        (18, Alt2) => DpiData(14),
+        (18, Alt3) => Spi6Ce0,
+        (18, Alt4) => Spi1Ce0,
+        (18, Alt5) => Pwm0Channel0,
+        (19, Alt0) => PcmFs,
+        (19, Alt1) => SmiData(11),
+        (19, Alt2) => DpiData(15),
+        (19, Alt3) => Spi6Miso,
+        (19, Alt4) => Spi1Miso,
+        (19, Alt5) => Pwm0Channel1,
+        (20, Alt0) => PcmDin,
+        (20, Alt1) => SmiData(12),
+        (20, Alt2) => DpiData(16),
+        (20, Alt3) => Spi6Mosi,
+        (20, Alt4) => Spi1Mosi,
+        (20, Alt5) => Gpclk0,
+        (21, Alt0) => PcmDout,
+        (21, Alt1) => SmiData(13),
+        (21, Alt2) => DpiData(17),
+        (21, Alt3) => Spi6Sclk,
+        (21, Alt4) => Spi1Sclk,
+        (21, Alt5) => Gpclk1,
+        (22, Alt0) => Sd0Clk,
+        (22, Alt1) => SmiData(14),
+        (22, Alt2) => DpiData(18),
+        (22, Alt3) => Sd1Clk,
+        (22, Alt4) => ArmTrst,
+        (22, Alt5) => I2c6Sda,
+        (23, Alt0) => Sd0Cmd,
+        (23, Alt1) => SmiData(15),
+        (23, Alt2) => DpiData(19),
+        (23, Alt3) => Sd1Cmd,
+        (23, Alt4) => ArmRtck,
+        (23, Alt5) => I2c6Scl,
+        (24, Alt0) => Sd0Data(0),
+        (24, Alt1) => SmiData(16),
+        (24, Alt2) => DpiData(20),
+        (24, Alt3) => Sd1Data(0),
+        (24, Alt4) => ArmTdo,
+        (24, Alt5) => Spi3Ce1,
+        (25, Alt0) => Sd0Data(1),
+        (25, Alt1) => SmiData(17),
+        (25, Alt2) => DpiData(21),
+        (25, Alt3) => Sd1Data(1),
+        (25, Alt4) => ArmTck,
+        (25, Alt5) => Spi4Ce1,
+        (26, Alt0) => Sd0Data(2),
+        (26, Alt2) => DpiData(22),
+        (26, Alt3) => Sd1Data(2),
+        (26, Alt4) => ArmTdi,
+        (26, Alt5) => Spi5Ce1,
+        (27, Alt0) => Sd0Data(3),
+        (27, Alt2) => DpiData(23),
+        (27, Alt3) => Sd1Data(3),
+        (27, Alt4) => ArmTms,
+        (27, Alt5) => Spi6Ce1,
+        (28, Alt0) => I2c0Sda,
+        (28, Alt1) => SmiAddress(5),
+        (28, Alt2) => PcmClk,
+        (29, Alt0) => I2c0Scl,
+        (29, Alt1) => SmiAddress(4),
+        (29, Alt2) => PcmFs,
+        (30, Alt1) => SmiAddress(3),
+        (30, Alt2) => PcmDin,
+        (30, Alt3) => Uart0Cts,
+        (30, Alt5) => Uart1Cts,
+        (31, Alt1) => SmiAddress(2),
+        (31, Alt2) => PcmDout,
+        (31, Alt3) => Uart0Rts,
+        (31, Alt5) => Uart1Rts,
+        (32, Alt0) => Gpclk0,
+        (32, Alt1) => SmiAddress(1),
+        (32, Alt3) => Uart0Tx,
+        (32, Alt5) => Uart1Tx,
+        (33, Alt1) => SmiAddress(0),
+        (33, Alt3) => Uart0Rx,
+        (33, Alt5) => Uart1Rx,
+        (34, Alt0) => Gpclk0,
+        (34, Alt1) => SmiOe,
+        (34, Alt3) => Sd1Clk,
+        (35, Alt0) => Spi0Ce1,
+        (35, Alt1) => SmiWe,
+        (35, Alt3) => Sd1Cmd,
+        (36, Alt0) => Spi0Ce0,
+        (36, Alt1) => SmiData(0),
+        (36, Alt2) => Uart0Tx,
+        (36, Alt3) => Sd1Data(0),
+        (37, Alt0) => Spi0Miso,
+        (37, Alt1) => SmiData(1),
+        (37, Alt2) => Uart0Rx,
+        (37, Alt3) => Sd1Data(1),
+        (38, Alt0) => Spi0Mosi,
+        (38, Alt1) => SmiData(2),
+        (38, Alt2) => Uart0Rts,
+        (38, Alt3) => Sd1Data(2),
+        (39, Alt0) => Spi0Sclk,
+        (39, Alt1) => SmiData(3),
+        (39, Alt2) => Uart0Cts,
+        (39, Alt3) => Sd1Data(3),
+        (40, Alt0) => Pwm1Channel0,
+        (40, Alt1) => SmiData(4),
+        (40, Alt3) => Sd1Data(4),
+        (40, Alt4) => Spi0Miso,
+        (40, Alt5) => Uart1Tx,
+        (41, Alt0) => Pwm1Channel1,
+        (41, Alt1) => SmiData(5),
+        (41, Alt3) => Sd1Data(5),
+        (41, Alt4) => Spi0Mosi,
+        (41, Alt5) => Uart1Rx,
+        (42, Alt0) => Gpclk1,
+        (42, Alt1) => SmiData(6),
+        (42, Alt3) => Sd1Data(6),
+        (42, Alt4) => Spi0Sclk,
+        (42, Alt5) => Uart1Rts,
+        (43, Alt0) => Gpclk2,
+        (43, Alt1) => SmiData(7),
+        (43, Alt3) => Sd1Data(7),
+        (43, Alt4) => Spi0Ce0,
+        (43, Alt5) => Uart1Cts,
+        (44, Alt0) => Gpclk1,
+        (44, Alt1) => I2c0Sda,
+        (44, Alt2) => I2c1Sda,
+        (44, Alt4) => Spi0Ce1,
+        (45, Alt0) => Pwm0Channel1,
+        (45, Alt1) => I2c0Scl,
+        (45, Alt2) => I2c1Scl,
+        (45, Alt4) => Spi0Ce2,
+        (46, Alt0) => I2c0Sda,
+        (46, Alt1) => I2c1Sda,
+        (46, Alt2) => Spi0Ce0,
+        (46, Alt5) => Spi2Ce1,
+        (47, Alt0) => I2c0Scl,
+        (47, Alt1) => I2c1Scl,
+        (47, Alt2) => Spi0Miso,
+        (47, Alt5) => Spi2Ce0,
+        (48, Alt0) => Sd0Clk,
+        (48, Alt2) => Spi0Mosi,
+        (48, Alt3) => Sd1Clk,
+        (48, Alt4) => ArmTrst,
+        (48, Alt5) => Spi2Sclk,
+        (49, Alt0) => Sd0Cmd,
+        (49, Alt1) => Gpclk0,
+        (49, Alt2) => Spi0Sclk,
+        (49, Alt3) => Sd1Cmd,
+        (49, Alt4) => ArmRtck,
+        (49, Alt5) => Spi2Mosi,
+        (50, Alt0) => Sd0Data(0),
+        (50, Alt1) => Gpclk1,
+        (50, Alt2) => PcmClk,
+        (50, Alt3) => Sd1Data(0),
+        (50, Alt4) => ArmTdo,
+        (50, Alt5) => Spi2Miso,
+        (51, Alt0) => Sd0Data(1),
+        (51, Alt1) => Gpclk2,
+        (51, Alt2) => PcmFs,
+        (51, Alt3) => Sd1Data(1),
+        (51, Alt4) => ArmTck,
+        (52, Alt0) => Sd0Data(2),
+        (52, Alt1) => Pwm0Channel0,
+        (52, Alt2) => PcmDin,
+        (52, Alt3) => Sd1Data(2),
+        (52, Alt4) => ArmTdi,
+        (53, Alt0) => Sd0Data(3),
+        (53, Alt1) => Pwm0Channel1,
+        (53, Alt2) => PcmDout,
+        (53, Alt3) => Sd1Data(3),
+        (53, Alt4) => ArmTms,
+        _ => return None,
+    };
+
+    Some(function)
+}
+
+// Returns the number of GPIO pins with documented alternate functions
+fn max_pins(soc: SoC) -> u8 {
+    match soc {
+        SoC::Bcm2835 | SoC::Bcm2836 | SoC::Bcm2837A1 | SoC::Bcm2837B0 => 46,
+        SoC::Bcm2711 => 54,
+        _ => 0,
+    }
+}
+
+const ALT_MODES: [Mode; 6] = [Alt0, Alt1, Alt2, Alt3, Alt4, Alt5];
+
+/// Returns the peripheral function that's assigned to the specified BCM GPIO pin
+/// when it's configured for the specified alternate function mode.
+///
+/// Returns `None` if `mode` isn't one of the alternate function modes, or if the
+/// function is reserved or not documented for the specified SoC. Functions are
+/// available for GPIO 0-45 on the BCM2835, BCM2836 and BCM2837, and for GPIO 0-53
+/// on the BCM2711.
+///
+/// ```
+/// use rppal::gpio::{alt_function, Function, Mode};
+/// use rppal::system::SoC;
+///
+/// assert_eq!(alt_function(SoC::Bcm2837B0, 18, Mode::Alt5), Some(Function::Pwm0Channel0));
+/// assert_eq!(alt_function(SoC::Bcm2837B0, 14, Mode::Alt0), Some(Function::Uart0Tx));
+/// ```
+pub fn alt_function(soc: SoC, pin: u8, mode: Mode) -> Option<Function> {
+    match soc {
+        SoC::Bcm2835 | SoC::Bcm2836 | SoC::Bcm2837A1 | SoC::Bcm2837B0 => {
+            bcm2835_function(pin, mode)
+        }
+        SoC::Bcm2711 => bcm2711_function(pin, mode),
+        _ => None,
+    }
+}
+
+/// Returns all BCM GPIO pins and alternate function modes that provide the
+/// specified peripheral function, ordered by pin number.
+///
+/// ```
+/// use rppal::gpio::{function_pins, Function, Mode};
+/// use rppal::system::SoC;
+///
+/// assert_eq!(
+///     function_pins(SoC::Bcm2711, Function::Pwm0Channel0),
+///     vec![(12, Mode::Alt0), (18, Mode::Alt5), (52, Mode::Alt1)]
+/// );
+/// ```
+pub fn function_pins(soc: SoC, function: Function) -> Vec<(u8, Mode)> {
+    let mut pins = Vec::new();
+
+    for pin in 0..max_pins(soc) {
+        for &mode in ALT_MODES.iter() {
+            if alt_function(soc, pin, mode) == Some(function) {
+                pins.push((pin, mode));
+            }
+        }
+    }
+
+    pins
+}
@@ -0,0 +1,302 @@
+// Copyright (c) 2017-2019 Rene van der Meer
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL
+// THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Pin numbering for the Raspberry Pi's GPIO header.
+//!
+//! [`Gpio`] identifies pins by their BCM GPIO pin number. `Header` translates between
+//! BCM GPIO pin numbers, physical pin numbers on the GPIO header, and the pin numbers
+//! used by the wiringPi library, based on the Raspberry Pi model.
+//!
+//! The Raspberry Pi A, B Rev 1 and B Rev 2 have a 26-pin header (P1). The A and B Rev 2
+//! also have an 8-pin P5 header, which exposes GPIO 28-31. All later models, except for
+//! the Compute Modules, have a 40-pin header (J8). The first 26 pins of the 40-pin
+//! header match the 26-pin header found on the B Rev 2.
+//!
+//! Physical pin numbers start at 1.
+//!
+//! ## Examples
+//!
+//! ```
+//! use rppal::gpio::header::{Header, PinType};
+//! use rppal::system::Model;
+//!
+//! let header = Header::for_model(Model::RaspberryPi3B).unwrap();
+//!
+//! assert_eq!(header.pin_type(11), Some(PinType::Gpio(17)));
+//! assert_eq!(header.physical_to_bcm(11), Some(17));
+//! assert_eq!(header.bcm_to_physical(17), Some(11));
+//! assert_eq!(header.wiringpi_to_bcm(0), Some(17));
+//! ```
+//!
+//! [`Gpio`]: ../struct.Gpio.html
+
+use std::fmt;
+
+use crate::gpio::{Error, Result};
+use crate::system::{DeviceInfo, Model};
+
+/// Pin types found on the GPIO header.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum PinType {
+    /// GPIO pin, identified by its BCM GPIO pin number.
+    Gpio(u8),
+    /// Ground.
+    Ground,
+    /// 3.3 V power.
+    Power3v3,
+    /// 5 V power.
+    Power5v,
+    /// Do not connect.
+    ///
+    /// Pins 4, 9, 14, 17, 20 and 25 on the Raspberry Pi B Rev 1's P1 header were
+    /// reserved for future use, and later became power or ground pins.
+    DoNotConnect,
+}
+
+impl fmt::Display for PinType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            PinType::Gpio(pin) => f.pad(&format!("GPIO{}", pin)),
+            PinType::Ground => f.pad("GND"),
+            PinType::Power3v3 => f.pad("3.3 V"),
+            PinType::Power5v => f.pad("5 V"),
+            PinType::DoNotConnect => f.pad("DNC"),
+        }
+    }
+}
+
+const HEADER: [PinType; 40] = [
+    PinType::Power3v3, // Physical pin 1
+    PinType::Power5v,  // Physical pin 2
+    PinType::Gpio(2),  // Physical pin 3
+    PinType::Power5v,  // Physical pin 4
+    PinType::Gpio(3),  // Physical pin 5
+    PinType::Ground,   // Physical pin 6
+    PinType::Gpio(4),  // Physical pin 7
+    PinType::Gpio(14), // Physical pin 8
+    PinType::Ground,   // Physical pin 9
+    PinType::Gpio(15), // Physical pin 10
+    PinType::Gpio(17), // Physical pin 11
+    PinType::Gpio(18), // Physical pin 12
+    PinType::Gpio(27), // Physical pin 13
+    PinType::Ground,   // Physical pin 14
+    PinType::Gpio(22), // Physical pin 15
+    PinType::Gpio(23), // Physical pin 16
+    PinType::Power3v3, // Physical pin 17
+    PinType::Gpio(24), // Physical pin 18
+    PinType::Gpio(10), // Physical pin 19
+    PinType::Ground,   // Physical pin 20
+    PinType::Gpio(9),  // Physical pin 21
+    PinType::Gpio(25), // Physical pin 22
+    PinType::Gpio(11), // Physical pin 23
+    PinType::Gpio(8),  // Physical pin 24
+    PinType::Ground,   // Physical pin 25
+    PinType::Gpio(7),  // Physical pin 26
+    PinType::Gpio(0),  // Physical pin 27
+    PinType::Gpio(1),  // Physical pin 28
+    PinType::Gpio(5),  // Physical pin 29
+    PinType::Ground,   // Physical pin 30
+    PinType::Gpio(6),  // Physical pin 31
+    PinType::Gpio(12), // Physical pin 32
+    PinType::Gpio(13), // Physical pin 33
+    PinType::Ground,   // Physical pin 34
+    PinType::Gpio(19), // Physical pin 35
+    PinType::Gpio(16), // Physical pin 36
+    PinType::Gpio(26), // Physical pin 37
+    PinType::Gpio(20), // Physical pin 38
+    PinType::Ground,   // Physical pin 39
+    PinType::Gpio(21), // Physical pin 40
+];
+
+const HEADER_P5: [PinType; 8] = [
+    PinType::Power5v,  // Physical pin 1
+    PinType::Power3v3, // Physical pin 2
+    PinType::Gpio(28), // Physical pin 3
+    PinType::Gpio(29), // Physical pin 4
+    PinType::Gpio(30), // Physical pin 5
+    PinType::Gpio(31), // Physical pin 6
+    PinType::Ground,   // Physical pin 7
+    PinType::Ground,   // Physical pin 8
+];
+
+const MAX_PINS_SHORT: usize = 26;
+const MAX_PINS_LONG: usize = 40;
+
+// BCM GPIO pin numbers indexed by wiringPi pin number
+const WIRINGPI_REV1: [u8; 17] = [17, 18, 21, 22, 23, 24, 25, 4, 0, 1, 8, 7, 10, 9, 11, 14, 15];
+const WIRINGPI_REV2: [u8; 32] = [
+    17, 18, 27, 22, 23, 24, 25, 4, 2, 3, 8, 7, 10, 9, 11, 14, 15, 28, 29, 30, 31, 5, 6, 13, 19, 26,
+    12, 16, 20, 21, 0, 1,
+];
+
+/// GPIO header layout for a specific Raspberry Pi model.
+///
+/// `Header` translates between BCM GPIO pin numbers, physical pin numbers and
+/// wiringPi pin numbers. More information can be found in the [module documentation].
+///
+/// [module documentation]: index.html
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Header {
+    model: Model,
+    pins: Vec<PinType>,
+    p5_pins: Vec<PinType>,
+    wiringpi: &'static [u8],
+}
+
+impl Header {
+    /// Constructs a new `Header` for the Raspberry Pi model the code is running on.
+    ///
+    /// Returns `Err(`[`Error::UnknownModel`]`)` if the model can't be identified, or
+    /// if it doesn't have a standard GPIO header, such as the Compute Modules.
+    ///
+    /// [`Error::UnknownModel`]: ../enum.Error.html#variant.UnknownModel
+    pub fn new() -> Result<Header> {
+        match Header::for_model(DeviceInfo::new()?.model()) {
+            Some(header) => Ok(header),
+            None => Err(Error::UnknownModel),
+        }
+    }
+
+    /// Constructs a new `Header` for the specified Raspberry Pi model.
+    ///
+    /// Returns `None` if the model doesn't have a standard GPIO header, such as
+    /// the Compute Modules.
+    pub fn for_model(model: Model) -> Option<Header> {
+        let (pins, p5_pins, wiringpi): (Vec<PinType>, &[PinType], &'static [u8]) = match model {
+            Model::RaspberryPiBRev1 => {
+                // A few pins are switched on the Pi B Rev 1, and the pins that later
+                // became power or ground pins weren't connected yet
+                let mut pins = HEADER[..MAX_PINS_SHORT].to_vec();
+                pins[2] = PinType::Gpio(0);
+                pins[4] = PinType::Gpio(1);
+                pins[12] = PinType::Gpio(21);
+                for &physical in &[4, 9, 14, 17, 20, 25] {
+                    pins[physical - 1] = PinType::DoNotConnect;
+                }
+
+                (pins, &[], &WIRINGPI_REV1)
+            }
+            Model::RaspberryPiA | Model::RaspberryPiBRev2 => (
+                HEADER[..MAX_PINS_SHORT].to_vec(),
+                &HEADER_P5,
+                &WIRINGPI_REV2,
+            ),
+            Model::RaspberryPiAPlus
+            | Model::RaspberryPiBPlus
+            | Model::RaspberryPi2B
+            | Model::RaspberryPi3APlus
+            | Model::RaspberryPi3B
+            | Model::RaspberryPi3BPlus
+            | Model::RaspberryPi4B
+            | Model::RaspberryPiZero
+            | Model::RaspberryPiZeroW => (HEADER[..MAX_PINS_LONG].to_vec(), &[], &WIRINGPI_REV2),
+            _ => return None,
+        };
+
+        Some(Header {
+            model,
+            pins,
+            p5_pins: p5_pins.to_vec(),
+            wiringpi,
+        })
+    }
+
+    /// Returns the Raspberry Pi model.
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    /// Returns the pins on the main GPIO header (P1 or J8), ordered by physical pin number.
+    pub fn pins(&self) -> &[PinType] {
+        &self.pins
+    }
+
+    /// Returns the pins on the P5 header, ordered by physical pin number.
+    ///
+    /// The P5 header is only available on the Raspberry Pi A and B Rev 2. For all
+    /// other models, the returned slice is empty.
+    pub fn p5_pins(&self) -> &[PinType] {
+        &self.p5_pins
+    }
+
+    /// Returns the type of the specified physical pin on the main GPIO header.
+    pub fn pin_type(&self, physical: u8) -> Option<PinType> {
+        if physical == 0 {
+            return None;
+        }
+
+        self.pins.get(physical as usize - 1).cloned()
+    }
+
+    /// Returns the BCM GPIO pin number for the specified physical pin on the main
+    /// GPIO header.
+    ///
+    /// Returns `None` if the physical pin doesn't exist, or isn't a GPIO pin.
+    pub fn physical_to_bcm(&self, physical: u8) -> Option<u8> {
+        match self.pin_type(physical) {
+            Some(PinType::Gpio(pin)) => Some(pin),
+            _ => None,
+        }
+    }
+
+    /// Returns the physical pin number on the main GPIO header for the specified BCM
+    /// GPIO pin number.
+    ///
+    /// Returns `None` if the GPIO pin isn't available on the main GPIO header.
+    pub fn bcm_to_physical(&self, pin: u8) -> Option<u8> {
+        self.pins
+            .iter()
+            .position(|&pin_type| pin_type == PinType::Gpio(pin))
+            .map(|idx| idx as u8 + 1)
+    }
+
+    /// Returns the BCM GPIO pin number for the specified wiringPi pin number.
+    ///
+    /// Returns `None` if the wiringPi pin number isn't available on any of the
+    /// GPIO headers.
+    pub fn wiringpi_to_bcm(&self, wiringpi: u8) -> Option<u8> {
+        self.wiringpi
+            .get(wiringpi as usize)
+            .cloned()
+            .filter(|&pin| self.is_exposed(pin))
+    }
+
+    /// Returns the wiringPi pin number for the specified BCM GPIO pin number.
+    ///
+    /// Returns `None` if the GPIO pin isn't available on any of the GPIO headers.
+    pub fn bcm_to_wiringpi(&self, pin: u8) -> Option<u8> {
+        if !self.is_exposed(pin) {
+            return None;
+        }
+
+        self.wiringpi
+            .iter()
+            .position(|&wiringpi_pin| wiringpi_pin == pin)
+            .map(|wiringpi| wiringpi as u8)
+    }
+
+    // Returns true if the GPIO pin is available on either the main or P5 header
+    fn is_exposed(&self, pin: u8) -> bool {
+        self.pins
+            .iter()
+            .chain(self.p5_pins.iter())
+            .any(|&pin_type| pin_type == PinType::Gpio(pin))
+    }
+}
@@ -20,60 +20,393 @@
 
 #![allow(dead_code)]
 
+use std::collections::HashMap;
 use std::fmt;
-use std::thread;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, ThreadId};
 use std::time::{Duration, Instant};
 
-use crate::gpio::epoll::{epoll_event, Epoll, EventFd, EPOLLERR, EPOLLET, EPOLLIN, EPOLLPRI};
+use crate::gpio::backend::GpioBackend;
+use crate::gpio::epoll::{self, epoll_event, Epoll, EventFd, EPOLLERR, EPOLLET, EPOLLIN, EPOLLPRI};
 use crate::gpio::ioctl;
 use crate::gpio::pin::InputPin;
-use crate::gpio::{Error, Level, Result, Trigger};
+use crate::gpio::{Error, Event, EventsRead, Level, Result, Trigger};
+
+// Returns the time remaining until the deadline, rounded up to the nearest
+// millisecond to match epoll's timeout resolution.
+pub fn time_until(deadline: Instant) -> Duration {
+    let now = Instant::now();
+
+    if deadline > now {
+        deadline - now + Duration::from_millis(1)
+    } else {
+        Duration::new(0, 0)
+    }
+}
+
+// Software-based debounce filter, used when the kernel doesn't debounce the input
+#[derive(Debug)]
+struct Debounce {
+    period: Duration,
+    // Last stable level
+    level: Level,
+    // Most recent edge that hasn't been stable for the debounce period yet, and
+    // the time it was read
+    pending: Option<(Event, Instant)>,
+}
+
+impl Debounce {
+    fn new(period: Duration, level: Level) -> Debounce {
+        Debounce {
+            period,
+            level,
+            pending: None,
+        }
+    }
+
+    // Replace the pending edge. Returns the previous pending edge if the input
+    // remained stable for the debounce period before the new edge occurred.
+    fn push(&mut self, event: Event) -> Option<Event> {
+        let stable = if let Some((pending, _)) = self.pending {
+            event.timestamp >= pending.timestamp + self.period
+        } else {
+            false
+        };
+
+        let resolved = if stable { self.resolve() } else { None };
+        self.pending = Some((event, Instant::now()));
+
+        resolved
+    }
+
+    // Time at which the pending edge is considered stable
+    fn deadline(&self) -> Option<Instant> {
+        self.pending.map(|(_, read)| read + self.period)
+    }
+
+    // Returns the pending edge if the input has been stable for the debounce period
+    fn poll(&mut self) -> Option<Event> {
+        match self.deadline() {
+            Some(deadline) if deadline <= Instant::now() => self.resolve(),
+            _ => None,
+        }
+    }
+
+    fn resolve(&mut self) -> Option<Event> {
+        let (event, _) = self.pending.take()?;
+
+        // Ignore glitches that return to the previous stable level
+        if event.level() == self.level {
+            return None;
+        }
+
+        self.level = event.level();
+
+        Some(event)
+    }
+}
 
 #[derive(Debug)]
-struct Interrupt {
+pub struct Interrupt {
     pin: u8,
     trigger: Trigger,
-    cdev_fd: i32,
-    event_request: ioctl::EventRequest,
+    debounce_period: Option<Duration>,
+    // Trigger edges and events are inverted for active-low pins
+    active_low: bool,
+    backend: Arc<dyn GpioBackend>,
+    // None if the event request couldn't be replaced by reset()
+    event_request: Option<ioctl::EventSource>,
+    // Software-based debounce filter, if the kernel doesn't debounce the input
+    debounce: Option<Debounce>,
+    // Sequence number of the last event read from event_request
+    seqno: u32,
+    // Number of events dropped by the kernel since the last call to take_lost()
+    lost: u32,
+    // Sequence number of the last debounced event
+    debounced_seqno: u32,
 }
 
 impl Interrupt {
-    fn new(cdev_fd: i32, pin: u8, trigger: Trigger) -> Result<Interrupt> {
+    pub fn new(
+        backend: Arc<dyn GpioBackend>,
+        pin: u8,
+        trigger: Trigger,
+        debounce_period: Option<Duration>,
+        active_low: bool,
+    ) -> Result<Interrupt> {
+        let (event_request, debounce) =
+            Interrupt::request(&backend, pin, trigger, debounce_period, active_low)?;
+
         Ok(Interrupt {
             pin,
             trigger,
-            cdev_fd,
-            event_request: ioctl::EventRequest::new(cdev_fd, pin, trigger)?,
+            debounce_period,
+            active_low,
+            backend,
+            event_request: Some(event_request),
+            debounce,
+            seqno: 0,
+            lost: 0,
+            debounced_seqno: 0,
         })
     }
 
+    fn request(
+        backend: &Arc<dyn GpioBackend>,
+        pin: u8,
+        trigger: Trigger,
+        debounce_period: Option<Duration>,
+        active_low: bool,
+    ) -> Result<(ioctl::EventSource, Option<Debounce>)> {
+        let period = if let Some(period) = debounce_period {
+            period
+        } else if active_low {
+            return Ok((backend.event_request(pin, trigger.invert(), None)?, None));
+        } else {
+            return Ok((backend.event_request(pin, trigger, None)?, None));
+        };
+
+        // Determining the stable level requires both edges. Events that don't match
+        // the requested trigger are filtered out after debouncing.
+        let event_request = backend.event_request(pin, Trigger::Both, debounce_period)?;
+
+        if event_request.debounced() {
+            Ok((event_request, None))
+        } else {
+            let level = if active_low {
+                !backend.level(pin)
+            } else {
+                backend.level(pin)
+            };
+
+            Ok((event_request, Some(Debounce::new(period, level))))
+        }
+    }
+
     fn trigger(&self) -> Trigger {
         self.trigger
     }
 
-    fn fd(&self) -> i32 {
-        self.event_request.fd
+    fn debounce_period(&self) -> Option<Duration> {
+        self.debounce_period
+    }
+
+    pub fn fd(&self) -> i32 {
+        match self.event_request {
+            Some(ref event_request) => event_request.fd(),
+            None => -1,
+        }
+    }
+
+    fn event_request(&mut self) -> Result<&mut ioctl::EventSource> {
+        match self.event_request {
+            Some(ref mut event_request) => Ok(event_request),
+            None => Err(Error::Io(io::Error::from_raw_os_error(libc::EBADF))),
+        }
     }
 
     fn pin(&self) -> u8 {
         self.pin
     }
 
-    fn set_trigger(&mut self, trigger: Trigger) -> Result<()> {
+    fn set_trigger(
+        &mut self,
+        trigger: Trigger,
+        debounce_period: Option<Duration>,
+        active_low: bool,
+    ) -> Result<()> {
         self.trigger = trigger;
+        self.debounce_period = debounce_period;
+        self.active_low = active_low;
 
         self.reset()
     }
 
-    fn event(&mut self) -> Result<ioctl::Event> {
+    // Returns None if the event was filtered out, or is still being debounced
+    pub fn event(&mut self) -> Result<Option<Event>> {
         // This might block if there are no events waiting
-        ioctl::get_event(self.event_request.fd)
+        let event = self.event_request()?.read_event()?;
+
+        Ok(self.process(event))
+    }
+
+    // Blocks until an event is available, or the deadline is reached
+    pub fn wait_event(&mut self, deadline: Option<Instant>) -> Result<Option<Event>> {
+        loop {
+            if let Some(event) = self.poll_debounced() {
+                return Ok(Some(event));
+            }
+
+            // Wake up in time to return any debounced edges that become stable
+            // before the deadline
+            let wait_deadline = match (deadline, self.deadline()) {
+                (Some(deadline), Some(debounce_deadline)) => Some(deadline.min(debounce_deadline)),
+                (deadline, debounce_deadline) => deadline.or(debounce_deadline),
+            };
+
+            if epoll::poll_readable(&[self.fd()], wait_deadline.map(time_until))? {
+                if let Some(event) = self.event()? {
+                    return Ok(Some(event));
+                }
+            } else if let Some(deadline) = deadline {
+                if deadline <= Instant::now() {
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    // Read all queued events that fit in the buffer, without blocking
+    fn read_events(&mut self, events: &mut [Event]) -> Result<usize> {
+        if self.debounce_period.is_none() {
+            let num_events = self.event_request()?.read_events(events)?;
+            for event in &mut events[..num_events] {
+                self.track_seqno(event);
+                *event = self.logical(*event);
+            }
+
+            return Ok(num_events);
+        }
+
+        // Each raw event results in at most one debounced event
+        let mut buffer = [Event {
+            pin: self.pin,
+            trigger: Trigger::Disabled,
+            timestamp: Duration::new(0, 0),
+            seqno: 0,
+        }; 16];
+
+        let mut count = 0;
+        while count < events.len() {
+            let max_events = buffer.len().min(events.len() - count);
+            let num_events = self
+                .event_request()?
+                .read_events(&mut buffer[..max_events])?;
+            if num_events == 0 {
+                break;
+            }
+
+            for event in &buffer[..num_events] {
+                if let Some(event) = self.process(*event) {
+                    events[count] = event;
+                    count += 1;
+                }
+            }
+        }
+
+        if count < events.len() {
+            if let Some(event) = self.poll_debounced() {
+                events[count] = event;
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    // Time at which a pending edge is considered stable by the software-based
+    // debounce filter
+    pub fn deadline(&self) -> Option<Instant> {
+        if let Some(ref debounce) = self.debounce {
+            debounce.deadline()
+        } else {
+            None
+        }
+    }
+
+    // Returns a pending edge once the input has been stable for the debounce period
+    pub fn poll_debounced(&mut self) -> Option<Event> {
+        let event = if let Some(ref mut debounce) = self.debounce {
+            debounce.poll()
+        } else {
+            None
+        };
+
+        event.and_then(|event| self.filter(event))
+    }
+
+    fn process(&mut self, event: Event) -> Option<Event> {
+        self.track_seqno(&event);
+        let event = self.logical(event);
+
+        if self.debounce_period.is_none() {
+            return Some(event);
+        }
+
+        let event = if let Some(ref mut debounce) = self.debounce {
+            debounce.push(event)
+        } else {
+            Some(event)
+        };
+
+        event.and_then(|event| self.filter(event))
+    }
+
+    // Debounced events are requested for both edges, so we drop any edges that don't
+    // match the trigger, and renumber the remaining events.
+    fn filter(&mut self, event: Event) -> Option<Event> {
+        if self.trigger != Trigger::Both && self.trigger != event.trigger {
+            return None;
+        }
+
+        self.debounced_seqno = self.debounced_seqno.wrapping_add(1);
+
+        Some(Event {
+            seqno: self.debounced_seqno,
+            ..event
+        })
+    }
+
+    // Converts an event read from event_request to the pin's logic levels
+    fn logical(&self, event: Event) -> Event {
+        if self.active_low {
+            Event {
+                trigger: event.trigger.invert(),
+                ..event
+            }
+        } else {
+            event
+        }
+    }
+
+    // A gap between consecutive sequence numbers means the kernel's event queue
+    // overflowed, and some events were dropped.
+    fn track_seqno(&mut self, event: &Event) {
+        let gap = event.seqno.wrapping_sub(self.seqno.wrapping_add(1));
+
+        self.lost = self.lost.saturating_add(gap);
+        self.seqno = event.seqno;
+    }
+
+    fn take_lost(&mut self) -> u32 {
+        let lost = self.lost;
+        self.lost = 0;
+
+        lost
     }
 
     fn reset(&mut self) -> Result<()> {
-        // Close the old event fd before opening a new one
-        self.event_request.close();
-        self.event_request = ioctl::EventRequest::new(self.cdev_fd, self.pin, self.trigger)?;
+        // Close the old event fd before opening a new one, since a line can
+        // only be requested once
+        self.event_request = None;
+
+        let (event_request, debounce) = Interrupt::request(
+            &self.backend,
+            self.pin,
+            self.trigger,
+            self.debounce_period,
+            self.active_low,
+        )?;
+        self.event_request = Some(event_request);
+        self.debounce = debounce;
+
+        // Sequence numbers start over for every new event request
+        self.seqno = 0;
+        self.lost = 0;
+        self.debounced_seqno = 0;
 
         Ok(())
     }
@@ -82,15 +415,15 @@ impl Interrupt {
 #[derive(Debug)]
 struct TriggerStatus {
     interrupt: Option<Interrupt>,
-    triggered: bool,
-    level: Level,
+    // Most recent event that hasn't been returned yet
+    event: Option<Event>,
 }
 
 pub struct EventLoop {
     poll: Epoll,
     events: Vec<epoll_event>,
     trigger_status: Vec<TriggerStatus>,
-    cdev_fd: i32,
+    backend: Arc<dyn GpioBackend>,
 }
 
 impl fmt::Debug for EventLoop {
@@ -99,21 +432,20 @@ impl fmt::Debug for EventLoop {
             .field("poll", &self.poll)
             .field("events", &format_args!("{{ .. }}"))
             .field("trigger_status", &format_args!("{{ .. }}"))
-            .field("cdev_fd", &self.cdev_fd)
+            .field("backend", &self.backend)
             .finish()
     }
 }
 
 impl EventLoop {
-    pub fn new(cdev_fd: i32, capacity: usize) -> Result<EventLoop> {
+    pub fn new(backend: Arc<dyn GpioBackend>, capacity: usize) -> Result<EventLoop> {
         let mut trigger_status = Vec::with_capacity(capacity);
 
         // Initialize trigger_status while circumventing the Copy/Clone requirement
         for _ in 0..trigger_status.capacity() {
             trigger_status.push(TriggerStatus {
                 interrupt: None,
-                triggered: false,
-                level: Level::Low,
+                event: None,
             });
         }
 
@@ -121,7 +453,7 @@ impl EventLoop {
             poll: Epoll::new()?,
             events: vec![epoll_event { events: 0, u64: 0 }; capacity],
             trigger_status,
-            cdev_fd,
+            backend,
         })
     }
 
@@ -130,16 +462,14 @@ impl EventLoop {
         pins: &[&'a InputPin],
         reset: bool,
         timeout: Option<Duration>,
-    ) -> Result<Option<(&'a InputPin, Level)>> {
+    ) -> Result<Option<(&'a InputPin, Event)>> {
         for pin in pins {
             let trigger_status = &mut self.trigger_status[pin.pin() as usize];
 
             // Did we cache any trigger events during the previous poll?
-            if trigger_status.triggered {
-                trigger_status.triggered = false;
-
+            if let Some(event) = trigger_status.event.take() {
                 if !reset {
-                    return Ok(Some((pin, trigger_status.level)));
+                    return Ok(Some((pin, event)));
                 }
             }
 
@@ -160,10 +490,31 @@ impl EventLoop {
         // Loop until we get any of the events we're waiting for, or a timeout occurs
         let now = Instant::now();
         loop {
-            let num_events = self.poll.wait(&mut self.events, timeout)?;
+            // Wake up in time to return any debounced edges that become stable
+            // before the timeout
+            let mut wait_timeout = timeout;
+            let mut debouncing = false;
+            for pin in pins {
+                if let Some(ref interrupt) = self.trigger_status[pin.pin() as usize].interrupt {
+                    if let Some(deadline) = interrupt.deadline() {
+                        let remaining = time_until(deadline);
+                        let shorter = match wait_timeout {
+                            Some(t) => remaining < t,
+                            None => true,
+                        };
+
+                        if shorter {
+                            wait_timeout = Some(remaining);
+                            debouncing = true;
+                        }
+                    }
+                }
+            }
+
+            let num_events = self.poll.wait(&mut self.events, wait_timeout)?;
 
             // No events means a timeout occurred
-            if num_events == 0 {
+            if num_events == 0 && !debouncing {
                 return Ok(None);
             }
 
@@ -178,24 +529,30 @@ impl EventLoop {
                 );
 
                 if let Some(ref mut interrupt) = trigger_status.interrupt {
-                    trigger_status.level = match interrupt.event()?.trigger {
-                        Trigger::RisingEdge => Level::High,
-                        Trigger::FallingEdge => Level::Low,
-                        _ => unsafe { std::hint::unreachable_unchecked() },
-                    };
-
-                    trigger_status.triggered = true;
+                    if let Some(event) = interrupt.event()? {
+                        trigger_status.event = Some(event);
+                    }
                 };
             }
 
+            // Check for any debounced edges that are now stable
+            for pin in pins {
+                let trigger_status = &mut self.trigger_status[pin.pin() as usize];
+
+                if let Some(ref mut interrupt) = trigger_status.interrupt {
+                    if let Some(event) = interrupt.poll_debounced() {
+                        trigger_status.event = Some(event);
+                    }
+                }
+            }
+
             // Were any interrupts triggered? If so, return one. The rest
             // will be saved for the next poll.
             for pin in pins {
                 let trigger_status = &mut self.trigger_status[pin.pin() as usize];
 
-                if trigger_status.triggered {
-                    trigger_status.triggered = false;
-                    return Ok(Some((pin, trigger_status.level)));
+                if let Some(event) = trigger_status.event.take() {
+                    return Ok(Some((pin, event)));
                 }
             }
 
@@ -211,17 +568,73 @@ impl EventLoop {
         }
     }
 
-    pub fn set_interrupt(&mut self, pin: u8, trigger: Trigger) -> Result<()> {
+    pub fn read_events(&mut self, pins: &[&InputPin], events: &mut [Event]) -> Result<EventsRead> {
+        let mut events_read = EventsRead {
+            count: 0,
+            lost: 0,
+            pending: false,
+        };
+
+        for pin in pins {
+            let trigger_status = &mut self.trigger_status[pin.pin() as usize];
+
+            // Any events cached during a previous poll come first
+            if events_read.count < events.len() {
+                if let Some(event) = trigger_status.event.take() {
+                    events[events_read.count] = event;
+                    events_read.count += 1;
+                }
+            }
+
+            if let Some(ref mut interrupt) = trigger_status.interrupt {
+                events_read.count += interrupt.read_events(&mut events[events_read.count..])?;
+                events_read.lost = events_read.lost.saturating_add(interrupt.take_lost());
+            }
+        }
+
+        // If the buffer is full, check if there are any events left
+        if events_read.count == events.len() {
+            let mut fds = Vec::with_capacity(pins.len());
+            for pin in pins {
+                let trigger_status = &self.trigger_status[pin.pin() as usize];
+
+                if trigger_status.event.is_some() {
+                    events_read.pending = true;
+                }
+
+                if let Some(ref interrupt) = trigger_status.interrupt {
+                    fds.push(interrupt.fd());
+                }
+            }
+
+            if !events_read.pending {
+                events_read.pending = epoll::poll_readable(&fds, Some(Duration::new(0, 0)))?;
+            }
+        }
+
+        Ok(events_read)
+    }
+
+    pub fn set_interrupt(
+        &mut self,
+        pin: u8,
+        trigger: Trigger,
+        debounce_period: Option<Duration>,
+        active_low: bool,
+    ) -> Result<()> {
         let trigger_status = &mut self.trigger_status[pin as usize];
 
-        trigger_status.triggered = false;
+        trigger_status.event = None;
 
         // Interrupt already exists. We just need to change the trigger.
         if let Some(ref mut interrupt) = trigger_status.interrupt {
-            if interrupt.trigger != trigger {
+            if interrupt.trigger != trigger
+                || interrupt.debounce_period != debounce_period
+                || interrupt.active_low != active_low
+            {
                 // This requires a new event request, so the fd might change
                 self.poll.delete(interrupt.fd())?;
-                interrupt.set_trigger(trigger)?;
+                interrupt.set_trigger(trigger, debounce_period, active_low)?;
                 self.poll
                     .add(interrupt.fd(), u64::from(pin), EPOLLIN | EPOLLPRI)?;
             }
@@ -230,7 +643,13 @@ impl EventLoop {
         }
 
         // Register a new interrupt
-        let interrupt = Interrupt::new(self.cdev_fd, pin, trigger)?;
+        let interrupt = Interrupt::new(
+            self.backend.clone(),
+            pin,
+            trigger,
+            debounce_period,
+            active_low,
+        )?;
         self.poll
             .add(interrupt.fd(), u64::from(pin), EPOLLIN | EPOLLPRI)?;
         trigger_status.interrupt = Some(interrupt);
@@ -241,7 +660,7 @@ impl EventLoop {
     pub fn clear_interrupt(&mut self, pin: u8) -> Result<()> {
         let trigger_status = &mut self.trigger_status[pin as usize];
 
-        trigger_status.triggered = false;
+        trigger_status.event = None;
 
         if let Some(interrupt) = trigger_status.interrupt.take() {
             self.poll.delete(interrupt.fd())?;
@@ -251,77 +670,325 @@ impl EventLoop {
     }
 }
 
+// Executes a callback, typically by submitting it to a user-supplied thread pool
+pub type Executor = Arc<dyn Fn(Box<dyn FnOnce() + Send>) + Send + Sync>;
+
+// Epoll id used for the EventFd that wakes up the dispatcher thread
+const ID_NOTIFY: u64 = !0;
+
+struct Callback {
+    callback: Mutex<Box<dyn FnMut(Event) + Send>>,
+    // Cleared when the AsyncInterrupt is stopped, so any callbacks that are
+    // still waiting to be executed by the executor are skipped.
+    active: AtomicBool,
+    panicked: AtomicBool,
+    // Thread that's currently executing the callback
+    thread: Mutex<Option<ThreadId>>,
+}
+
+impl Callback {
+    fn call(&self, event: Event) {
+        // A panicking callback shouldn't take down the dispatcher thread. The
+        // panic is reported when the AsyncInterrupt is stopped.
+        if let Ok(mut callback) = self.callback.lock() {
+            // Checked while holding the lock, so the callback isn't executed once
+            // wait() returns
+            if !self.active.load(Ordering::SeqCst) {
+                return;
+            }
+
+            *self.thread.lock().unwrap() = Some(thread::current().id());
+
+            if panic::catch_unwind(AssertUnwindSafe(|| (*callback)(event))).is_err() {
+                self.active.store(false, Ordering::SeqCst);
+                self.panicked.store(true, Ordering::SeqCst);
+            }
+
+            *self.thread.lock().unwrap() = None;
+        }
+    }
+
+    // Waits for an in-flight call to return. A callback that stops its own
+    // interrupt trigger can't wait for itself.
+    fn wait(&self) {
+        if *self.thread.lock().unwrap() == Some(thread::current().id()) {
+            return;
+        }
+
+        drop(self.callback.lock());
+    }
+}
+
+impl fmt::Debug for Callback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Callback")
+            .field("callback", &format_args!("{{ .. }}"))
+            .field("active", &self.active)
+            .field("panicked", &self.panicked)
+            .finish()
+    }
+}
+
 #[derive(Debug)]
-pub struct AsyncInterrupt {
-    poll_thread: Option<thread::JoinHandle<Result<()>>>,
-    tx: EventFd,
+struct Handler {
+    interrupt: Interrupt,
+    callback: Arc<Callback>,
+    // Error that occurred while reading events, which stopped the handler
+    error: Option<Error>,
 }
 
-impl AsyncInterrupt {
-    pub fn new<C>(fd: i32, pin: u8, trigger: Trigger, mut callback: C) -> Result<AsyncInterrupt>
-    where
-        C: FnMut(Level) + Send + 'static,
-    {
-        let tx = EventFd::new()?;
-        let rx = tx.fd();
-
-        let poll_thread = thread::spawn(move || -> Result<()> {
-            let poll = Epoll::new()?;
-
-            // rx becomes readable when the main thread calls notify()
-            poll.add(rx, rx as u64, EPOLLERR | EPOLLET | EPOLLIN)?;
-
-            let mut interrupt = Interrupt::new(fd, pin, trigger)?;
-            poll.add(interrupt.fd(), interrupt.fd() as u64, EPOLLIN | EPOLLPRI)?;
-
-            let mut events = [epoll_event { events: 0, u64: 0 }; 2];
-            loop {
-                let num_events = poll.wait(&mut events, None)?;
-                if num_events > 0 {
-                    for event in &events[0..num_events] {
-                        let fd = event.u64 as i32;
-                        if fd == rx {
-                            return Ok(()); // The main thread asked us to stop
-                        } else if fd == interrupt.fd() {
-                            let level = match interrupt.event()?.trigger {
-                                Trigger::RisingEdge => Level::High,
-                                _ => Level::Low,
-                            };
-
-                            callback(level);
+struct DispatcherInner {
+    poll: Epoll,
+    notify: EventFd,
+    stop: AtomicBool,
+    handlers: Mutex<HashMap<u8, Handler>>,
+    executor: Arc<Mutex<Option<Executor>>>,
+    // OS error that stopped the dispatcher thread
+    error: Mutex<Option<i32>>,
+}
+
+impl DispatcherInner {
+    // Returns the error that stopped the dispatcher thread, if any
+    fn check(&self) -> Result<()> {
+        match *self.error.lock().unwrap() {
+            Some(code) => Err(Error::Io(io::Error::from_raw_os_error(code))),
+            None => Ok(()),
+        }
+    }
+
+    fn run(&self) {
+        let mut events = [epoll_event { events: 0, u64: 0 }; 16];
+        let mut calls = Vec::new();
+
+        loop {
+            // Wake up when a debounced edge becomes stable
+            let timeout = self
+                .handlers
+                .lock()
+                .unwrap()
+                .values()
+                .filter_map(|handler| handler.interrupt.deadline())
+                .min()
+                .map(time_until);
+
+            let num_events = match self.poll.wait(&mut events, timeout) {
+                Ok(num_events) => num_events,
+                // Restart the wait if it was interrupted by a signal
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    // Any remaining errors are unrecoverable. The error is stored, so it
+                    // can be returned when interrupt triggers are added or removed.
+                    *self.error.lock().unwrap() = Some(e.raw_os_error().unwrap_or(libc::EIO));
+
+                    return;
+                }
+            };
+
+            if self.stop.load(Ordering::SeqCst) {
+                return;
+            }
+
+            {
+                let mut handlers = self.handlers.lock().unwrap();
+
+                for event in &events[0..num_events] {
+                    if event.u64 == ID_NOTIFY {
+                        continue;
+                    }
+
+                    if let Some(handler) = handlers.get_mut(&(event.u64 as u8)) {
+                        match handler.interrupt.event() {
+                            Ok(Some(event)) => calls.push((handler.callback.clone(), event)),
+                            Ok(None) => (),
+                            Err(e) => {
+                                let _ = self.poll.delete(handler.interrupt.fd());
+                                handler.error = Some(e);
+                            }
                         }
                     }
                 }
+
+                for handler in handlers.values_mut() {
+                    if let Some(event) = handler.interrupt.poll_debounced() {
+                        calls.push((handler.callback.clone(), event));
+                    }
+                }
+            }
+
+            // Callbacks are executed after releasing the lock, so they're able to
+            // configure interrupt triggers themselves.
+            let executor = self.executor.lock().unwrap().clone();
+            for (callback, event) in calls.drain(..) {
+                if let Some(ref executor) = executor {
+                    executor(Box::new(move || callback.call(event)));
+                } else {
+                    callback.call(event);
+                }
             }
+        }
+    }
+}
+
+impl fmt::Debug for DispatcherInner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DispatcherInner")
+            .field("poll", &self.poll)
+            .field("notify", &self.notify)
+            .field("stop", &self.stop)
+            .field("handlers", &self.handlers)
+            .field("executor", &format_args!("{{ .. }}"))
+            .finish()
+    }
+}
+
+// Polls the event fds for all asynchronous interrupt triggers on a single
+// thread, and executes the relevant callbacks.
+#[derive(Debug)]
+pub struct Dispatcher {
+    inner: Arc<DispatcherInner>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Dispatcher {
+    pub fn new(executor: Arc<Mutex<Option<Executor>>>) -> Result<Dispatcher> {
+        let inner = Arc::new(DispatcherInner {
+            poll: Epoll::new()?,
+            notify: EventFd::new()?,
+            stop: AtomicBool::new(false),
+            handlers: Mutex::new(HashMap::new()),
+            executor,
+            error: Mutex::new(None),
         });
 
-        Ok(AsyncInterrupt {
-            poll_thread: Some(poll_thread),
-            tx,
+        // notify becomes readable when Dispatcher is dropped
+        inner
+            .poll
+            .add(inner.notify.fd(), ID_NOTIFY, EPOLLERR | EPOLLET | EPOLLIN)?;
+
+        let thread_inner = inner.clone();
+        let thread = thread::spawn(move || thread_inner.run());
+
+        Ok(Dispatcher {
+            inner,
+            thread: Some(thread),
         })
     }
 
-    pub fn stop(&mut self) -> Result<()> {
-        self.tx.notify()?;
+    fn add(&self, interrupt: Interrupt, callback: Arc<Callback>) -> Result<()> {
+        self.inner.check()?;
+
+        let pin = interrupt.pin();
+        let mut handlers = self.inner.handlers.lock().unwrap();
 
-        if let Some(poll_thread) = self.poll_thread.take() {
-            match poll_thread.join() {
-                Ok(r) => return r,
-                Err(_) => return Err(Error::ThreadPanic),
+        self.inner
+            .poll
+            .add(interrupt.fd(), u64::from(pin), EPOLLIN | EPOLLPRI)?;
+
+        handlers.insert(
+            pin,
+            Handler {
+                interrupt,
+                callback,
+                error: None,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn remove(&self, pin: u8) -> Result<()> {
+        let handler = self.inner.handlers.lock().unwrap().remove(&pin);
+
+        if let Some(mut handler) = handler {
+            handler.callback.active.store(false, Ordering::SeqCst);
+            handler.callback.wait();
+
+            if let Some(e) = handler.error.take() {
+                return Err(e);
+            }
+
+            self.inner.poll.delete(handler.interrupt.fd())?;
+
+            if handler.callback.panicked.load(Ordering::SeqCst) {
+                return Err(Error::ThreadPanic);
             }
+
+            self.inner.check()?;
         }
 
         Ok(())
     }
 }
 
-impl Drop for AsyncInterrupt {
+impl Drop for Dispatcher {
     fn drop(&mut self) {
-        // Don't wait for the poll thread to exit if the main thread is panicking,
-        // because we could potentially block indefinitely while unwinding if the
-        // poll thread is executing a callback that doesn't return.
-        if !thread::panicking() {
-            let _ = self.stop();
+        self.inner.stop.store(true, Ordering::SeqCst);
+
+        if self.inner.notify.notify().is_err() {
+            return;
         }
+
+        if let Some(thread) = self.thread.take() {
+            // Don't wait for the dispatcher thread to exit if we're running on it,
+            // which happens when a callback drops the last InputPin with an
+            // asynchronous interrupt trigger, or if the current thread is panicking,
+            // because a callback might not return.
+            if thread.thread().id() != thread::current().id() && !thread::panicking() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AsyncInterrupt {
+    dispatcher: Arc<Dispatcher>,
+    pin: u8,
+    stopped: bool,
+}
+
+impl AsyncInterrupt {
+    pub fn new<C>(
+        dispatcher: Arc<Dispatcher>,
+        backend: Arc<dyn GpioBackend>,
+        pin: u8,
+        trigger: Trigger,
+        debounce_period: Option<Duration>,
+        active_low: bool,
+        callback: C,
+    ) -> Result<AsyncInterrupt>
+    where
+        C: FnMut(Event) + Send + 'static,
+    {
+        let interrupt = Interrupt::new(backend, pin, trigger, debounce_period, active_low)?;
+        let callback = Arc::new(Callback {
+            callback: Mutex::new(Box::new(callback)),
+            active: AtomicBool::new(true),
+            panicked: AtomicBool::new(false),
+            thread: Mutex::new(None),
+        });
+
+        dispatcher.add(interrupt, callback)?;
+
+        Ok(AsyncInterrupt {
+            dispatcher,
+            pin,
+            stopped: false,
+        })
+    }
+
+    pub fn stop(&mut self) -> Result<()> {
+        if self.stopped {
+            return Ok(());
+        }
+
+        self.stopped = true;
+        self.dispatcher.remove(self.pin)
+    }
+}
+
+impl Drop for AsyncInterrupt {
+    fn drop(&mut self) {
+        let _ = self.stop();
     }
 }
@@ -23,19 +23,22 @@
 use libc::{self, c_int, c_ulong, c_void, ENOENT};
 use std::ffi::CString;
 use std::fmt;
-use std::fs::{File, OpenOptions};
+use std::fs::{self, File, OpenOptions};
 use std::io;
 use std::mem;
-use std::os::unix::io::AsRawFd;
+use std::os::unix::io::{AsRawFd, FromRawFd};
 use std::time::Duration;
 
-use crate::gpio::{Error, Level, Result, Trigger};
+use crate::gpio::{self, epoll};
+use crate::gpio::{Drive, Error, Event, Level, Mode, PullUpDown, Result, Trigger};
 
 #[cfg(target_env = "gnu")]
 type IoctlLong = libc::c_ulong;
 #[cfg(target_env = "musl")]
 type IoctlLong = c_int;
 
+const PATH_DEV: &str = "/dev";
+const GPIOCHIP: &str = "gpiochip";
 const PATH_GPIOCHIP: &str = "/dev/gpiochip";
 const CONSUMER_LABEL: &str = "RPPAL";
 const DRIVER_NAME: &[u8] = b"pinctrl-bcm2835\0";
@@ -56,6 +59,12 @@ const NR_GET_LINE_HANDLE: IoctlLong = 0x03 << NRSHIFT;
 const NR_GET_LINE_EVENT: IoctlLong = 0x04 << NRSHIFT;
 const NR_GET_LINE_VALUES: IoctlLong = 0x08 << NRSHIFT;
 const NR_SET_LINE_VALUES: IoctlLong = 0x09 << NRSHIFT;
+const NR_SET_CONFIG: IoctlLong = 0x0A << NRSHIFT;
+const NR_GET_LINE_INFO_V2: IoctlLong = 0x05 << NRSHIFT;
+const NR_GET_LINE_V2: IoctlLong = 0x07 << NRSHIFT;
+const NR_LINE_SET_CONFIG_V2: IoctlLong = 0x0D << NRSHIFT;
+const NR_LINE_GET_VALUES_V2: IoctlLong = 0x0E << NRSHIFT;
+const NR_LINE_SET_VALUES_V2: IoctlLong = 0x0F << NRSHIFT;
 
 const TYPE_GPIO: IoctlLong = (0xB4 as IoctlLong) << TYPESHIFT;
 
@@ -64,6 +73,11 @@ const SIZE_LINE_INFO: IoctlLong = (mem::size_of::<LineInfo>() as IoctlLong) << S
 const SIZE_HANDLE_REQUEST: IoctlLong = (mem::size_of::<HandleRequest>() as IoctlLong) << SIZESHIFT;
 const SIZE_EVENT_REQUEST: IoctlLong = (mem::size_of::<EventRequest>() as IoctlLong) << SIZESHIFT;
 const SIZE_HANDLE_DATA: IoctlLong = (mem::size_of::<HandleData>() as IoctlLong) << SIZESHIFT;
+const SIZE_HANDLE_CONFIG: IoctlLong = (mem::size_of::<HandleConfig>() as IoctlLong) << SIZESHIFT;
+const SIZE_LINE_INFO_V2: IoctlLong = (mem::size_of::<LineInfoV2>() as IoctlLong) << SIZESHIFT;
+const SIZE_LINE_REQUEST_V2: IoctlLong = (mem::size_of::<LineRequestV2>() as IoctlLong) << SIZESHIFT;
+const SIZE_LINE_CONFIG_V2: IoctlLong = (mem::size_of::<LineConfigV2>() as IoctlLong) << SIZESHIFT;
+const SIZE_LINE_VALUES_V2: IoctlLong = (mem::size_of::<LineValuesV2>() as IoctlLong) << SIZESHIFT;
 
 const DIR_NONE: c_ulong = 0;
 const DIR_WRITE: IoctlLong = 1 << DIRSHIFT;
@@ -80,6 +94,17 @@ const REQ_GET_LINE_VALUES: IoctlLong =
     DIR_READ_WRITE | TYPE_GPIO | NR_GET_LINE_VALUES | SIZE_HANDLE_DATA;
 const REQ_SET_LINE_VALUES: IoctlLong =
     DIR_READ_WRITE | TYPE_GPIO | NR_SET_LINE_VALUES | SIZE_HANDLE_DATA;
+const REQ_SET_CONFIG: IoctlLong = DIR_READ_WRITE | TYPE_GPIO | NR_SET_CONFIG | SIZE_HANDLE_CONFIG;
+const REQ_GET_LINE_INFO_V2: IoctlLong =
+    DIR_READ_WRITE | TYPE_GPIO | NR_GET_LINE_INFO_V2 | SIZE_LINE_INFO_V2;
+const REQ_GET_LINE_V2: IoctlLong =
+    DIR_READ_WRITE | TYPE_GPIO | NR_GET_LINE_V2 | SIZE_LINE_REQUEST_V2;
+const REQ_LINE_SET_CONFIG_V2: IoctlLong =
+    DIR_READ_WRITE | TYPE_GPIO | NR_LINE_SET_CONFIG_V2 | SIZE_LINE_CONFIG_V2;
+const REQ_LINE_GET_VALUES_V2: IoctlLong =
+    DIR_READ_WRITE | TYPE_GPIO | NR_LINE_GET_VALUES_V2 | SIZE_LINE_VALUES_V2;
+const REQ_LINE_SET_VALUES_V2: IoctlLong =
+    DIR_READ_WRITE | TYPE_GPIO | NR_LINE_SET_VALUES_V2 | SIZE_LINE_VALUES_V2;
 
 const NAME_BUFSIZE: usize = 32;
 const LABEL_BUFSIZE: usize = 32;
@@ -104,6 +129,19 @@ impl ChipInfo {
 
         Ok(chip_info)
     }
+
+    pub fn name(&self) -> String {
+        cbuf_to_cstring(&self.name).to_string_lossy().into_owned()
+    }
+
+    pub fn label(&self) -> String {
+        cbuf_to_cstring(&self.label).to_string_lossy().into_owned()
+    }
+
+    // Returns true if the chip controls the SoC's GPIO pins
+    pub fn is_bcm2835(&self) -> bool {
+        self.label[0..DRIVER_NAME.len()] == DRIVER_NAME[..]
+    }
 }
 
 impl fmt::Debug for ChipInfo {
@@ -121,6 +159,10 @@ const LINE_FLAG_IS_OUT: u32 = 0x02;
 const LINE_FLAG_ACTIVE_LOW: u32 = 0x04;
 const LINE_FLAG_OPEN_DRAIN: u32 = 0x08;
 const LINE_FLAG_OPEN_SOURCE: u32 = 0x10;
+// Bias flags require Linux 5.5 or later
+const LINE_FLAG_BIAS_PULL_UP: u32 = 0x20;
+const LINE_FLAG_BIAS_PULL_DOWN: u32 = 0x40;
+const LINE_FLAG_BIAS_DISABLE: u32 = 0x80;
 
 #[derive(Copy, Clone)]
 #[repr(C)]
@@ -142,6 +184,103 @@ impl LineInfo {
     }
 }
 
+impl LineInfo {
+    // Retrieve the line info using the specified ABI version. v2 flags are
+    // converted to their v1 equivalents.
+    pub fn for_pin(cdev_fd: c_int, abi: Abi, pin: u8) -> Result<LineInfo> {
+        match abi {
+            Abi::V2 => {
+                let mut line_info_v2 = LineInfoV2::new();
+                line_info_v2.offset = u32::from(pin);
+
+                parse_retval!(unsafe {
+                    libc::ioctl(cdev_fd, REQ_GET_LINE_INFO_V2, &mut line_info_v2)
+                })?;
+
+                let flag_map = [
+                    (LINE_FLAG_USED_V2, LINE_FLAG_KERNEL),
+                    (LINE_FLAG_OUTPUT_V2, LINE_FLAG_IS_OUT),
+                    (LINE_FLAG_ACTIVE_LOW_V2, LINE_FLAG_ACTIVE_LOW),
+                    (LINE_FLAG_OPEN_DRAIN_V2, LINE_FLAG_OPEN_DRAIN),
+                    (LINE_FLAG_OPEN_SOURCE_V2, LINE_FLAG_OPEN_SOURCE),
+                    (LINE_FLAG_BIAS_PULL_UP_V2, LINE_FLAG_BIAS_PULL_UP),
+                    (LINE_FLAG_BIAS_PULL_DOWN_V2, LINE_FLAG_BIAS_PULL_DOWN),
+                    (LINE_FLAG_BIAS_DISABLED_V2, LINE_FLAG_BIAS_DISABLE),
+                ];
+
+                Ok(LineInfo {
+                    line_offset: line_info_v2.offset,
+                    flags: flag_map
+                        .iter()
+                        .filter(|(flag_v2, _)| line_info_v2.flags & flag_v2 != 0)
+                        .fold(0, |flags, (_, flag)| flags | flag),
+                    name: line_info_v2.name,
+                    consumer: line_info_v2.consumer,
+                })
+            }
+            Abi::V1 => {
+                let mut line_info = LineInfo::new();
+                line_info.line_offset = u32::from(pin);
+
+                parse_retval!(unsafe { libc::ioctl(cdev_fd, REQ_GET_LINE_INFO, &mut line_info) })?;
+
+                Ok(line_info)
+            }
+        }
+    }
+
+    pub fn is_output(&self) -> bool {
+        self.flags & LINE_FLAG_IS_OUT > 0
+    }
+
+    // Set if the line is requested by a kernel driver or a user space process
+    pub fn is_used(&self) -> bool {
+        self.flags & LINE_FLAG_KERNEL > 0
+    }
+
+    pub fn consumer(&self) -> String {
+        cbuf_to_cstring(&self.consumer)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    // Converts the line info to its public equivalent
+    pub fn info(&self, pin: u8) -> gpio::LineInfo {
+        gpio::LineInfo {
+            pin,
+            name: cbuf_to_cstring(&self.name).to_string_lossy().into_owned(),
+            consumer: self.consumer(),
+            direction: if self.is_output() {
+                Mode::Output
+            } else {
+                Mode::Input
+            },
+            used: self.is_used(),
+            active_low: self.flags & LINE_FLAG_ACTIVE_LOW > 0,
+            drive: if self.flags & LINE_FLAG_OPEN_DRAIN > 0 {
+                Drive::OpenDrain
+            } else if self.flags & LINE_FLAG_OPEN_SOURCE > 0 {
+                Drive::OpenSource
+            } else {
+                Drive::PushPull
+            },
+            bias: self.bias(),
+        }
+    }
+
+    pub fn bias(&self) -> Option<PullUpDown> {
+        if self.flags & LINE_FLAG_BIAS_PULL_UP > 0 {
+            Some(PullUpDown::PullUp)
+        } else if self.flags & LINE_FLAG_BIAS_PULL_DOWN > 0 {
+            Some(PullUpDown::PullDown)
+        } else if self.flags & LINE_FLAG_BIAS_DISABLE > 0 {
+            Some(PullUpDown::Off)
+        } else {
+            None
+        }
+    }
+}
+
 impl fmt::Debug for LineInfo {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("LineInfo")
@@ -159,6 +298,10 @@ const HANDLE_FLAG_OUTPUT: u32 = 0x02;
 const HANDLE_FLAG_ACTIVE_LOW: u32 = 0x04;
 const HANDLE_FLAG_OPEN_DRAIN: u32 = 0x08;
 const HANDLE_FLAG_OPEN_SOURCE: u32 = 0x10;
+// Bias flags require Linux 5.5 or later
+const HANDLE_FLAG_BIAS_PULL_UP: u32 = 0x20;
+const HANDLE_FLAG_BIAS_PULL_DOWN: u32 = 0x40;
+const HANDLE_FLAG_BIAS_DISABLE: u32 = 0x80;
 
 #[repr(C)]
 pub struct HandleRequest {
@@ -171,10 +314,10 @@ pub struct HandleRequest {
 }
 
 impl HandleRequest {
-    pub fn new(cdev_fd: c_int, pins: &[u8]) -> Result<HandleRequest> {
+    pub fn new(cdev_fd: c_int, pins: &[u8], flags: u32, levels: u64) -> Result<HandleRequest> {
         let mut handle_request = HandleRequest {
             line_offsets: [0u32; HANDLES_MAX],
-            flags: 0,
+            flags,
             default_values: [0u8; HANDLES_MAX],
             consumer_label: [0u8; LABEL_BUFSIZE],
             lines: 0,
@@ -191,6 +334,7 @@ impl HandleRequest {
 
         for (idx, pin) in pins.iter().enumerate() {
             handle_request.line_offsets[idx] = u32::from(*pin);
+            handle_request.default_values[idx] = ((levels >> idx) & 1) as u8;
         }
 
         // Set consumer label, so other processes know we're using these pins
@@ -290,6 +434,40 @@ impl fmt::Debug for HandleData {
     }
 }
 
+// Reconfigures a requested line handle (Linux 5.5 or later)
+#[repr(C)]
+pub struct HandleConfig {
+    pub flags: u32,
+    pub default_values: [u8; HANDLES_MAX],
+    pub padding: [u32; 4],
+}
+
+impl HandleConfig {
+    pub fn new(flags: u32, levels: u64) -> HandleConfig {
+        let mut handle_config = HandleConfig {
+            flags,
+            default_values: [0u8; HANDLES_MAX],
+            padding: [0u32; 4],
+        };
+
+        for (idx, value) in handle_config.default_values.iter_mut().enumerate() {
+            *value = ((levels >> idx) & 1) as u8;
+        }
+
+        handle_config
+    }
+}
+
+impl fmt::Debug for HandleConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HandleConfig")
+            .field("flags", &self.flags)
+            .field("default_values", &&self.default_values[..])
+            .field("padding", &self.padding)
+            .finish()
+    }
+}
+
 const EVENT_FLAG_RISING_EDGE: u32 = 0x01;
 const EVENT_FLAG_FALLING_EDGE: u32 = 0x02;
 const EVENT_FLAG_BOTH_EDGES: u32 = EVENT_FLAG_RISING_EDGE | EVENT_FLAG_FALLING_EDGE;
@@ -304,10 +482,15 @@ pub struct EventRequest {
 }
 
 impl EventRequest {
-    pub fn new(cdev_fd: c_int, pin: u8, trigger: Trigger) -> Result<EventRequest> {
+    pub fn new(
+        cdev_fd: c_int,
+        pin: u8,
+        trigger: Trigger,
+        handle_flags: u32,
+    ) -> Result<EventRequest> {
         let mut event_request = EventRequest {
             line_offset: u32::from(pin),
-            handle_flags: HANDLE_FLAG_INPUT,
+            handle_flags,
             event_flags: trigger as u32,
             consumer_label: [0u8; LABEL_BUFSIZE],
             fd: 0,
@@ -393,48 +576,902 @@ impl EventData {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
-pub struct Event {
+// Records with an unknown event id, which could be added by future kernel
+// versions, are skipped.
+impl Event {
+    // The v1 ABI doesn't include the line offset or a sequence number, so those
+    // are provided by the EventSource.
+    fn from_event_data(event_data: EventData, pin: u8, seqno: u32) -> Option<Event> {
+        let trigger = match event_data.id {
+            EVENT_TYPE_RISING_EDGE => Trigger::RisingEdge,
+            EVENT_TYPE_FALLING_EDGE => Trigger::FallingEdge,
+            _ => return None,
+        };
+
+        Some(Event {
+            pin,
+            trigger,
+            timestamp: Duration::from_nanos(event_data.timestamp),
+            seqno,
+        })
+    }
+
+    fn from_line_event(line_event: LineEventV2) -> Option<Event> {
+        let trigger = match line_event.id {
+            LINE_EVENT_RISING_EDGE_V2 => Trigger::RisingEdge,
+            LINE_EVENT_FALLING_EDGE_V2 => Trigger::FallingEdge,
+            _ => return None,
+        };
+
+        Some(Event {
+            pin: line_event.offset as u8,
+            trigger,
+            timestamp: Duration::from_nanos(line_event.timestamp_ns),
+            seqno: line_event.line_seqno,
+        })
+    }
+}
+
+// GPIO character device uAPI v2 (Linux 5.10 or later)
+
+const LINES_MAX_V2: usize = 64;
+const LINE_NUM_ATTRS_MAX_V2: usize = 10;
+
+const LINE_FLAG_USED_V2: u64 = 1 << 0;
+const LINE_FLAG_ACTIVE_LOW_V2: u64 = 1 << 1;
+const LINE_FLAG_INPUT_V2: u64 = 1 << 2;
+const LINE_FLAG_OUTPUT_V2: u64 = 1 << 3;
+const LINE_FLAG_EDGE_RISING_V2: u64 = 1 << 4;
+const LINE_FLAG_EDGE_FALLING_V2: u64 = 1 << 5;
+const LINE_FLAG_OPEN_DRAIN_V2: u64 = 1 << 6;
+const LINE_FLAG_OPEN_SOURCE_V2: u64 = 1 << 7;
+const LINE_FLAG_BIAS_PULL_UP_V2: u64 = 1 << 8;
+const LINE_FLAG_BIAS_PULL_DOWN_V2: u64 = 1 << 9;
+const LINE_FLAG_BIAS_DISABLED_V2: u64 = 1 << 10;
+const LINE_FLAG_EVENT_CLOCK_REALTIME_V2: u64 = 1 << 11;
+
+const LINE_ATTR_ID_FLAGS_V2: u32 = 1;
+const LINE_ATTR_ID_OUTPUT_VALUES_V2: u32 = 2;
+const LINE_ATTR_ID_DEBOUNCE_V2: u32 = 3;
+
+const LINE_EVENT_RISING_EDGE_V2: u32 = 1;
+const LINE_EVENT_FALLING_EDGE_V2: u32 = 2;
+
+// ABI version of the GPIO character device
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Abi {
+    V1,
+    V2,
+}
+
+impl Abi {
+    // Older kernels return either ENOTTY or EINVAL for unknown ioctl requests,
+    // so we check if a v2 request succeeds instead.
+    pub fn detect(cdev_fd: c_int) -> Abi {
+        let mut line_info = LineInfoV2::new();
+
+        if unsafe { libc::ioctl(cdev_fd, REQ_GET_LINE_INFO_V2, &mut line_info) } == -1 {
+            Abi::V1
+        } else {
+            Abi::V2
+        }
+    }
+}
+
+// Direction of a requested line. AsIs leaves the current direction unchanged,
+// and can't be combined with any other settings.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Direction {
+    Input,
+    Output,
+    AsIs,
+}
+
+// Timestamp clock used for line events
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum EventClock {
+    Monotonic,
+    Realtime,
+}
+
+// ABI-independent line settings. The v1 ABI doesn't support bias, debounce
+// or event clock selection, in which case those settings are ignored.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct LineSettings {
+    pub direction: Direction,
     pub trigger: Trigger,
-    pub timestamp: Duration,
+    // None leaves the bias as-is
+    pub bias: Option<PullUpDown>,
+    pub drive: Drive,
+    pub active_low: bool,
+    pub debounce: Option<Duration>,
+    pub event_clock: EventClock,
 }
 
-impl Event {
-    fn from_event_data(event_data: EventData) -> Event {
-        Event {
-            trigger: match event_data.id {
-                EVENT_TYPE_RISING_EDGE => Trigger::RisingEdge,
-                EVENT_TYPE_FALLING_EDGE => Trigger::FallingEdge,
-                _ => unreachable!(),
-            },
-            timestamp: Duration::from_nanos(event_data.timestamp),
+impl LineSettings {
+    pub fn new(direction: Direction) -> LineSettings {
+        LineSettings {
+            direction,
+            trigger: Trigger::Disabled,
+            bias: None,
+            drive: Drive::PushPull,
+            active_low: false,
+            debounce: None,
+            event_clock: EventClock::Monotonic,
+        }
+    }
+
+    fn flags_v2(&self) -> u64 {
+        let mut flags = match self.direction {
+            Direction::Input => LINE_FLAG_INPUT_V2,
+            Direction::Output => LINE_FLAG_OUTPUT_V2,
+            Direction::AsIs => 0,
+        };
+
+        match self.trigger {
+            Trigger::RisingEdge => flags |= LINE_FLAG_EDGE_RISING_V2,
+            Trigger::FallingEdge => flags |= LINE_FLAG_EDGE_FALLING_V2,
+            Trigger::Both => flags |= LINE_FLAG_EDGE_RISING_V2 | LINE_FLAG_EDGE_FALLING_V2,
+            Trigger::Disabled => (),
+        }
+
+        match self.bias {
+            Some(PullUpDown::PullUp) => flags |= LINE_FLAG_BIAS_PULL_UP_V2,
+            Some(PullUpDown::PullDown) => flags |= LINE_FLAG_BIAS_PULL_DOWN_V2,
+            Some(PullUpDown::Off) => flags |= LINE_FLAG_BIAS_DISABLED_V2,
+            None => (),
+        }
+
+        // Drive modes are only valid for outputs
+        if self.direction == Direction::Output {
+            match self.drive {
+                Drive::OpenDrain => flags |= LINE_FLAG_OPEN_DRAIN_V2,
+                Drive::OpenSource => flags |= LINE_FLAG_OPEN_SOURCE_V2,
+                Drive::PushPull => (),
+            }
+        }
+
+        if self.active_low {
+            flags |= LINE_FLAG_ACTIVE_LOW_V2;
+        }
+
+        if self.event_clock == EventClock::Realtime && self.trigger != Trigger::Disabled {
+            flags |= LINE_FLAG_EVENT_CLOCK_REALTIME_V2;
+        }
+
+        flags
+    }
+
+    fn handle_flags_v1(&self) -> u32 {
+        let mut flags = match self.direction {
+            Direction::Input => HANDLE_FLAG_INPUT,
+            Direction::Output => HANDLE_FLAG_OUTPUT,
+            Direction::AsIs => 0,
+        };
+
+        match self.bias {
+            Some(PullUpDown::PullUp) => flags |= HANDLE_FLAG_BIAS_PULL_UP,
+            Some(PullUpDown::PullDown) => flags |= HANDLE_FLAG_BIAS_PULL_DOWN,
+            Some(PullUpDown::Off) => flags |= HANDLE_FLAG_BIAS_DISABLE,
+            None => (),
+        }
+
+        if self.direction == Direction::Output {
+            match self.drive {
+                Drive::OpenDrain => flags |= HANDLE_FLAG_OPEN_DRAIN,
+                Drive::OpenSource => flags |= HANDLE_FLAG_OPEN_SOURCE,
+                Drive::PushPull => (),
+            }
+        }
+
+        if self.active_low {
+            flags |= HANDLE_FLAG_ACTIVE_LOW;
+        }
+
+        flags
+    }
+}
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub union LineAttributeValueV2 {
+    pub flags: u64,
+    pub values: u64,
+    pub debounce_period_us: u32,
+}
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct LineAttributeV2 {
+    pub id: u32,
+    pub padding: u32,
+    pub value: LineAttributeValueV2,
+}
+
+impl LineAttributeV2 {
+    pub fn new() -> LineAttributeV2 {
+        LineAttributeV2 {
+            id: 0,
+            padding: 0,
+            value: LineAttributeValueV2 { flags: 0 },
+        }
+    }
+}
+
+impl fmt::Debug for LineAttributeV2 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("LineAttributeV2");
+        debug.field("id", &self.id);
+
+        // Only read the union field that's relevant for the attribute's id
+        unsafe {
+            match self.id {
+                LINE_ATTR_ID_DEBOUNCE_V2 => {
+                    debug.field("debounce_period_us", &self.value.debounce_period_us)
+                }
+                LINE_ATTR_ID_OUTPUT_VALUES_V2 => debug.field("values", &self.value.values),
+                _ => debug.field("flags", &self.value.flags),
+            };
+        }
+
+        debug.finish()
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub struct LineConfigAttributeV2 {
+    pub attr: LineAttributeV2,
+    pub mask: u64,
+}
+
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub struct LineConfigV2 {
+    pub flags: u64,
+    pub num_attrs: u32,
+    pub padding: [u32; 5],
+    pub attrs: [LineConfigAttributeV2; LINE_NUM_ATTRS_MAX_V2],
+}
+
+impl LineConfigV2 {
+    pub fn new(settings: &LineSettings, lines: usize, levels: u64) -> LineConfigV2 {
+        let mut line_config = LineConfigV2 {
+            flags: settings.flags_v2(),
+            num_attrs: 0,
+            padding: [0u32; 5],
+            attrs: [LineConfigAttributeV2 {
+                attr: LineAttributeV2::new(),
+                mask: 0,
+            }; LINE_NUM_ATTRS_MAX_V2],
+        };
+
+        // Debounce is configured through an attribute that applies to all requested lines
+        if let Some(debounce) = settings.debounce {
+            let mut attr = LineAttributeV2::new();
+            attr.id = LINE_ATTR_ID_DEBOUNCE_V2;
+            attr.value.debounce_period_us = debounce.as_micros().min(u128::from(u32::MAX)) as u32;
+
+            line_config.attrs[line_config.num_attrs as usize] = LineConfigAttributeV2 {
+                attr,
+                mask: line_mask(lines),
+            };
+            line_config.num_attrs += 1;
+        }
+
+        // Initial output levels
+        if settings.direction == Direction::Output {
+            let mut attr = LineAttributeV2::new();
+            attr.id = LINE_ATTR_ID_OUTPUT_VALUES_V2;
+            attr.value.values = levels;
+
+            line_config.attrs[line_config.num_attrs as usize] = LineConfigAttributeV2 {
+                attr,
+                mask: line_mask(lines),
+            };
+            line_config.num_attrs += 1;
+        }
+
+        line_config
+    }
+}
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct LineInfoV2 {
+    pub name: [u8; NAME_BUFSIZE],
+    pub consumer: [u8; LABEL_BUFSIZE],
+    pub offset: u32,
+    pub num_attrs: u32,
+    pub flags: u64,
+    pub attrs: [LineAttributeV2; LINE_NUM_ATTRS_MAX_V2],
+    pub padding: [u32; 4],
+}
+
+impl LineInfoV2 {
+    pub fn new() -> LineInfoV2 {
+        LineInfoV2 {
+            name: [0u8; NAME_BUFSIZE],
+            consumer: [0u8; LABEL_BUFSIZE],
+            offset: 0,
+            num_attrs: 0,
+            flags: 0,
+            attrs: [LineAttributeV2::new(); LINE_NUM_ATTRS_MAX_V2],
+            padding: [0u32; 4],
+        }
+    }
+}
+
+impl fmt::Debug for LineInfoV2 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LineInfoV2")
+            .field("name", &cbuf_to_cstring(&self.name))
+            .field("consumer", &cbuf_to_cstring(&self.consumer))
+            .field("offset", &self.offset)
+            .field("num_attrs", &self.num_attrs)
+            .field("flags", &self.flags)
+            .field("attrs", &&self.attrs[..self.num_attrs as usize])
+            .finish()
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+#[repr(C)]
+pub struct LineValuesV2 {
+    pub bits: u64,
+    pub mask: u64,
+}
+
+#[repr(C)]
+pub struct LineRequestV2 {
+    pub offsets: [u32; LINES_MAX_V2],
+    pub consumer: [u8; LABEL_BUFSIZE],
+    pub config: LineConfigV2,
+    pub num_lines: u32,
+    pub event_buffer_size: u32,
+    pub padding: [u32; 5],
+    pub fd: c_int,
+}
+
+impl LineRequestV2 {
+    pub fn new(
+        cdev_fd: c_int,
+        pins: &[u8],
+        settings: &LineSettings,
+        levels: u64,
+    ) -> Result<LineRequestV2> {
+        if pins.len() > LINES_MAX_V2 {
+            return Err(too_many_lines());
+        }
+
+        let mut line_request = LineRequestV2 {
+            offsets: [0u32; LINES_MAX_V2],
+            consumer: [0u8; LABEL_BUFSIZE],
+            config: LineConfigV2::new(settings, pins.len(), levels),
+            num_lines: pins.len() as u32,
+            event_buffer_size: 0,
+            padding: [0u32; 5],
+            fd: 0,
+        };
+
+        for (idx, pin) in pins.iter().enumerate() {
+            line_request.offsets[idx] = u32::from(*pin);
+        }
+
+        // Set consumer label, so other processes know we're using these pins
+        line_request.consumer[0..CONSUMER_LABEL.len()].copy_from_slice(CONSUMER_LABEL.as_bytes());
+
+        parse_retval!(unsafe { libc::ioctl(cdev_fd, REQ_GET_LINE_V2, &mut line_request) })?;
+
+        // If the line fd is zero or negative, an error occurred
+        if line_request.fd <= 0 {
+            Err(Error::Io(std::io::Error::last_os_error()))
+        } else {
+            Ok(line_request)
+        }
+    }
+
+    pub fn levels(&self) -> Result<LineValuesV2> {
+        let mut line_values = LineValuesV2 {
+            bits: 0,
+            mask: line_mask(self.num_lines as usize),
+        };
+
+        parse_retval!(unsafe { libc::ioctl(self.fd, REQ_LINE_GET_VALUES_V2, &mut line_values) })?;
+
+        Ok(line_values)
+    }
+
+    pub fn set_levels(&mut self, levels: &[Level]) -> Result<()> {
+        let levels: &[Level] = if levels.len() > self.num_lines as usize {
+            &levels[0..self.num_lines as usize]
+        } else {
+            levels
+        };
+
+        let mut line_values = LineValuesV2 {
+            bits: 0,
+            mask: line_mask(levels.len()),
+        };
+
+        for (idx, level) in levels.iter().enumerate() {
+            line_values.bits |= (*level as u64) << idx;
+        }
+
+        parse_retval!(unsafe { libc::ioctl(self.fd, REQ_LINE_SET_VALUES_V2, &mut line_values) })?;
+
+        Ok(())
+    }
+
+    pub fn set_config(&mut self, settings: &LineSettings, levels: u64) -> Result<()> {
+        let mut line_config = LineConfigV2::new(settings, self.num_lines as usize, levels);
+
+        parse_retval!(unsafe { libc::ioctl(self.fd, REQ_LINE_SET_CONFIG_V2, &mut line_config) })?;
+
+        self.config = line_config;
+
+        Ok(())
+    }
+
+    pub fn close(&mut self) {
+        if self.fd > 0 {
+            unsafe {
+                libc::close(self.fd);
+            }
+
+            self.fd = 0;
+        }
+    }
+}
+
+impl Drop for LineRequestV2 {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+impl fmt::Debug for LineRequestV2 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LineRequestV2")
+            .field(
+                "offsets",
+                &format_args!("{:?}", &self.offsets[..self.num_lines as usize]),
+            )
+            .field("consumer", &cbuf_to_cstring(&self.consumer))
+            .field("config", &self.config)
+            .field("num_lines", &self.num_lines)
+            .field("event_buffer_size", &self.event_buffer_size)
+            .field("fd", &self.fd)
+            .finish()
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+#[repr(C)]
+struct LineEventV2 {
+    timestamp_ns: u64,
+    id: u32,
+    offset: u32,
+    seqno: u32,
+    line_seqno: u32,
+    padding: [u32; 6],
+}
+
+impl LineEventV2 {
+    fn new(event_fd: c_int) -> Result<LineEventV2> {
+        let mut line_event = LineEventV2::default();
+
+        let bytes_read = parse_retval!(unsafe {
+            libc::read(
+                event_fd,
+                &mut line_event as *mut LineEventV2 as *mut c_void,
+                mem::size_of::<LineEventV2>(),
+            )
+        })?;
+
+        if bytes_read < mem::size_of::<LineEventV2>() as isize {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "failed to fill whole buffer",
+            )
+            .into())
+        } else {
+            Ok(line_event)
         }
     }
 }
 
-// Read interrupt event
-pub fn get_event(event_fd: c_int) -> Result<Event> {
-    let event_data = EventData::new(event_fd)?;
-    Ok(Event::from_event_data(event_data))
+// Read as many fixed-size kernel structs as fit in the buffer with a single
+// read(). Returns the number of structs read.
+fn read_structs<T>(fd: c_int, buffer: &mut [T]) -> Result<usize> {
+    let bytes_read = parse_retval!(unsafe {
+        libc::read(
+            fd,
+            buffer.as_mut_ptr() as *mut c_void,
+            mem::size_of_val(buffer),
+        )
+    })? as usize;
+
+    let num_structs = bytes_read / mem::size_of::<T>();
+    if num_structs == 0 || num_structs * mem::size_of::<T>() != bytes_read {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "failed to fill whole buffer",
+        )
+        .into())
+    } else {
+        Ok(num_structs)
+    }
+}
+
+// The kernel limits the number of lines in a single request
+fn too_many_lines() -> Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        "too many lines in a single request",
+    )
+    .into()
+}
+
+// Returns a bitmask with the lowest `lines` bits set
+fn line_mask(lines: usize) -> u64 {
+    if lines >= 64 {
+        !0
+    } else {
+        (1u64 << lines) - 1
+    }
+}
+
+// Owned fd that produces interrupt events in the format that matches the
+// ABI version used to request them.
+#[derive(Debug)]
+pub struct EventSource {
+    file: File,
+    abi: Abi,
+    pin: u8,
+    // Sequence number of the last v1 event
+    seqno: u32,
+    // Input is debounced by the kernel
+    debounced: bool,
+}
+
+impl EventSource {
+    pub fn new(cdev_fd: c_int, abi: Abi, pin: u8, settings: &LineSettings) -> Result<EventSource> {
+        // Transfer ownership of the fd, so it doesn't get closed when the
+        // request goes out of scope.
+        let fd = match abi {
+            Abi::V2 => {
+                let mut line_request = LineRequestV2::new(cdev_fd, &[pin], settings, 0)?;
+                let fd = line_request.fd;
+                line_request.fd = 0;
+                fd
+            }
+            Abi::V1 => {
+                let mut event_request =
+                    EventRequest::new(cdev_fd, pin, settings.trigger, settings.handle_flags_v1())?;
+                let fd = event_request.fd;
+                event_request.fd = 0;
+                fd
+            }
+        };
+
+        let mut event_source = EventSource::from_file(unsafe { File::from_raw_fd(fd) }, abi, pin);
+        // The v1 ABI doesn't support debouncing
+        event_source.debounced = abi == Abi::V2 && settings.debounce.is_some();
+
+        Ok(event_source)
+    }
+
+    pub fn from_file(file: File, abi: Abi, pin: u8) -> EventSource {
+        EventSource {
+            file,
+            abi,
+            pin,
+            seqno: 0,
+            debounced: false,
+        }
+    }
+
+    pub fn abi(&self) -> Abi {
+        self.abi
+    }
+
+    pub fn debounced(&self) -> bool {
+        self.debounced
+    }
+
+    pub fn fd(&self) -> c_int {
+        self.file.as_raw_fd()
+    }
+
+    // Read interrupt event. This might block if there are no events waiting.
+    pub fn read_event(&mut self) -> Result<Event> {
+        loop {
+            let event = match self.abi {
+                Abi::V2 => Event::from_line_event(LineEventV2::new(self.fd())?),
+                Abi::V1 => {
+                    let seqno = self.seqno.wrapping_add(1);
+                    let event = Event::from_event_data(EventData::new(self.fd())?, self.pin, seqno);
+                    if event.is_some() {
+                        self.seqno = seqno;
+                    }
+
+                    event
+                }
+            };
+
+            if let Some(event) = event {
+                return Ok(event);
+            }
+        }
+    }
+
+    // Read up to events.len() queued interrupt events without blocking. Returns
+    // the number of events read.
+    pub fn read_events(&mut self, events: &mut [Event]) -> Result<usize> {
+        // Matches the kernel's default event buffer size for a single line
+        const BATCH_SIZE: usize = 16;
+
+        let fd = self.fd();
+        let mut count = 0;
+        while count < events.len() && epoll::poll_readable(&[fd], Some(Duration::new(0, 0)))? {
+            let batch_size = BATCH_SIZE.min(events.len() - count);
+
+            match self.abi {
+                Abi::V2 => {
+                    let mut buffer = [LineEventV2::default(); BATCH_SIZE];
+                    let num_events = read_structs(fd, &mut buffer[..batch_size])?;
+
+                    for line_event in &buffer[..num_events] {
+                        if let Some(event) = Event::from_line_event(*line_event) {
+                            events[count] = event;
+                            count += 1;
+                        }
+                    }
+                }
+                Abi::V1 => {
+                    // Linux 5.6 and earlier only return a single event per read()
+                    let mut buffer = [EventData::default(); BATCH_SIZE];
+                    let num_events = read_structs(fd, &mut buffer[..batch_size])?;
+
+                    for event_data in &buffer[..num_events] {
+                        let seqno = self.seqno.wrapping_add(1);
+                        if let Some(event) = Event::from_event_data(*event_data, self.pin, seqno) {
+                            self.seqno = seqno;
+                            events[count] = event;
+                            count += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(count)
+    }
+}
+
+// ABI-independent handle used to read and write the levels of one or more
+// requested lines. Levels are stored as a bitmask, where bit 0 corresponds to
+// the first requested line.
+#[derive(Debug)]
+pub struct LineHandle {
+    fd: c_int,
+    abi: Abi,
+    lines: usize,
+}
+
+impl LineHandle {
+    pub fn new(
+        cdev_fd: c_int,
+        abi: Abi,
+        pins: &[u8],
+        settings: &LineSettings,
+        levels: u64,
+    ) -> Result<LineHandle> {
+        if pins.len() > HANDLES_MAX {
+            return Err(too_many_lines());
+        }
+
+        // Transfer ownership of the fd, so it doesn't get closed when the
+        // request goes out of scope.
+        let fd = match abi {
+            Abi::V2 => {
+                let mut line_request = LineRequestV2::new(cdev_fd, pins, settings, levels)?;
+                let fd = line_request.fd;
+                line_request.fd = 0;
+                fd
+            }
+            Abi::V1 => {
+                let mut handle_request =
+                    HandleRequest::new(cdev_fd, pins, settings.handle_flags_v1(), levels)?;
+                let fd = handle_request.fd;
+                handle_request.fd = 0;
+                fd
+            }
+        };
+
+        Ok(LineHandle {
+            fd,
+            abi,
+            lines: pins.len(),
+        })
+    }
+
+    // Both v1 event requests and v2 line requests support reading the line
+    // level through the event fd. The fd is duplicated, so the line stays
+    // requested until both the EventSource and the LineHandle are closed.
+    pub fn from_event_source(event_source: &EventSource) -> Result<LineHandle> {
+        Ok(LineHandle {
+            fd: parse_retval!(unsafe { libc::dup(event_source.fd()) })?,
+            abi: event_source.abi,
+            lines: 1,
+        })
+    }
+
+    pub fn levels(&self) -> Result<u64> {
+        match self.abi {
+            Abi::V2 => {
+                let mut line_values = LineValuesV2 {
+                    bits: 0,
+                    mask: line_mask(self.lines),
+                };
+
+                parse_retval!(unsafe {
+                    libc::ioctl(self.fd, REQ_LINE_GET_VALUES_V2, &mut line_values)
+                })?;
+
+                Ok(line_values.bits)
+            }
+            Abi::V1 => {
+                let mut handle_data = HandleData::new();
+
+                parse_retval!(unsafe {
+                    libc::ioctl(self.fd, REQ_GET_LINE_VALUES, &mut handle_data)
+                })?;
+
+                Ok(handle_data.values[..self.lines]
+                    .iter()
+                    .enumerate()
+                    .fold(0, |bits, (idx, value)| {
+                        bits | (u64::from(*value & 1) << idx)
+                    }))
+            }
+        }
+    }
+
+    pub fn lines(&self) -> usize {
+        self.lines
+    }
+
+    // Sets the levels of the lines selected by mask. The v1 ABI always sets all
+    // lines, so the current levels of any unselected lines are read first.
+    pub fn set_levels(&self, mask: u64, levels: u64) -> Result<()> {
+        let mask = mask & line_mask(self.lines);
+
+        match self.abi {
+            Abi::V2 => {
+                let mut line_values = LineValuesV2 { bits: levels, mask };
+
+                parse_retval!(unsafe {
+                    libc::ioctl(self.fd, REQ_LINE_SET_VALUES_V2, &mut line_values)
+                })?;
+            }
+            Abi::V1 => {
+                let levels = if mask == line_mask(self.lines) {
+                    levels
+                } else {
+                    (self.levels()? & !mask) | (levels & mask)
+                };
+
+                let mut handle_data = HandleData::new();
+                for (idx, value) in handle_data.values[..self.lines].iter_mut().enumerate() {
+                    *value = ((levels >> idx) & 1) as u8;
+                }
+
+                parse_retval!(unsafe {
+                    libc::ioctl(self.fd, REQ_SET_LINE_VALUES, &mut handle_data)
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Changes the configuration of the requested lines without releasing them.
+    // Fails on Linux 5.4 and earlier, and for handles created from a v1 event
+    // source.
+    pub fn set_config(&self, settings: &LineSettings, levels: u64) -> Result<()> {
+        match self.abi {
+            Abi::V2 => {
+                let mut line_config = LineConfigV2::new(settings, self.lines, levels);
+
+                parse_retval!(unsafe {
+                    libc::ioctl(self.fd, REQ_LINE_SET_CONFIG_V2, &mut line_config)
+                })?;
+            }
+            Abi::V1 => {
+                let mut handle_config = HandleConfig::new(settings.handle_flags_v1(), levels);
+
+                parse_retval!(unsafe { libc::ioctl(self.fd, REQ_SET_CONFIG, &mut handle_config) })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn close(&mut self) {
+        if self.fd > 0 {
+            unsafe {
+                libc::close(self.fd);
+            }
+
+            self.fd = 0;
+        }
+    }
+}
+
+impl Drop for LineHandle {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+// Write an interrupt event using the same format as the kernel's v2 ABI. This
+// is used by the simulated backend to send events through a datagram socket.
+// Returns false if the event was dropped because the socket buffer is full.
+pub fn put_event(socket_fd: c_int, event: Event) -> Result<bool> {
+    let line_event = LineEventV2 {
+        timestamp_ns: event.timestamp.as_nanos() as u64,
+        id: match event.trigger {
+            Trigger::RisingEdge => LINE_EVENT_RISING_EDGE_V2,
+            _ => LINE_EVENT_FALLING_EDGE_V2,
+        },
+        offset: u32::from(event.pin),
+        seqno: event.seqno,
+        line_seqno: event.seqno,
+        ..LineEventV2::default()
+    };
+
+    let bytes_sent = match parse_retval!(unsafe {
+        libc::send(
+            socket_fd,
+            &line_event as *const LineEventV2 as *const c_void,
+            mem::size_of::<LineEventV2>(),
+            libc::MSG_NOSIGNAL | libc::MSG_DONTWAIT,
+        )
+    }) {
+        Ok(bytes_sent) => bytes_sent as usize,
+        Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+
+    if bytes_sent != mem::size_of::<LineEventV2>() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::WriteZero,
+            "failed to write whole event",
+        )
+        .into());
+    }
+
+    Ok(true)
+}
+
+pub fn open_gpiochip(path: &str) -> Result<File> {
+    match OpenOptions::new().read(true).write(true).open(path) {
+        Ok(file) => Ok(file),
+        Err(ref e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            Err(Error::PermissionDenied(path.to_owned()))
+        }
+        Err(e) => Err(Error::from(e)),
+    }
 }
 
 // Find the correct gpiochip device based on its label
 pub fn find_gpiochip() -> Result<File> {
     for id in 0..=255 {
-        let gpiochip = match OpenOptions::new()
-            .read(true)
-            .write(true)
-            .open(format!("{}{}", PATH_GPIOCHIP, id))
-        {
-            Ok(file) => file,
-            Err(ref e) if e.kind() == io::ErrorKind::PermissionDenied => {
-                return Err(Error::PermissionDenied(format!("{}{}", PATH_GPIOCHIP, id)));
-            }
-            Err(e) => return Err(Error::from(e)),
-        };
+        let gpiochip = open_gpiochip(&format!("{}{}", PATH_GPIOCHIP, id))?;
 
-        let chip_info = ChipInfo::new(gpiochip.as_raw_fd())?;
-        if chip_info.label[0..DRIVER_NAME.len()] == DRIVER_NAME[..] {
+        if ChipInfo::new(gpiochip.as_raw_fd())?.is_bcm2835() {
             return Ok(gpiochip);
         }
     }
@@ -443,6 +1480,30 @@ pub fn find_gpiochip() -> Result<File> {
     Err(Error::Io(io::Error::from_raw_os_error(ENOENT)))
 }
 
+// Returns the paths of all available gpiochip devices, sorted by their id
+pub fn gpiochip_paths() -> Result<Vec<String>> {
+    let mut ids = Vec::new();
+
+    for entry in fs::read_dir(PATH_DEV)? {
+        let file_name = entry?.file_name();
+
+        if let Some(id) = file_name
+            .to_str()
+            .filter(|name| name.starts_with(GPIOCHIP))
+            .and_then(|name| name[GPIOCHIP.len()..].parse::<u32>().ok())
+        {
+            ids.push(id);
+        }
+    }
+
+    ids.sort();
+
+    Ok(ids
+        .iter()
+        .map(|id| format!("{}{}", PATH_GPIOCHIP, id))
+        .collect())
+}
+
 // Create a CString from a C-style NUL-terminated char array. This workaround
 // is needed for fixed-length buffers that fill the remaining bytes with NULs,
 // because CString::new() interprets those as a NUL in the middle of the byte
@@ -30,26 +30,42 @@ use std::time::Duration;
 
 use libc::{self, c_void, off_t, size_t, MAP_FAILED, MAP_SHARED, O_SYNC, PROT_READ, PROT_WRITE};
 
-use crate::gpio::{Error, Level, Mode, PullUpDown, Result};
-use crate::system::DeviceInfo;
+use crate::gpio::{Detect, Error, Level, Mode, PullUpDown, Result};
+use crate::system::{DeviceInfo, SoC};
 
 const PATH_DEV_GPIOMEM: &str = "/dev/gpiomem";
 const PATH_DEV_MEM: &str = "/dev/mem";
 
-// The BCM2835 has 41 32-bit registers related to the GPIO (datasheet @ 6.1).
-const GPIO_MEM_REGISTERS: usize = 41;
+// The BCM2835 has 41 32-bit registers related to the GPIO (datasheet @ 6.1). The
+// BCM2711 adds GPIO_PUP_PDN_CNTRL_REG0-3, which extends the range to 61 registers.
+const GPIO_MEM_REGISTERS: usize = 61;
 const GPIO_MEM_SIZE: usize = GPIO_MEM_REGISTERS * std::mem::size_of::<u32>();
 
 const GPFSEL0: usize = 0x00;
 const GPSET0: usize = 0x1c / std::mem::size_of::<u32>();
 const GPCLR0: usize = 0x28 / std::mem::size_of::<u32>();
 const GPLEV0: usize = 0x34 / std::mem::size_of::<u32>();
+const GPEDS0: usize = 0x40 / std::mem::size_of::<u32>();
+const GPREN0: usize = 0x4c / std::mem::size_of::<u32>();
+const GPFEN0: usize = 0x58 / std::mem::size_of::<u32>();
+const GPHEN0: usize = 0x64 / std::mem::size_of::<u32>();
+const GPLEN0: usize = 0x70 / std::mem::size_of::<u32>();
+const GPAREN0: usize = 0x7c / std::mem::size_of::<u32>();
+const GPAFEN0: usize = 0x88 / std::mem::size_of::<u32>();
 const GPPUD: usize = 0x94 / std::mem::size_of::<u32>();
 const GPPUDCLK0: usize = 0x98 / std::mem::size_of::<u32>();
+// Only available on BCM2711 (datasheet @ 5.2).
+const GPPUPPDN0: usize = 0xe4 / std::mem::size_of::<u32>();
+
+// BCM2711 GPIO_PUP_PDN_CNTRL_REG resistor selection values
+const GPPUPPDN_OFF: u32 = 0b00;
+const GPPUPPDN_PULLUP: u32 = 0b01;
+const GPPUPPDN_PULLDOWN: u32 = 0b10;
 
 pub struct GpioMem {
     mem_ptr: *mut u32,
     locks: [AtomicBool; GPIO_MEM_REGISTERS],
+    soc: SoC,
 }
 
 impl fmt::Debug for GpioMem {
@@ -57,6 +73,7 @@ impl fmt::Debug for GpioMem {
         f.debug_struct("GpioMem")
             .field("mem_ptr", &self.mem_ptr)
             .field("locks", &format_args!("{{ .. }}"))
+            .field("soc", &self.soc)
             .finish()
     }
 }
@@ -87,7 +104,17 @@ impl GpioMem {
 
         let locks = init_array!(AtomicBool::new(false), GPIO_MEM_REGISTERS);
 
-        Ok(GpioMem { mem_ptr, locks })
+        // /dev/gpiomem doesn't require the model to be identified. If that fails, assume
+        // we're on a SoC that supports the legacy pull-up/pull-down registers.
+        let soc = DeviceInfo::new()
+            .map(|device_info| device_info.soc())
+            .unwrap_or(SoC::Bcm2835);
+
+        Ok(GpioMem {
+            mem_ptr,
+            locks,
+            soc,
+        })
     }
 
     fn map_devgpiomem() -> Result<*mut u32> {
@@ -185,6 +212,32 @@ impl GpioMem {
         unsafe { std::mem::transmute((reg_value >> shift) as u8 & 0b1) }
     }
 
+    // Returns the levels of all pins, where bit n corresponds to GPIO n
+    #[inline(always)]
+    pub(crate) fn levels(&self) -> u64 {
+        u64::from(self.read(GPLEV0)) | (u64::from(self.read(GPLEV0 + 1)) << 32)
+    }
+
+    // Sets the pins in mask to the corresponding bits in levels, with a single
+    // write to GPSETn and GPCLRn for each affected bank
+    #[inline(always)]
+    pub(crate) fn set_levels(&self, mask: u64, levels: u64) {
+        let set = mask & levels;
+        let clear = mask & !levels;
+
+        for bank in 0..2 {
+            let shift = bank * 32;
+
+            if (set >> shift) as u32 > 0 {
+                self.write(GPSET0 + bank, (set >> shift) as u32);
+            }
+
+            if (clear >> shift) as u32 > 0 {
+                self.write(GPCLR0 + bank, (clear >> shift) as u32);
+            }
+        }
+    }
+
     pub(crate) fn mode(&self, pin: u8) -> Mode {
         let offset = GPFSEL0 + pin as usize / 10;
         let shift = (pin % 10) * 3;
@@ -213,7 +266,121 @@ impl GpioMem {
         self.locks[offset].store(false, Ordering::SeqCst);
     }
 
+    // Enables the selected event detection in GPRENn/GPFENn/GPHENn/GPLENn/GPARENn/GPAFENn,
+    // and disables all others
+    pub(crate) fn set_edge_detect(&self, pin: u8, detect: Detect) {
+        let registers = [
+            (
+                GPREN0,
+                detect == Detect::RisingEdge || detect == Detect::BothEdges,
+            ),
+            (
+                GPFEN0,
+                detect == Detect::FallingEdge || detect == Detect::BothEdges,
+            ),
+            (GPHEN0, detect == Detect::High),
+            (GPLEN0, detect == Detect::Low),
+            (
+                GPAREN0,
+                detect == Detect::AsyncRisingEdge || detect == Detect::AsyncBothEdges,
+            ),
+            (
+                GPAFEN0,
+                detect == Detect::AsyncFallingEdge || detect == Detect::AsyncBothEdges,
+            ),
+        ];
+
+        // Disable all detection first, so switching conditions never briefly enables two
+        // of them at once
+        for &(register, _) in registers.iter() {
+            self.set_bit(register + pin as usize / 32, pin % 32, false);
+        }
+
+        if detect == Detect::Disabled {
+            return;
+        }
+
+        // Clear any stale event before enabling detection
+        self.take_edge_detected(pin);
+
+        for &(register, enabled) in registers.iter().filter(|&&(_, enabled)| enabled) {
+            self.set_bit(register + pin as usize / 32, pin % 32, enabled);
+        }
+    }
+
+    // Returns true if an edge was detected since the last call, and clears the
+    // event. GPEDSn bits are cleared by writing a 1.
+    #[inline(always)]
+    pub(crate) fn take_edge_detected(&self, pin: u8) -> bool {
+        let offset = GPEDS0 + pin as usize / 32;
+        let mask = 1 << (pin % 32);
+
+        if self.read(offset) & mask > 0 {
+            self.write(offset, mask);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn set_bit(&self, offset: usize, shift: u8, value: bool) {
+        self.lock(offset);
+
+        let reg_value = self.read(offset);
+        if value {
+            self.write(offset, reg_value | (1 << shift));
+        } else {
+            self.write(offset, reg_value & !(1 << shift));
+        }
+
+        self.unlock(offset);
+    }
+
     pub(crate) fn set_pullupdown(&self, pin: u8, pud: PullUpDown) {
+        if self.soc == SoC::Bcm2711 {
+            self.set_pullupdown_bcm2711(pin, pud);
+        } else {
+            self.set_pullupdown_bcm283x(pin, pud);
+        }
+    }
+
+    // The BCM2711 has a separate register field for each pin's resistor state,
+    // which replaces the GPPUD/GPPUDCLK clocking sequence.
+    fn set_pullupdown_bcm2711(&self, pin: u8, pud: PullUpDown) {
+        let offset = GPPUPPDN0 + pin as usize / 16;
+        let shift = (pin % 16) * 2;
+
+        let value = match pud {
+            PullUpDown::Off => GPPUPPDN_OFF,
+            PullUpDown::PullUp => GPPUPPDN_PULLUP,
+            PullUpDown::PullDown => GPPUPPDN_PULLDOWN,
+        };
+
+        self.lock(offset);
+
+        let reg_value = self.read(offset);
+        self.write(offset, (reg_value & !(0b11 << shift)) | (value << shift));
+
+        self.unlock(offset);
+    }
+
+    // Spins until the lock for the register at offset is acquired
+    fn lock(&self, offset: usize) {
+        loop {
+            if self.locks[offset]
+                .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+            {
+                break;
+            }
+        }
+    }
+
+    fn unlock(&self, offset: usize) {
+        self.locks[offset].store(false, Ordering::SeqCst);
+    }
+
+    fn set_pullupdown_bcm283x(&self, pin: u8, pud: PullUpDown) {
         let offset = GPPUDCLK0 + pin as usize / 32;
         let shift = pin % 32;
 
@@ -252,6 +419,23 @@ impl GpioMem {
         self.locks[offset].store(false, Ordering::SeqCst);
         self.locks[GPPUD].store(false, Ordering::SeqCst);
     }
+
+    // The resistor state can only be read back on the BCM2711. The GPPUD/GPPUDCLK
+    // registers on earlier SoCs are write-only.
+    pub(crate) fn pullupdown(&self, pin: u8) -> Option<PullUpDown> {
+        if self.soc != SoC::Bcm2711 {
+            return None;
+        }
+
+        let offset = GPPUPPDN0 + pin as usize / 16;
+        let shift = (pin % 16) * 2;
+
+        match (self.read(offset) >> shift) & 0b11 {
+            GPPUPPDN_PULLUP => Some(PullUpDown::PullUp),
+            GPPUPPDN_PULLDOWN => Some(PullUpDown::PullDown),
+            _ => Some(PullUpDown::Off),
+        }
+    }
 }
 
 impl Drop for GpioMem {
@@ -0,0 +1,248 @@
+// Copyright (c) 2017-2019 Rene van der Meer
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL
+// THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::ptr;
+
+use libc::{self, c_void, off_t, size_t, MAP_FAILED, MAP_SHARED, O_SYNC, PROT_READ, PROT_WRITE};
+
+use crate::gpio::{Error, Result};
+use crate::system::DeviceInfo;
+
+const PATH_DEV_MEM: &str = "/dev/mem";
+
+// The pad control registers are located in the power management section, which
+// isn't exposed through /dev/gpiomem. We only need to map up to PADS2.
+const PADS_MEM_REGISTERS: usize = 14;
+const PADS_MEM_SIZE: usize = PADS_MEM_REGISTERS * std::mem::size_of::<u32>();
+
+const PADS0: usize = 0x2c / std::mem::size_of::<u32>();
+
+// Writes are ignored unless the password is set in bits 31:24.
+const PADS_PASSWD: u32 = 0x5a << 24;
+const PADS_SLEW: u32 = 1 << 4;
+const PADS_HYST: u32 = 1 << 3;
+const PADS_DRIVE_MASK: u32 = 0b111;
+
+/// GPIO pad banks.
+///
+/// The drive strength, slew rate and hysteresis settings are shared by all pins
+/// within a bank.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Bank {
+    /// BCM GPIO 0-27, which includes all pins on the GPIO header.
+    Bank0 = 0,
+    /// BCM GPIO 28-45.
+    Bank1 = 1,
+    /// BCM GPIO 46-53.
+    Bank2 = 2,
+}
+
+impl Bank {
+    /// Returns the bank that contains the specified BCM GPIO pin number.
+    ///
+    /// Returns `None` if `pin` is out of range.
+    pub fn from_pin(pin: u8) -> Option<Bank> {
+        match pin {
+            0..=27 => Some(Bank::Bank0),
+            28..=45 => Some(Bank::Bank1),
+            46..=53 => Some(Bank::Bank2),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Bank {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Bank::Bank0 => write!(f, "Bank0"),
+            Bank::Bank1 => write!(f, "Bank1"),
+            Bank::Bank2 => write!(f, "Bank2"),
+        }
+    }
+}
+
+/// Maximum output current.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum DriveStrength {
+    Ma2 = 0,
+    Ma4 = 1,
+    Ma6 = 2,
+    Ma8 = 3,
+    Ma10 = 4,
+    Ma12 = 5,
+    Ma14 = 6,
+    Ma16 = 7,
+}
+
+impl DriveStrength {
+    fn from_bits(bits: u32) -> DriveStrength {
+        match bits & PADS_DRIVE_MASK {
+            0 => DriveStrength::Ma2,
+            1 => DriveStrength::Ma4,
+            2 => DriveStrength::Ma6,
+            3 => DriveStrength::Ma8,
+            4 => DriveStrength::Ma10,
+            5 => DriveStrength::Ma12,
+            6 => DriveStrength::Ma14,
+            _ => DriveStrength::Ma16,
+        }
+    }
+}
+
+impl fmt::Display for DriveStrength {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} mA", (*self as u8 + 1) * 2)
+    }
+}
+
+/// Pad configuration for a [`Bank`].
+///
+/// The default configuration matches the state of the pads after a reset: 8 mA drive
+/// strength, no slew rate limiting, and input hysteresis enabled.
+///
+/// [`Bank`]: enum.Bank.html
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct PadConfig {
+    /// Maximum output current of each pin in the bank. The drive strength doesn't
+    /// limit the current. Exceeding it causes the output voltage to drop below
+    /// (or rise above) the valid logic levels.
+    pub drive_strength: DriveStrength,
+    /// Limits the slew rate of output transitions, which reduces ringing and
+    /// electromagnetic interference on long wires.
+    pub slew_rate_limited: bool,
+    /// Enables input hysteresis (Schmitt trigger), which prevents noisy or slowly
+    /// changing input signals from causing spurious level changes.
+    pub hysteresis: bool,
+}
+
+impl PadConfig {
+    pub(crate) fn from_register(value: u32) -> PadConfig {
+        PadConfig {
+            drive_strength: DriveStrength::from_bits(value),
+            slew_rate_limited: value & PADS_SLEW == 0,
+            hysteresis: value & PADS_HYST > 0,
+        }
+    }
+
+    pub(crate) fn to_register(self) -> u32 {
+        let mut value = self.drive_strength as u32;
+
+        if !self.slew_rate_limited {
+            value |= PADS_SLEW;
+        }
+
+        if self.hysteresis {
+            value |= PADS_HYST;
+        }
+
+        value
+    }
+}
+
+impl Default for PadConfig {
+    fn default() -> PadConfig {
+        PadConfig {
+            drive_strength: DriveStrength::Ma8,
+            slew_rate_limited: false,
+            hysteresis: true,
+        }
+    }
+}
+
+// Provides access to the pad control registers through /dev/mem.
+pub(crate) struct PadsMem {
+    mem_ptr: *mut u32,
+}
+
+impl fmt::Debug for PadsMem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PadsMem")
+            .field("mem_ptr", &self.mem_ptr)
+            .finish()
+    }
+}
+
+impl PadsMem {
+    pub(crate) fn open() -> Result<PadsMem> {
+        // Identify which SoC we're using, so we know what offset to start at
+        let device_info = DeviceInfo::new().map_err(|_| Error::UnknownModel)?;
+
+        let mem_file = match OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(O_SYNC)
+            .open(PATH_DEV_MEM)
+        {
+            Ok(file) => file,
+            Err(ref e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                return Err(Error::PermissionDenied(String::from(PATH_DEV_MEM)));
+            }
+            Err(e) => return Err(Error::Io(e)),
+        };
+
+        // Memory-map /dev/mem at the appropriate offset for our SoC
+        let mem_ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                PADS_MEM_SIZE,
+                PROT_READ | PROT_WRITE,
+                MAP_SHARED,
+                mem_file.as_raw_fd(),
+                (device_info.peripheral_base() + device_info.pads_offset()) as off_t,
+            )
+        };
+
+        if mem_ptr == MAP_FAILED {
+            return Err(Error::Io(io::Error::last_os_error()));
+        }
+
+        Ok(PadsMem {
+            mem_ptr: mem_ptr as *mut u32,
+        })
+    }
+
+    pub(crate) fn config(&self, bank: Bank) -> PadConfig {
+        let value = unsafe { ptr::read_volatile(self.mem_ptr.add(PADS0 + bank as usize)) };
+
+        PadConfig::from_register(value)
+    }
+
+    pub(crate) fn set_config(&self, bank: Bank, config: PadConfig) {
+        unsafe {
+            ptr::write_volatile(
+                self.mem_ptr.add(PADS0 + bank as usize),
+                PADS_PASSWD | config.to_register(),
+            );
+        }
+    }
+}
+
+impl Drop for PadsMem {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.mem_ptr as *mut c_void, PADS_MEM_SIZE as size_t);
+        }
+    }
+}
@@ -18,13 +18,20 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use std::os::unix::io::AsRawFd;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use std::time::Duration;
-
-use super::soft_pwm::SoftPwm;
-use crate::gpio::{interrupt::AsyncInterrupt, GpioState, Level, Mode, PullUpDown, Result, Trigger};
+use std::time::{Duration, Instant};
+
+use super::counter::FrequencyCounter;
+use super::soft_pwm::{PwmBackend, SoftPwm};
+use super::waveform::WaveformHandle;
+use crate::gpio::backend::GpioBackend;
+#[cfg(feature = "async")]
+use crate::gpio::EventStream;
+use crate::gpio::{
+    interrupt::{AsyncInterrupt, Interrupt},
+    Detect, Drive, Event, EventsRead, GpioState, Level, Mode, PullUpDown, Result, Trigger,
+};
 
 const NANOS_PER_SEC: f64 = 1_000_000_000.0;
 
@@ -44,6 +51,37 @@ macro_rules! impl_pin {
     }
 }
 
+macro_rules! impl_active_low {
+    () => {
+        /// Returns `true` if the pin is configured as active-low.
+        #[inline]
+        pub fn active_low(&self) -> bool {
+            self.pin.active_low
+        }
+
+        /// When enabled, inverts the pin's logic level.
+        ///
+        /// An active-low pin reads [`High`] when the line is low, and vice versa. Writing
+        /// [`High`] sets the line low. Interrupt trigger edges and the [`Level`] and
+        /// [`Event`] values delivered by interrupts are inverted as well, so a
+        /// [`RisingEdge`] trigger fires when the line goes low. By default, this is set
+        /// to `false`.
+        ///
+        /// Changing `active_low` doesn't affect the line itself. The new setting applies
+        /// to interrupt triggers and software-based PWM signals that are configured
+        /// after calling `set_active_low`.
+        ///
+        /// [`High`]: enum.Level.html#variant.High
+        /// [`Level`]: enum.Level.html
+        /// [`Event`]: struct.Event.html
+        /// [`RisingEdge`]: enum.Trigger.html#variant.RisingEdge
+        #[inline]
+        pub fn set_active_low(&mut self, active_low: bool) {
+            self.pin.active_low = active_low;
+        }
+    };
+}
+
 macro_rules! impl_input {
     () => {
         /// Reads the pin's logic level.
@@ -67,6 +105,15 @@ macro_rules! impl_input {
         pub fn is_high(&self) -> bool {
             self.pin.read() == Level::High
         }
+
+        /// Returns the current state of the built-in pull-up/pull-down resistors.
+        ///
+        /// The resistor state can only be read back on the BCM2711 (Raspberry Pi 4 B).
+        /// On earlier SoCs, `pullupdown` returns `None`.
+        #[inline]
+        pub fn pullupdown(&self) -> Option<PullUpDown> {
+            self.pin.pullupdown()
+        }
     }
 }
 
@@ -100,7 +147,7 @@ macro_rules! impl_output {
         /// [`High`]: enum.Level.html#variant.High
         #[inline]
         pub fn toggle(&mut self) {
-            if self.pin.read() == Level::Low {
+            if self.pin.output() == Level::Low {
                 self.set_high();
             } else {
                 self.set_low();
@@ -121,18 +168,33 @@ macro_rules! impl_output {
         /// If `set_pwm` is called when a PWM thread is already active, the existing thread
         /// will be reconfigured at the end of the current cycle.
         ///
+        /// The PWM signal is generated by the backend selected through [`set_pwm_backend`].
+        /// If the backend was changed since the PWM signal was started, the existing signal
+        /// is stopped first.
+        ///
         /// [`Pwm`]: ../pwm/struct.Pwm.html
         /// [here]: index.html#software-based-pwm
+        /// [`set_pwm_backend`]: #method.set_pwm_backend
         pub fn set_pwm(&mut self, period: Duration, pulse_width: Duration) -> Result<()> {
+            if let Some(ref mut soft_pwm) = self.soft_pwm {
+                if soft_pwm.backend() != self.pwm_backend {
+                    soft_pwm.stop()?;
+                    self.soft_pwm = None;
+                }
+            }
+
             if let Some(ref mut soft_pwm) = self.soft_pwm {
                 soft_pwm.reconfigure(period, pulse_width);
             } else {
                 self.soft_pwm = Some(SoftPwm::new(
+                    self.pwm_backend,
                     self.pin.pin,
+                    self.pin.drive,
+                    self.pin.active_low,
                     self.pin.gpio_state.clone(),
                     period,
                     pulse_width,
-                ));
+                )?);
             }
 
             // Store frequency/duty cycle for the embedded-hal PwmPin implementation.
@@ -190,6 +252,91 @@ macro_rules! impl_output {
 
             Ok(())
         }
+
+        /// Returns the backend used for software-based PWM.
+        pub fn pwm_backend(&self) -> PwmBackend {
+            self.pwm_backend
+        }
+
+        /// Selects the backend used for software-based PWM.
+        ///
+        /// By default, the PWM signal is emulated by a dedicated thread. [`PwmBackend::Scheduler`]
+        /// services all pins using that backend from a single shared thread.
+        /// [`PwmBackend::Dma`] generates the PWM signal through a DMA channel instead, which
+        /// is more accurate and doesn't use any CPU time, but requires root privileges. More
+        /// information can be found [here].
+        ///
+        /// The selected backend is used the next time [`set_pwm`] or [`set_pwm_frequency`]
+        /// is called.
+        ///
+        /// [`PwmBackend::Scheduler`]: enum.PwmBackend.html#variant.Scheduler
+        /// [`PwmBackend::Dma`]: enum.PwmBackend.html#variant.Dma
+        /// [here]: index.html#software-based-pwm
+        /// [`set_pwm`]: #method.set_pwm
+        /// [`set_pwm_frequency`]: #method.set_pwm_frequency
+        pub fn set_pwm_backend(&mut self, backend: PwmBackend) {
+            self.pwm_backend = backend;
+        }
+
+        /// Generates a waveform, and blocks until the waveform has completed.
+        ///
+        /// `waveform` consists of a sequence of output states, each of which is held
+        /// for the specified duration. Once the waveform has completed, the pin retains
+        /// the output state of the last entry.
+        ///
+        /// If [`PwmBackend::Dma`] is selected through [`set_pwm_backend`], the waveform
+        /// is generated through DMA, and each duration is rounded to the nearest step.
+        /// Otherwise, the waveform is generated by a dedicated thread, which is subject
+        /// to the same timing limitations as software-based PWM. More information can be
+        /// found [here].
+        ///
+        /// Any active software-based PWM signal is stopped first.
+        ///
+        /// [`PwmBackend::Dma`]: enum.PwmBackend.html#variant.Dma
+        /// [`set_pwm_backend`]: #method.set_pwm_backend
+        /// [here]: index.html#software-based-pwm
+        pub fn send_waveform(&mut self, waveform: &[(Level, Duration)]) -> Result<()> {
+            self.send_waveform_repeat(waveform, Some(1))?.wait()
+        }
+
+        /// Generates a repeating waveform in the background.
+        ///
+        /// `waveform` is repeated `count` times, or until the returned [`WaveformHandle`]
+        /// is stopped or goes out of scope if `count` is set to `None`. Use
+        /// [`WaveformHandle::wait`] to block until the waveform has completed.
+        ///
+        /// More information on how the waveform is generated can be found in the
+        /// documentation for [`send_waveform`].
+        ///
+        /// With [`PwmBackend::Dma`], all `count` repetitions are written out as a single
+        /// chain of DMA control blocks, which is limited to 65,535 control blocks. Each
+        /// entry takes up two control blocks, plus one for every 16,383 steps beyond the
+        /// first. If the repeated waveform doesn't fit, `send_waveform_repeat` returns an
+        /// [`Error::Io`] with `ErrorKind::InvalidInput`. A waveform that repeats
+        /// indefinitely is only written out once, and isn't affected by this limit.
+        ///
+        /// [`WaveformHandle`]: struct.WaveformHandle.html
+        /// [`PwmBackend::Dma`]: enum.PwmBackend.html#variant.Dma
+        /// [`Error::Io`]: enum.Error.html#variant.Io
+        /// [`WaveformHandle::wait`]: struct.WaveformHandle.html#method.wait
+        /// [`send_waveform`]: #method.send_waveform
+        pub fn send_waveform_repeat(
+            &mut self,
+            waveform: &[(Level, Duration)],
+            count: Option<u32>,
+        ) -> Result<WaveformHandle<'_>> {
+            self.clear_pwm()?;
+
+            WaveformHandle::new(
+                self.pwm_backend,
+                self.pin.pin,
+                self.pin.drive,
+                self.pin.active_low,
+                self.pin.gpio_state.clone(),
+                waveform,
+                count,
+            )
+        }
     }
 }
 
@@ -217,12 +364,16 @@ macro_rules! impl_reset_on_drop {
     };
 }
 
+// The optional $pre_drop method is called before the pin is reset, regardless
+// of the value of reset_on_drop
 macro_rules! impl_drop {
-    ($struct:ident) => {
+    ($struct:ident $(, $pre_drop:ident)?) => {
         impl Drop for $struct {
             /// Resets the pin's mode and disables the built-in pull-up/pull-down
             /// resistors if `reset_on_drop` is set to `true` (default).
             fn drop(&mut self) {
+                $(self.$pre_drop();)?
+
                 if !self.reset_on_drop {
                     return;
                 }
@@ -284,13 +435,22 @@ macro_rules! impl_eq {
 #[derive(Debug)]
 pub struct Pin {
     pub(crate) pin: u8,
-    gpio_state: Arc<GpioState>,
+    pub(crate) gpio_state: Arc<GpioState>,
+    // Output drive mode, which is only changed for open-drain/open-source OutputPins
+    pub(crate) drive: Drive,
+    // Inverts logic levels, which is only changed for InputPin, OutputPin and IoPin
+    pub(crate) active_low: bool,
 }
 
 impl Pin {
     #[inline]
     pub(crate) fn new(pin: u8, gpio_state: Arc<GpioState>) -> Pin {
-        Pin { pin, gpio_state }
+        Pin {
+            pin,
+            gpio_state,
+            drive: Drive::PushPull,
+            active_low: false,
+        }
     }
 
     /// Returns the GPIO pin number.
@@ -304,13 +464,22 @@ impl Pin {
     /// Returns the pin's mode.
     #[inline]
     pub fn mode(&self) -> Mode {
-        self.gpio_state.gpio_mem.mode(self.pin)
+        self.gpio_state.backend.mode(self.pin)
     }
 
     /// Reads the pin's logic level.
     #[inline]
     pub fn read(&self) -> Level {
-        self.gpio_state.gpio_mem.level(self.pin)
+        self.logical(self.gpio_state.backend.level(self.pin))
+    }
+
+    /// Returns the current state of the built-in pull-up/pull-down resistors.
+    ///
+    /// The resistor state can only be read back on the BCM2711 (Raspberry Pi 4 B).
+    /// On earlier SoCs, `pullupdown` returns `None`.
+    #[inline]
+    pub fn pullupdown(&self) -> Option<PullUpDown> {
+        self.gpio_state.backend.pullupdown(self.pin)
     }
 
     /// Consumes the `Pin`, returns an [`InputPin`], sets its mode to [`Input`],
@@ -357,7 +526,49 @@ impl Pin {
     /// [`Output`]: enum.Mode.html#variant.Output
     #[inline]
     pub fn into_output(self) -> OutputPin {
-        OutputPin::new(self)
+        OutputPin::new(self, Drive::PushPull)
+    }
+
+    /// Consumes the `Pin`, and returns an open-drain [`OutputPin`].
+    ///
+    /// An open-drain output only actively drives the line when its output state is set to
+    /// [`Low`]. When set to [`High`], the line is released, and pulled high by an external
+    /// pull-up resistor. This allows multiple devices to safely share a wired-AND line,
+    /// such as an interrupt line or a 1-Wire bus. The output state is initially set to
+    /// [`High`].
+    ///
+    /// The [`Cdev`] backend configures the line as an open-drain output through the
+    /// `gpiochip` character device. Other backends emulate an open-drain output by setting
+    /// the pin's mode to [`Output`] while the line is driven low, and to [`Input`] when
+    /// it's released.
+    ///
+    /// [`OutputPin`]: struct.OutputPin.html
+    /// [`Low`]: enum.Level.html#variant.Low
+    /// [`High`]: enum.Level.html#variant.High
+    /// [`Cdev`]: enum.Backend.html#variant.Cdev
+    /// [`Output`]: enum.Mode.html#variant.Output
+    /// [`Input`]: enum.Mode.html#variant.Input
+    #[inline]
+    pub fn into_output_open_drain(self) -> OutputPin {
+        OutputPin::new(self, Drive::OpenDrain)
+    }
+
+    /// Consumes the `Pin`, and returns an open-source [`OutputPin`].
+    ///
+    /// An open-source output only actively drives the line when its output state is set to
+    /// [`High`]. When set to [`Low`], the line is released, and pulled low by an external
+    /// pull-down resistor. The output state is initially set to [`Low`].
+    ///
+    /// Open-source outputs are configured or emulated the same way as open-drain outputs.
+    /// More information can be found [here].
+    ///
+    /// [`OutputPin`]: struct.OutputPin.html
+    /// [`Low`]: enum.Level.html#variant.Low
+    /// [`High`]: enum.Level.html#variant.High
+    /// [here]: #method.into_output_open_drain
+    #[inline]
+    pub fn into_output_open_source(self) -> OutputPin {
+        OutputPin::new(self, Drive::OpenSource)
     }
 
     /// Consumes the `Pin`, returns an [`IoPin`] and sets its mode to the specified mode.
@@ -371,22 +582,41 @@ impl Pin {
 
     #[inline]
     pub(crate) fn set_mode(&mut self, mode: Mode) {
-        self.gpio_state.gpio_mem.set_mode(self.pin, mode);
+        self.gpio_state.backend.set_mode(self.pin, mode);
     }
 
     #[inline]
     pub(crate) fn set_pullupdown(&mut self, pud: PullUpDown) {
-        self.gpio_state.gpio_mem.set_pullupdown(self.pin, pud);
+        self.gpio_state.backend.set_pullupdown(self.pin, pud);
     }
 
     #[inline]
     pub(crate) fn set_low(&mut self) {
-        self.gpio_state.gpio_mem.set_low(self.pin);
+        let level = self.logical(Level::Low);
+        self.gpio_state.backend.write(self.pin, level, self.drive);
     }
 
     #[inline]
     pub(crate) fn set_high(&mut self) {
-        self.gpio_state.gpio_mem.set_high(self.pin);
+        let level = self.logical(Level::High);
+        self.gpio_state.backend.write(self.pin, level, self.drive);
+    }
+
+    // Returns the output state, which only differs from the pin's logic
+    // level for open-drain and open-source outputs.
+    #[inline]
+    pub(crate) fn output(&self) -> Level {
+        self.logical(self.gpio_state.backend.output(self.pin, self.drive))
+    }
+
+    // Converts between the line's physical level and the pin's logic level
+    #[inline]
+    pub(crate) fn logical(&self, level: Level) -> Level {
+        if self.active_low {
+            !level
+        } else {
+            level
+        }
     }
 
     #[inline]
@@ -401,12 +631,27 @@ impl Pin {
 impl Drop for Pin {
     fn drop(&mut self) {
         // Release taken pin
+        self.gpio_state.backend.release(self.pin);
         self.gpio_state.pins_taken[self.pin as usize].store(false, Ordering::SeqCst);
     }
 }
 
 impl_eq!(Pin);
 
+// Disables edge detection when the InputPin goes out of scope, so the edge
+// detect registers aren't left configured without an owner.
+#[derive(Debug)]
+struct EdgeDetect {
+    pin: u8,
+    backend: Arc<dyn GpioBackend>,
+}
+
+impl Drop for EdgeDetect {
+    fn drop(&mut self) {
+        let _ = self.backend.set_edge_detect(self.pin, Detect::Disabled);
+    }
+}
+
 /// GPIO pin configured as input.
 ///
 /// `InputPin`s are constructed by converting a [`Pin`] using [`Pin::into_input`],
@@ -431,6 +676,9 @@ pub struct InputPin {
     pub(crate) pin: Pin,
     prev_mode: Option<Mode>,
     async_interrupt: Option<AsyncInterrupt>,
+    sync_interrupt: bool,
+    debounce: Option<Duration>,
+    edge_detect: Option<EdgeDetect>,
     reset_on_drop: bool,
     pud_mode: PullUpDown,
 }
@@ -452,6 +700,9 @@ impl InputPin {
             pin,
             prev_mode,
             async_interrupt: None,
+            sync_interrupt: false,
+            debounce: None,
+            edge_detect: None,
             reset_on_drop: true,
             pud_mode,
         }
@@ -459,6 +710,36 @@ impl InputPin {
 
     impl_pin!();
     impl_input!();
+    impl_active_low!();
+
+    /// Returns the debounce period for interrupt triggers.
+    pub fn debounce(&self) -> Option<Duration> {
+        self.debounce
+    }
+
+    /// Sets the debounce period for interrupt triggers.
+    ///
+    /// When a debounce period is set, any edges are ignored until the pin's logic
+    /// level has been stable for the specified duration, after which a single
+    /// trigger event is generated for the stable level. This prevents the
+    /// contact bounce of a mechanical switch from triggering multiple interrupts
+    /// for a single button press. Setting `period` to `None` disables debouncing.
+    ///
+    /// On Linux 5.10 or later, the input is debounced by the kernel. On earlier
+    /// versions, RPPAL falls back to a software-based filter that relies on the
+    /// event timestamps. In either case, debouncing delays trigger events by the
+    /// debounce period.
+    ///
+    /// The debounce period is applied to interrupt triggers that are configured
+    /// after calling `set_debounce`, using [`set_interrupt`], [`set_async_interrupt`]
+    /// or [`set_async_event_interrupt`].
+    ///
+    /// [`set_interrupt`]: #method.set_interrupt
+    /// [`set_async_interrupt`]: #method.set_async_interrupt
+    /// [`set_async_event_interrupt`]: #method.set_async_event_interrupt
+    pub fn set_debounce(&mut self, period: Option<Duration>) {
+        self.debounce = period.filter(|period| *period > Duration::new(0, 0));
+    }
 
     /// Configures a synchronous interrupt trigger.
     ///
@@ -473,11 +754,22 @@ impl InputPin {
         self.clear_async_interrupt()?;
 
         // Each pin can only be configured for a single trigger type
-        (*self.pin.gpio_state.sync_interrupts.lock().unwrap()).set_interrupt(self.pin(), trigger)
+        (*self.pin.gpio_state.sync_interrupts.lock().unwrap()).set_interrupt(
+            self.pin(),
+            trigger,
+            self.debounce,
+            self.pin.active_low,
+        )?;
+
+        self.sync_interrupt = true;
+
+        Ok(())
     }
 
     /// Removes a previously configured synchronous interrupt trigger.
     pub fn clear_interrupt(&mut self) -> Result<()> {
+        self.sync_interrupt = false;
+
         (*self.pin.gpio_state.sync_interrupts.lock().unwrap()).clear_interrupt(self.pin())
     }
 
@@ -506,14 +798,33 @@ impl InputPin {
         reset: bool,
         timeout: Option<Duration>,
     ) -> Result<Option<Level>> {
+        Ok(self.poll_event(reset, timeout)?.map(|event| event.level()))
+    }
+
+    /// Blocks until an interrupt is triggered on the pin, or a timeout occurs.
+    ///
+    /// `poll_event` behaves the same as [`poll_interrupt`], but returns an [`Event`] containing
+    /// the trigger's timestamp and sequence number instead of the pin's logic level.
+    ///
+    /// [`poll_interrupt`]: #method.poll_interrupt
+    /// [`Event`]: struct.Event.html
+    pub fn poll_event(&mut self, reset: bool, timeout: Option<Duration>) -> Result<Option<Event>> {
         let opt =
             (*self.pin.gpio_state.sync_interrupts.lock().unwrap()).poll(&[self], reset, timeout)?;
 
-        if let Some(trigger) = opt {
-            Ok(Some(trigger.1))
-        } else {
-            Ok(None)
-        }
+        Ok(opt.map(|(_, event)| event))
+    }
+
+    /// Reads all queued interrupt trigger events for the pin, without blocking.
+    ///
+    /// This only works after the pin has been configured for synchronous interrupts using
+    /// [`set_interrupt`]. More information can be found in the documentation for
+    /// [`Gpio::read_events`].
+    ///
+    /// [`set_interrupt`]: #method.set_interrupt
+    /// [`Gpio::read_events`]: struct.Gpio.html#method.read_events
+    pub fn read_events(&mut self, events: &mut [Event]) -> Result<EventsRead> {
+        (*self.pin.gpio_state.sync_interrupts.lock().unwrap()).read_events(&[self], events)
     }
 
     /// Configures an asynchronous interrupt trigger, which executes the callback on a
@@ -521,29 +832,91 @@ impl InputPin {
     ///
     /// The callback closure or function pointer is called with a single [`Level`] argument.
     ///
+    /// Callbacks for all pins are executed on a single dispatcher thread, unless an executor
+    /// has been configured through [`Gpio::set_callback_executor`]. A callback that blocks
+    /// delays the callbacks for any other pins.
+    ///
     /// Any previously configured (a)synchronous interrupt triggers for this pin are cleared
     /// when `set_async_interrupt` is called, or when `InputPin` goes out of scope.
     ///
     /// [`clear_async_interrupt`]: #method.clear_async_interrupt
     /// [`Level`]: enum.Level.html
-    pub fn set_async_interrupt<C>(&mut self, trigger: Trigger, callback: C) -> Result<()>
+    /// [`Gpio::set_callback_executor`]: struct.Gpio.html#method.set_callback_executor
+    pub fn set_async_interrupt<C>(&mut self, trigger: Trigger, mut callback: C) -> Result<()>
     where
         C: FnMut(Level) + Send + 'static,
+    {
+        self.set_async_event_interrupt(trigger, move |event| callback(event.level()))
+    }
+
+    /// Configures an asynchronous interrupt trigger, which executes the callback on a
+    /// separate thread when the interrupt is triggered.
+    ///
+    /// `set_async_event_interrupt` behaves the same as [`set_async_interrupt`], but the
+    /// callback closure or function pointer is called with a single [`Event`] argument,
+    /// containing the trigger's timestamp and sequence number.
+    ///
+    /// [`set_async_interrupt`]: #method.set_async_interrupt
+    /// [`Event`]: struct.Event.html
+    pub fn set_async_event_interrupt<C>(&mut self, trigger: Trigger, callback: C) -> Result<()>
+    where
+        C: FnMut(Event) + Send + 'static,
     {
         self.clear_interrupt()?;
         self.clear_async_interrupt()?;
 
         self.async_interrupt = Some(AsyncInterrupt::new(
-            self.pin.gpio_state.cdev.as_raw_fd(),
+            self.pin.gpio_state.async_interrupts()?,
+            self.pin.gpio_state.backend.clone(),
             self.pin(),
             trigger,
+            self.debounce,
+            self.pin.active_low,
             callback,
         )?);
 
         Ok(())
     }
 
+    /// Returns a [`Stream`] of interrupt trigger events for the pin.
+    ///
+    /// `events` offers an alternative to [`set_async_event_interrupt`] for applications
+    /// that use an async runtime, such as `tokio` or `async-std`. Rather than executing
+    /// a callback on the dispatcher thread, the returned [`EventStream`] can be awaited
+    /// alongside other futures and streams.
+    ///
+    /// Any previously configured (a)synchronous interrupt triggers for this pin are cleared
+    /// when `events` is called. The interrupt trigger is removed when the [`EventStream`]
+    /// goes out of scope. The debounce period set through [`set_debounce`] is applied to
+    /// the stream.
+    ///
+    /// `events` is only available when the optional `async` feature is enabled.
+    ///
+    /// [`Stream`]: https://docs.rs/futures-core/0.3/futures_core/stream/trait.Stream.html
+    /// [`set_async_event_interrupt`]: #method.set_async_event_interrupt
+    /// [`EventStream`]: struct.EventStream.html
+    /// [`set_debounce`]: #method.set_debounce
+    #[cfg(feature = "async")]
+    pub fn events(&mut self, trigger: Trigger) -> Result<EventStream<'_>> {
+        self.clear_interrupt()?;
+        self.clear_async_interrupt()?;
+
+        let interrupt = Interrupt::new(
+            self.pin.gpio_state.backend.clone(),
+            self.pin(),
+            trigger,
+            self.debounce,
+            self.pin.active_low,
+        )?;
+
+        EventStream::new(interrupt, self.pin.gpio_state.reactor()?)
+    }
+
     /// Removes a previously configured asynchronous interrupt trigger.
+    ///
+    /// If the callback is currently being executed, `clear_async_interrupt` waits for
+    /// it to return, unless it's called from within the callback itself. Once
+    /// `clear_async_interrupt` returns, the callback won't be called again.
     pub fn clear_async_interrupt(&mut self) -> Result<()> {
         if let Some(mut interrupt) = self.async_interrupt.take() {
             interrupt.stop()?;
@@ -552,16 +925,168 @@ impl InputPin {
         Ok(())
     }
 
+    /// Blocks until a complete pulse is detected on the pin, and returns its duration.
+    ///
+    /// A pulse starts with an edge that changes the pin's logic level to `level`, and
+    /// ends with the next edge that changes it back. Any pulse that's already in
+    /// progress when `measure_pulse` is called is ignored. The duration is calculated
+    /// from the timestamps of the interrupt trigger events, and the debounce period set
+    /// through [`set_debounce`] is applied to both edges.
+    ///
+    /// The `timeout` duration indicates how long the call will block while waiting
+    /// for the pulse to complete, after which an `Ok(None)` is returned. `timeout` can
+    /// be set to `None` to wait indefinitely.
+    ///
+    /// Any previously configured (a)synchronous interrupt triggers for this pin are
+    /// cleared when `measure_pulse` is called.
+    ///
+    /// [`set_debounce`]: #method.set_debounce
+    pub fn measure_pulse(
+        &mut self,
+        level: Level,
+        timeout: Option<Duration>,
+    ) -> Result<Option<Duration>> {
+        self.clear_interrupt()?;
+        self.clear_async_interrupt()?;
+
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        let mut interrupt = Interrupt::new(
+            self.pin.gpio_state.backend.clone(),
+            self.pin(),
+            Trigger::Both,
+            self.debounce,
+            self.pin.active_low,
+        )?;
+
+        let start = loop {
+            match interrupt.wait_event(deadline)? {
+                Some(event) if event.level() == level => break event.timestamp,
+                Some(_) => (),
+                None => return Ok(None),
+            }
+        };
+
+        let end = loop {
+            match interrupt.wait_event(deadline)? {
+                Some(event) if event.level() != level => break event.timestamp,
+                Some(_) => (),
+                None => return Ok(None),
+            }
+        };
+
+        Ok(Some(end.checked_sub(start).unwrap_or_default()))
+    }
+
+    /// Returns a [`FrequencyCounter`] that continuously measures the frequency, period
+    /// and duty cycle of the signal on the pin.
+    ///
+    /// The frequency is averaged over the rising edges that occurred within `window`.
+    /// A longer window results in a more stable reading, while a shorter window
+    /// responds faster to changes in frequency.
+    ///
+    /// Any previously configured (a)synchronous interrupt triggers for this pin are
+    /// cleared when `frequency_counter` is called. The interrupt trigger is removed
+    /// when the [`FrequencyCounter`] goes out of scope. The debounce period set through
+    /// [`set_debounce`] is applied to the counter.
+    ///
+    /// [`FrequencyCounter`]: struct.FrequencyCounter.html
+    /// [`set_debounce`]: #method.set_debounce
+    pub fn frequency_counter(&mut self, window: Duration) -> Result<FrequencyCounter<'_>> {
+        FrequencyCounter::new(self, window)
+    }
+
+    /// Configures the GPIO peripheral's edge or level detection for the pin.
+    ///
+    /// Event detection offers a low-latency alternative to interrupts for tight control
+    /// loops. Rather than waiting for the kernel to deliver an interrupt trigger event, the
+    /// event detect registers are accessed directly, and polled with [`take_edge_detected`].
+    /// `detect` selects synchronous or asynchronous edge detection, or level detection.
+    /// Setting `detect` to [`Disabled`] disables event detection, which also happens
+    /// automatically when `InputPin` goes out of scope.
+    ///
+    /// Event detection requires direct access to the GPIO registers. The [`Cdev`] backend
+    /// returns `Err(`[`Error::NotSupported`]`)`.
+    ///
+    /// # Interference with kernel interrupts
+    ///
+    /// Every detected event raises the GPIO bank's interrupt, which is handled by the
+    /// kernel's GPIO driver. The driver only acknowledges events on lines that have an
+    /// interrupt trigger configured through the `gpiochip` character device. Events on
+    /// any other pin keep the interrupt asserted, until the kernel considers it spurious
+    /// ("nobody cared") and disables it. From that point until the next reboot, **all**
+    /// interrupt triggers on the GPIO chip stop working, for every pin and every process,
+    /// including [`poll_interrupt`] and [`set_async_interrupt`].
+    ///
+    /// Only use event detection on systems where no process relies on GPIO interrupt
+    /// triggers. The same registers are also used by the kernel to implement interrupt
+    /// triggers, so event detection can't be combined with (a)synchronous interrupts on
+    /// the same pin either.
+    ///
+    /// [`take_edge_detected`]: #method.take_edge_detected
+    /// [`Disabled`]: enum.Detect.html#variant.Disabled
+    /// [`Cdev`]: enum.Backend.html#variant.Cdev
+    /// [`Error::NotSupported`]: enum.Error.html#variant.NotSupported
+    /// [`poll_interrupt`]: #method.poll_interrupt
+    /// [`set_async_interrupt`]: #method.set_async_interrupt
+    pub fn set_edge_detect(&mut self, detect: Detect) -> Result<()> {
+        if detect == Detect::Disabled {
+            self.edge_detect = None;
+
+            return Ok(());
+        }
+
+        let detect = if self.pin.active_low {
+            detect.invert()
+        } else {
+            detect
+        };
+
+        let backend = &self.pin.gpio_state.backend;
+        backend.set_edge_detect(self.pin.pin, detect)?;
+
+        if self.edge_detect.is_none() {
+            self.edge_detect = Some(EdgeDetect {
+                pin: self.pin.pin,
+                backend: backend.clone(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Returns `true` if an event matching the condition configured with [`set_edge_detect`]
+    /// was detected since the previous call, and clears the event.
+    ///
+    /// Multiple edges that occur between two calls are reported as a single event. With
+    /// level detection, the event is set again immediately for as long as the pin remains
+    /// at the selected level.
+    ///
+    /// [`set_edge_detect`]: #method.set_edge_detect
+    #[inline]
+    pub fn take_edge_detected(&mut self) -> bool {
+        self.pin.gpio_state.backend.take_edge_detected(self.pin.pin)
+    }
+
+    // Releases the synchronous interrupt trigger, so the line doesn't linger in
+    // the event loop after the pin is dropped
+    fn release_interrupt(&mut self) {
+        if self.sync_interrupt {
+            let _ = self.clear_interrupt();
+        }
+    }
+
     impl_reset_on_drop!();
 }
 
-impl_drop!(InputPin);
+impl_drop!(InputPin, release_interrupt);
 impl_eq!(InputPin);
 
 /// GPIO pin configured as output.
 ///
-/// `OutputPin`s are constructed by converting a [`Pin`] using [`Pin::into_output`].
-/// The pin's mode is automatically set to [`Output`].
+/// `OutputPin`s are constructed by converting a [`Pin`] using [`Pin::into_output`],
+/// [`Pin::into_output_open_drain`] or [`Pin::into_output_open_source`]. The pin's
+/// mode is automatically set to [`Output`].
 ///
 /// An `OutputPin` can be used to change a pin's output state.
 ///
@@ -581,15 +1106,18 @@ impl_eq!(InputPin);
 /// [`Pin`]: struct.Pin.html
 /// [`Output`]: enum.Mode.html#variant.Output
 /// [`Pin::into_output`]: struct.Pin.html#method.into_output
+/// [`Pin::into_output_open_drain`]: struct.Pin.html#method.into_output_open_drain
+/// [`Pin::into_output_open_source`]: struct.Pin.html#method.into_output_open_source
 /// [`digital::OutputPin`]: ../../embedded_hal/digital/trait.OutputPin.html
 /// [`PwmPin`]: ../../embedded_hal/trait.PwmPin.html
 #[derive(Debug)]
 pub struct OutputPin {
-    pin: Pin,
+    pub(crate) pin: Pin,
     prev_mode: Option<Mode>,
     reset_on_drop: bool,
     pud_mode: PullUpDown,
     pub(crate) soft_pwm: Option<SoftPwm>,
+    pwm_backend: PwmBackend,
     // Stores the softpwm frequency. Used for embedded_hal::PwmPin.
     #[cfg(feature = "hal")]
     pub(crate) frequency: f64,
@@ -599,14 +1127,27 @@ pub struct OutputPin {
 }
 
 impl OutputPin {
-    pub(crate) fn new(mut pin: Pin) -> OutputPin {
+    pub(crate) fn new(mut pin: Pin, drive: Drive) -> OutputPin {
         let prev_mode = pin.mode();
 
-        let prev_mode = if prev_mode == Mode::Output {
-            None
-        } else {
-            pin.set_mode(Mode::Output);
-            Some(prev_mode)
+        let prev_mode = match drive {
+            Drive::PushPull if prev_mode == Mode::Output => None,
+            Drive::PushPull => {
+                pin.set_mode(Mode::Output);
+                Some(prev_mode)
+            }
+            // Emulated open-drain/open-source outputs switch between modes,
+            // so the original mode always needs to be restored.
+            Drive::OpenDrain => {
+                pin.drive = drive;
+                pin.set_high();
+                Some(prev_mode)
+            }
+            Drive::OpenSource => {
+                pin.drive = drive;
+                pin.set_low();
+                Some(prev_mode)
+            }
         };
 
         OutputPin {
@@ -615,6 +1156,7 @@ impl OutputPin {
             reset_on_drop: true,
             pud_mode: PullUpDown::Off,
             soft_pwm: None,
+            pwm_backend: PwmBackend::Thread,
             #[cfg(feature = "hal")]
             frequency: 0.0,
             #[cfg(feature = "hal")]
@@ -624,12 +1166,27 @@ impl OutputPin {
 
     impl_pin!();
 
+    /// Returns the pin's output drive mode.
+    #[inline]
+    pub fn drive(&self) -> Drive {
+        self.pin.drive
+    }
+
+    /// Reads the logic level of the line.
+    ///
+    /// For open-drain and open-source outputs, the logic level differs from the output
+    /// state while the line is released and another device drives it.
+    #[inline]
+    pub fn read(&self) -> Level {
+        self.pin.read()
+    }
+
     /// Returns `true` if the pin's output state is set to [`Low`].
     ///
     /// [`Low`]: enum.Level.html#variant.Low
     #[inline]
     pub fn is_set_low(&self) -> bool {
-        self.pin.read() == Level::Low
+        self.pin.output() == Level::Low
     }
 
     /// Returns `true` if the pin's output state is set to [`High`].
@@ -637,10 +1194,11 @@ impl OutputPin {
     /// [`High`]: enum.Level.html#variant.High
     #[inline]
     pub fn is_set_high(&self) -> bool {
-        self.pin.read() == Level::High
+        self.pin.output() == Level::High
     }
 
     impl_output!();
+    impl_active_low!();
     impl_reset_on_drop!();
 }
 
@@ -683,6 +1241,7 @@ pub struct IoPin {
     reset_on_drop: bool,
     pud_mode: PullUpDown,
     pub(crate) soft_pwm: Option<SoftPwm>,
+    pwm_backend: PwmBackend,
     // Stores the softpwm frequency. Used for embedded_hal::PwmPin.
     #[cfg(feature = "hal")]
     pub(crate) frequency: f64,
@@ -709,6 +1268,7 @@ impl IoPin {
             reset_on_drop: true,
             pud_mode: PullUpDown::Off,
             soft_pwm: None,
+            pwm_backend: PwmBackend::Thread,
             #[cfg(feature = "hal")]
             frequency: 0.0,
             #[cfg(feature = "hal")]
@@ -747,6 +1307,7 @@ impl IoPin {
 
     impl_input!();
     impl_output!();
+    impl_active_low!();
     impl_reset_on_drop!();
 }
 
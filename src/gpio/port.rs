@@ -0,0 +1,260 @@
+// Copyright (c) 2017-2019 Rene van der Meer
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL
+// THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use std::sync::Arc;
+
+use crate::gpio::backend::GpioBackend;
+use crate::gpio::pin::{InputPin, OutputPin};
+use crate::gpio::soft_pwm::invalid_input;
+use crate::gpio::{Drive, GpioState, Level, Result};
+
+// Returns the backend shared by all pins. Pins retrieved from different chips
+// or simulators don't share a GpioState, and are accessed through different backends.
+fn shared_backend<'a, I>(mut states: I) -> Result<Arc<dyn GpioBackend>>
+where
+    I: Iterator<Item = &'a Arc<GpioState>>,
+{
+    let first = states
+        .next()
+        .ok_or_else(|| invalid_input("A port requires at least one pin"))?;
+
+    if states.all(|state| Arc::ptr_eq(state, first)) {
+        Ok(first.backend.clone())
+    } else {
+        Err(invalid_input(
+            "All pins in a port must share the same backend",
+        ))
+    }
+}
+
+// Maps the bits of a port value to the corresponding BCM GPIO pin bits
+fn to_pin_mask(pins: &[u8], value: u64) -> u64 {
+    pins.iter()
+        .enumerate()
+        .filter(|&(bit, _)| value & (1 << bit) > 0)
+        .fold(0, |mask, (_, &pin)| mask | (1 << pin))
+}
+
+// Maps BCM GPIO pin bits to the corresponding bits of a port value
+fn from_pin_mask(pins: &[u8], pin_mask: u64) -> u64 {
+    pins.iter()
+        .enumerate()
+        .filter(|&(_, &pin)| pin_mask & (1 << pin) > 0)
+        .fold(0, |value, (bit, _)| value | (1 << bit))
+}
+
+/// A group of [`OutputPin`]s that are written simultaneously.
+///
+/// `OutputPort` combines several [`OutputPin`]s into a single port, and sets their logic
+/// levels based on the bits of a single value, which is useful for parallel buses such as
+/// 8-bit LCD data lines or resistor ladder DACs. Bit 0 corresponds to the first pin, bit 1
+/// to the second pin, and so on. Bits for active-low pins are inverted.
+///
+/// With the default [`Native`] backend, `write` updates all pins with a single write to the
+/// `GPSET` register, followed by a single write to the `GPCLR` register, for each of the two
+/// register banks (GPIO 0-31 and 32-53). With the [`Cdev`] backend, the pins are requested
+/// together through the `gpiochip` character device, and updated with a single system call.
+/// Open-drain and open-source pins are always updated individually, after all other pins.
+///
+/// [`OutputPin`]: struct.OutputPin.html
+/// [`Native`]: enum.Backend.html#variant.Native
+/// [`Cdev`]: enum.Backend.html#variant.Cdev
+#[derive(Debug)]
+pub struct OutputPort {
+    pins: Vec<OutputPin>,
+    pin_numbers: Vec<u8>,
+    // Push-pull pins, which are written simultaneously
+    pin_mask: u64,
+    // Active-low push-pull pins
+    invert_mask: u64,
+    // Indices of open-drain/open-source pins, which are written one at a time
+    single_pins: Vec<usize>,
+    backend: Arc<dyn GpioBackend>,
+}
+
+impl OutputPort {
+    /// Constructs a new `OutputPort` from the specified pins.
+    ///
+    /// Returns an error if `pins` is empty, or if the pins don't share the same backend,
+    /// which happens when they're retrieved from different [`Chip`]s or [`Simulator`]s.
+    ///
+    /// [`Chip`]: struct.Chip.html
+    /// [`Simulator`]: struct.Simulator.html
+    pub fn new(pins: Vec<OutputPin>) -> Result<OutputPort> {
+        let backend = shared_backend(pins.iter().map(|pin| &pin.pin.gpio_state))?;
+
+        let pin_numbers: Vec<u8> = pins.iter().map(|pin| pin.pin()).collect();
+        let pin_mask = pins
+            .iter()
+            .filter(|pin| pin.drive() == Drive::PushPull)
+            .fold(0, |mask, pin| mask | (1 << pin.pin()));
+        let invert_mask = pins
+            .iter()
+            .filter(|pin| pin.active_low())
+            .fold(0, |mask, pin| mask | (1 << pin.pin()))
+            & pin_mask;
+        let single_pins = (0..pins.len())
+            .filter(|&idx| pins[idx].drive() != Drive::PushPull)
+            .collect();
+
+        backend.combine(pin_mask)?;
+
+        Ok(OutputPort {
+            pins,
+            pin_numbers,
+            pin_mask,
+            invert_mask,
+            single_pins,
+            backend,
+        })
+    }
+
+    /// Returns the GPIO pin numbers, in the same order as the bits of the port's value.
+    pub fn pins(&self) -> &[u8] {
+        &self.pin_numbers
+    }
+
+    /// Sets the logic level of each pin to the corresponding bit in `value`.
+    ///
+    /// Any bits beyond the number of pins in the port are ignored.
+    #[inline]
+    pub fn write(&mut self, value: u64) {
+        self.backend.set_levels(
+            self.pin_mask,
+            to_pin_mask(&self.pin_numbers, value) ^ self.invert_mask,
+        );
+
+        for &idx in &self.single_pins {
+            self.pins[idx].write(if value & (1 << idx) > 0 {
+                Level::High
+            } else {
+                Level::Low
+            });
+        }
+    }
+
+    /// Returns the output state of each pin as the corresponding bit.
+    #[inline]
+    pub fn read(&self) -> u64 {
+        let value = from_pin_mask(
+            &self.pin_numbers,
+            self.backend.levels(self.pin_mask) ^ self.invert_mask,
+        );
+
+        self.single_pins
+            .iter()
+            .filter(|&&idx| self.pins[idx].is_set_high())
+            .fold(value, |value, &idx| value | (1 << idx))
+    }
+
+    /// Returns the individual pins, consuming the `OutputPort`.
+    pub fn into_pins(mut self) -> Vec<OutputPin> {
+        self.backend.split(self.pin_mask);
+
+        self.pins.drain(..).collect()
+    }
+}
+
+impl Drop for OutputPort {
+    fn drop(&mut self) {
+        self.backend.split(self.pin_mask);
+    }
+}
+
+/// A group of [`InputPin`]s that are read simultaneously.
+///
+/// `InputPort` combines several [`InputPin`]s into a single port, and returns their logic
+/// levels as the bits of a single value. Bit 0 corresponds to the first pin, bit 1 to the
+/// second pin, and so on. Bits for active-low pins are inverted.
+///
+/// With the default [`Native`] backend, `read` retrieves the logic levels of all pins with a
+/// single read of the `GPLEV` register for each of the two register banks (GPIO 0-31 and 32-53).
+/// With the [`Cdev`] backend, the pins are requested together through the `gpiochip` character
+/// device, and read with a single system call.
+///
+/// [`InputPin`]: struct.InputPin.html
+/// [`Native`]: enum.Backend.html#variant.Native
+/// [`Cdev`]: enum.Backend.html#variant.Cdev
+#[derive(Debug)]
+pub struct InputPort {
+    pins: Vec<InputPin>,
+    pin_numbers: Vec<u8>,
+    pin_mask: u64,
+    // Active-low pins
+    invert_mask: u64,
+    backend: Arc<dyn GpioBackend>,
+}
+
+impl InputPort {
+    /// Constructs a new `InputPort` from the specified pins.
+    ///
+    /// Returns an error if `pins` is empty, or if the pins don't share the same backend,
+    /// which happens when they're retrieved from different [`Chip`]s or [`Simulator`]s.
+    ///
+    /// [`Chip`]: struct.Chip.html
+    /// [`Simulator`]: struct.Simulator.html
+    pub fn new(pins: Vec<InputPin>) -> Result<InputPort> {
+        let backend = shared_backend(pins.iter().map(|pin| &pin.pin.gpio_state))?;
+
+        let pin_numbers: Vec<u8> = pins.iter().map(|pin| pin.pin()).collect();
+        let pin_mask = pin_numbers.iter().fold(0, |mask, pin| mask | (1 << pin));
+        let invert_mask = pins
+            .iter()
+            .filter(|pin| pin.active_low())
+            .fold(0, |mask, pin| mask | (1 << pin.pin()));
+
+        backend.combine(pin_mask)?;
+
+        Ok(InputPort {
+            pins,
+            pin_numbers,
+            pin_mask,
+            invert_mask,
+            backend,
+        })
+    }
+
+    /// Returns the GPIO pin numbers, in the same order as the bits of the port's value.
+    pub fn pins(&self) -> &[u8] {
+        &self.pin_numbers
+    }
+
+    /// Reads the logic level of each pin, and returns it as the corresponding bit.
+    #[inline]
+    pub fn read(&self) -> u64 {
+        from_pin_mask(
+            &self.pin_numbers,
+            self.backend.levels(self.pin_mask) ^ self.invert_mask,
+        )
+    }
+
+    /// Returns the individual pins, consuming the `InputPort`.
+    pub fn into_pins(mut self) -> Vec<InputPin> {
+        self.backend.split(self.pin_mask);
+
+        self.pins.drain(..).collect()
+    }
+}
+
+impl Drop for InputPort {
+    fn drop(&mut self) {
+        self.backend.split(self.pin_mask);
+    }
+}
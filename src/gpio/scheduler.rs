@@ -0,0 +1,430 @@
+// Copyright (c) 2017-2019 Rene van der Meer
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL
+// THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+// Shared software-based PWM scheduler.
+//
+// Rather than spawning a separate thread for each pin, all pins using
+// PwmBackend::Scheduler are serviced by a single real-time thread. The thread
+// keeps track of the next edge for each channel, and sleeps/busy-waits until the
+// earliest one. Edges that fall within the configured resolution of the earliest
+// edge are handled at the same time, with a single set_levels() call for each
+// GPIO backend, which translates to a single GPSETn/GPCLRn write per bank.
+
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+
+use super::soft_pwm::{get_time_ns, invalid_input, recv_until_ns, set_realtime_priority};
+use super::{Drive, Error, GpioState, Level, Result};
+
+// set_levels() is limited to a 64-bit mask. Any pins beyond that are written individually.
+const MAX_MASK_PIN: u8 = 64;
+
+/// Configuration for the shared software-based PWM scheduler.
+///
+/// All pins using [`PwmBackend::Scheduler`] are serviced by a single thread. Edges
+/// that fall within `resolution` of each other are written simultaneously, using a
+/// single register write for each GPIO bank when possible.
+///
+/// The default configuration supports up to 32 channels, with a resolution of 5 µs.
+///
+/// [`PwmBackend::Scheduler`]: enum.PwmBackend.html#variant.Scheduler
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct SchedulerConfig {
+    /// Maximum number of pins that can be driven simultaneously.
+    pub max_channels: usize,
+    /// Edges that are due within `resolution` of the earliest pending edge are written
+    /// at the same time. Larger values reduce the number of register writes, at the
+    /// cost of accuracy.
+    pub resolution: Duration,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> SchedulerConfig {
+        SchedulerConfig {
+            max_channels: 32,
+            resolution: Duration::from_micros(5),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Msg {
+    Add(usize, Channel),
+    Reconfigure(usize, Duration, Duration),
+    Remove(usize, Sender<()>),
+    Stop,
+}
+
+#[derive(Debug)]
+struct Channel {
+    pin: u8,
+    drive: Drive,
+    active_low: bool,
+    gpio_state: Arc<GpioState>,
+    period_ns: i64,
+    pulse_width_ns: i64,
+    // Period and pulse width that take effect at the start of the next cycle
+    pending: Option<(i64, i64)>,
+    cycle_start_ns: i64,
+    // None if the channel is idle
+    next_edge_ns: Option<i64>,
+    // True if the next edge ends the active part of the current cycle
+    in_pulse: bool,
+}
+
+impl Channel {
+    fn new(
+        pin: u8,
+        drive: Drive,
+        active_low: bool,
+        gpio_state: Arc<GpioState>,
+        period: Duration,
+        pulse_width: Duration,
+    ) -> Channel {
+        let mut channel = Channel {
+            pin,
+            drive,
+            active_low,
+            gpio_state,
+            period_ns: 0,
+            pulse_width_ns: 0,
+            pending: None,
+            cycle_start_ns: 0,
+            next_edge_ns: None,
+            in_pulse: false,
+        };
+
+        channel.reconfigure(period, pulse_width, get_time_ns());
+
+        channel
+    }
+
+    fn reconfigure(&mut self, period: Duration, pulse_width: Duration, now_ns: i64) {
+        let period_ns = period.as_nanos() as i64;
+        let pulse_width_ns = (pulse_width.as_nanos() as i64).min(period_ns);
+
+        self.pending = Some((period_ns, pulse_width_ns));
+
+        // Idle channels start a new cycle immediately
+        if self.next_edge_ns.is_none() {
+            self.next_edge_ns = Some(now_ns);
+        }
+    }
+
+    fn level(&self, active: bool) -> Level {
+        if active != self.active_low {
+            Level::High
+        } else {
+            Level::Low
+        }
+    }
+
+    // Moves past the next edge, and returns the pin's new output state
+    fn advance(&mut self, now_ns: i64) -> Level {
+        let edge_ns = self.next_edge_ns.unwrap_or(now_ns);
+
+        if self.in_pulse {
+            self.in_pulse = false;
+            self.next_edge_ns = Some(self.cycle_start_ns + self.period_ns);
+
+            return self.level(false);
+        }
+
+        // Start of a new cycle
+        if let Some((period_ns, pulse_width_ns)) = self.pending.take() {
+            self.period_ns = period_ns;
+            self.pulse_width_ns = pulse_width_ns;
+        }
+
+        // If we've fallen behind by more than a full period, start over rather than
+        // trying to catch up
+        self.cycle_start_ns = if now_ns - edge_ns > self.period_ns {
+            now_ns
+        } else {
+            edge_ns
+        };
+
+        if self.period_ns == 0 {
+            self.next_edge_ns = None;
+
+            self.level(false)
+        } else if self.pulse_width_ns == 0 || self.pulse_width_ns == self.period_ns {
+            self.next_edge_ns = Some(self.cycle_start_ns + self.period_ns);
+
+            self.level(self.pulse_width_ns > 0)
+        } else {
+            self.in_pulse = true;
+            self.next_edge_ns = Some(self.cycle_start_ns + self.pulse_width_ns);
+
+            self.level(true)
+        }
+    }
+}
+
+// Handles the edges for all channels that are due before window_ns
+fn write_edges(channels: &mut [(usize, Channel)], now_ns: i64, window_ns: i64) {
+    let mut writes: Vec<(&Arc<GpioState>, u64, u64)> = Vec::new();
+
+    for (_, channel) in channels.iter_mut() {
+        match channel.next_edge_ns {
+            Some(edge_ns) if edge_ns <= window_ns => (),
+            _ => continue,
+        }
+
+        let level = channel.advance(now_ns);
+
+        if channel.drive != Drive::PushPull || channel.pin >= MAX_MASK_PIN {
+            channel
+                .gpio_state
+                .backend
+                .write(channel.pin, level, channel.drive);
+
+            continue;
+        }
+
+        let mask = 1 << channel.pin;
+        let levels = if level == Level::High { mask } else { 0 };
+
+        // Group the edges for each GPIO backend
+        match writes
+            .iter_mut()
+            .find(|(gpio_state, _, _)| Arc::ptr_eq(gpio_state, &channel.gpio_state))
+        {
+            Some(write) => {
+                write.1 |= mask;
+                write.2 |= levels;
+            }
+            None => writes.push((&channel.gpio_state, mask, levels)),
+        }
+    }
+
+    for (gpio_state, mask, levels) in writes {
+        gpio_state.backend.set_levels(mask, levels);
+    }
+}
+
+fn run(receiver: Receiver<Msg>, resolution_ns: i64) {
+    set_realtime_priority();
+
+    let mut channels: Vec<(usize, Channel)> = Vec::new();
+
+    loop {
+        let next_edge_ns = channels
+            .iter()
+            .filter_map(|(_, channel)| channel.next_edge_ns)
+            .min();
+
+        let msg = match next_edge_ns {
+            Some(next_edge_ns) => match recv_until_ns(&receiver, next_edge_ns) {
+                Ok(Some(msg)) => msg,
+                Ok(None) => {
+                    write_edges(&mut channels, get_time_ns(), next_edge_ns + resolution_ns);
+                    continue;
+                }
+                Err(_) => return,
+            },
+            // Block until a channel is added
+            None => match receiver.recv() {
+                Ok(msg) => msg,
+                Err(_) => return,
+            },
+        };
+
+        match msg {
+            Msg::Add(id, channel) => channels.push((id, channel)),
+            Msg::Reconfigure(id, period, pulse_width) => {
+                if let Some((_, channel)) = channels.iter_mut().find(|(idx, _)| *idx == id) {
+                    channel.reconfigure(period, pulse_width, get_time_ns());
+                }
+            }
+            Msg::Remove(id, ack) => {
+                if let Some(idx) = channels.iter().position(|(idx, _)| *idx == id) {
+                    let (_, channel) = channels.remove(idx);
+                    channel.gpio_state.backend.write(
+                        channel.pin,
+                        channel.level(false),
+                        channel.drive,
+                    );
+                }
+
+                let _ = ack.send(());
+            }
+            Msg::Stop => return,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Scheduler {
+    config: SchedulerConfig,
+    scheduler_thread: Option<thread::JoinHandle<()>>,
+    sender: Mutex<Sender<Msg>>,
+    next_id: AtomicUsize,
+    channel_count: Mutex<usize>,
+}
+
+// Share a single scheduler between all pins using PwmBackend::Scheduler
+lazy_static! {
+    static ref SCHEDULER: Mutex<Weak<Scheduler>> = Mutex::new(Weak::new());
+}
+
+impl Scheduler {
+    // Returns the running scheduler, or starts a new one
+    fn shared(config: SchedulerConfig) -> Result<Arc<Scheduler>> {
+        let mut weak_scheduler = SCHEDULER.lock().unwrap();
+
+        if let Some(scheduler) = weak_scheduler.upgrade() {
+            if scheduler.config != config {
+                return Err(invalid_input(
+                    "PWM scheduler is already active with a different configuration",
+                ));
+            }
+
+            return Ok(scheduler);
+        }
+
+        if config.max_channels == 0 {
+            return Err(invalid_input("Unsupported maximum channel count"));
+        }
+
+        let resolution_ns = config.resolution.as_nanos() as i64;
+        let (sender, receiver): (Sender<Msg>, Receiver<Msg>) = mpsc::channel();
+        let scheduler_thread = thread::spawn(move || run(receiver, resolution_ns));
+
+        let scheduler = Arc::new(Scheduler {
+            config,
+            scheduler_thread: Some(scheduler_thread),
+            sender: Mutex::new(sender),
+            next_id: AtomicUsize::new(0),
+            channel_count: Mutex::new(0),
+        });
+
+        *weak_scheduler = Arc::downgrade(&scheduler);
+
+        Ok(scheduler)
+    }
+
+    fn send(&self, msg: Msg) {
+        let _ = self.sender.lock().unwrap().send(msg);
+    }
+
+    fn add(&self, channel: Channel) -> Result<usize> {
+        let mut channel_count = self.channel_count.lock().unwrap();
+        if *channel_count >= self.config.max_channels {
+            return Err(Error::Io(io::Error::from_raw_os_error(libc::EBUSY)));
+        }
+
+        *channel_count += 1;
+
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        self.send(Msg::Add(id, channel));
+
+        Ok(id)
+    }
+
+    fn remove(&self, id: usize) {
+        let (ack_sender, ack_receiver) = mpsc::channel();
+        self.send(Msg::Remove(id, ack_sender));
+
+        // Wait until the scheduler thread has set the pin's final output state
+        let _ = ack_receiver.recv();
+
+        *self.channel_count.lock().unwrap() -= 1;
+    }
+}
+
+impl Drop for Scheduler {
+    fn drop(&mut self) {
+        self.send(Msg::Stop);
+
+        if let Some(scheduler_thread) = self.scheduler_thread.take() {
+            let _ = scheduler_thread.join();
+        }
+    }
+}
+
+// Drives a single pin through the shared scheduler
+#[derive(Debug)]
+pub(crate) struct ScheduledPwm {
+    scheduler: Arc<Scheduler>,
+    id: usize,
+    active: bool,
+}
+
+impl ScheduledPwm {
+    pub(crate) fn new(
+        config: SchedulerConfig,
+        pin: u8,
+        drive: Drive,
+        active_low: bool,
+        gpio_state: Arc<GpioState>,
+        period: Duration,
+        pulse_width: Duration,
+    ) -> Result<ScheduledPwm> {
+        let scheduler = Scheduler::shared(config)?;
+        let id = scheduler.add(Channel::new(
+            pin,
+            drive,
+            active_low,
+            gpio_state,
+            period,
+            pulse_width,
+        ))?;
+
+        Ok(ScheduledPwm {
+            scheduler,
+            id,
+            active: true,
+        })
+    }
+
+    pub(crate) fn config(&self) -> SchedulerConfig {
+        self.scheduler.config
+    }
+
+    pub(crate) fn reconfigure(&mut self, period: Duration, pulse_width: Duration) {
+        self.scheduler
+            .send(Msg::Reconfigure(self.id, period, pulse_width));
+    }
+
+    pub(crate) fn stop(&mut self) -> Result<()> {
+        if self.active {
+            self.scheduler.remove(self.id);
+            self.active = false;
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for ScheduledPwm {
+    fn drop(&mut self) {
+        // Don't wait for the scheduler thread to respond if the main thread is panicking
+        if !thread::panicking() {
+            let _ = self.stop();
+        }
+    }
+}
@@ -0,0 +1,466 @@
+// Copyright (c) 2017-2019 Rene van der Meer
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL
+// THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use std::fs::File;
+use std::io;
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd};
+use std::os::unix::net::UnixDatagram;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use crate::gpio::backend::GpioBackend;
+use crate::gpio::soft_pwm::get_time_ns;
+use crate::gpio::{
+    ioctl, pin, Bank, Detect, Drive, Error, Event, GpioState, Level, LineInfo, Mode, PadConfig,
+    PullUpDown, Result, Trigger,
+};
+
+#[derive(Debug)]
+struct SimPin {
+    pin: u8,
+    // Line name reported through line_info
+    name: String,
+    mode: Mode,
+    level: Level,
+    // Output latch, which is set through GPSET/GPCLR regardless of the pin's mode
+    output: Level,
+    // Level that's externally applied to the pin through Simulator::set_level
+    driven: Option<Level>,
+    pud: PullUpDown,
+    // Write end of the socket that's read by the corresponding Interrupt
+    event_sink: Option<(Trigger, UnixDatagram)>,
+    // Sequence number of the last event sent through event_sink
+    seqno: u32,
+    // Simulated event detect enable registers and GPEDSn bit
+    edge_detect: Detect,
+    edge_detected: bool,
+}
+
+impl SimPin {
+    fn new(pin: u8) -> SimPin {
+        SimPin {
+            pin,
+            name: format!("GPIO{}", pin),
+            mode: Mode::Input,
+            level: Level::Low,
+            output: Level::Low,
+            driven: None,
+            pud: PullUpDown::Off,
+            event_sink: None,
+            seqno: 0,
+            edge_detect: Detect::Disabled,
+            edge_detected: false,
+        }
+    }
+
+    // The simulation doesn't distinguish between synchronous and asynchronous edge
+    // detection, since it has no sampling clock.
+    fn detects_edge(&self, edge: Trigger) -> bool {
+        let (rising, falling) = match self.edge_detect {
+            Detect::RisingEdge | Detect::AsyncRisingEdge => (true, false),
+            Detect::FallingEdge | Detect::AsyncFallingEdge => (false, true),
+            Detect::BothEdges | Detect::AsyncBothEdges => (true, true),
+            _ => (false, false),
+        };
+
+        (edge == Trigger::RisingEdge && rising) || (edge == Trigger::FallingEdge && falling)
+    }
+
+    fn detects_level(&self) -> bool {
+        (self.edge_detect == Detect::High && self.level == Level::High)
+            || (self.edge_detect == Detect::Low && self.level == Level::Low)
+    }
+
+    // Recalculate the pin's logic level, and generate an interrupt event if
+    // the level change matches the configured trigger.
+    fn update(&mut self) {
+        self.update_at(None);
+    }
+
+    // Same as update, but uses the specified event timestamp rather than the
+    // current time.
+    fn update_at(&mut self, timestamp: Option<Duration>) {
+        let level = if self.mode == Mode::Output {
+            self.output
+        } else if let Some(driven) = self.driven {
+            driven
+        } else {
+            match self.pud {
+                PullUpDown::PullUp => Level::High,
+                PullUpDown::PullDown => Level::Low,
+                PullUpDown::Off => self.level,
+            }
+        };
+
+        if level == self.level {
+            return;
+        }
+
+        self.level = level;
+
+        let edge = match level {
+            Level::High => Trigger::RisingEdge,
+            Level::Low => Trigger::FallingEdge,
+        };
+
+        if self.detects_edge(edge) || self.detects_level() {
+            self.edge_detected = true;
+        }
+
+        let closed = if let Some((trigger, ref sink)) = self.event_sink {
+            if trigger == edge || trigger == Trigger::Both {
+                self.seqno = self.seqno.wrapping_add(1);
+
+                let event = Event {
+                    pin: self.pin,
+                    trigger: edge,
+                    timestamp: timestamp
+                        .unwrap_or_else(|| Duration::from_nanos(get_time_ns() as u64)),
+                    seqno: self.seqno,
+                };
+
+                // Events are silently dropped if the socket buffer is full, similar
+                // to the kernel's event FIFO. The sequence number is still incremented,
+                // so the Interrupt can detect the gap. A refused connection or a broken
+                // pipe means the Interrupt has been dropped.
+                match ioctl::put_event(sink.as_raw_fd(), event) {
+                    Err(Error::Io(ref e)) => {
+                        e.kind() == io::ErrorKind::BrokenPipe
+                            || e.kind() == io::ErrorKind::ConnectionRefused
+                    }
+                    _ => false,
+                }
+            } else {
+                false
+            }
+        } else {
+            false
+        };
+
+        if closed {
+            self.event_sink = None;
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct SimBackend {
+    pins: Mutex<Vec<SimPin>>,
+    pads: Mutex<[PadConfig; 3]>,
+    // Each Simulator shares a single GpioState between its Gpio instances,
+    // similar to GPIO_STATE for the native backend.
+    pub(crate) gpio_state: Mutex<Weak<GpioState>>,
+}
+
+impl GpioBackend for SimBackend {
+    fn mode(&self, pin: u8) -> Mode {
+        self.pins.lock().unwrap()[pin as usize].mode
+    }
+
+    fn set_mode(&self, pin: u8, mode: Mode) {
+        let sim_pin = &mut self.pins.lock().unwrap()[pin as usize];
+
+        sim_pin.mode = mode;
+        sim_pin.update();
+    }
+
+    fn level(&self, pin: u8) -> Level {
+        self.pins.lock().unwrap()[pin as usize].level
+    }
+
+    fn set_high(&self, pin: u8) {
+        let sim_pin = &mut self.pins.lock().unwrap()[pin as usize];
+
+        sim_pin.output = Level::High;
+        sim_pin.update();
+    }
+
+    fn set_low(&self, pin: u8) {
+        let sim_pin = &mut self.pins.lock().unwrap()[pin as usize];
+
+        sim_pin.output = Level::Low;
+        sim_pin.update();
+    }
+
+    fn levels(&self, mask: u64) -> u64 {
+        self.pins
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|sim_pin| mask & (1 << sim_pin.pin) > 0 && sim_pin.level == Level::High)
+            .fold(0, |levels, sim_pin| levels | (1 << sim_pin.pin))
+    }
+
+    fn set_levels(&self, mask: u64, levels: u64) {
+        let mut pins = self.pins.lock().unwrap();
+
+        for sim_pin in pins
+            .iter_mut()
+            .filter(|sim_pin| mask & (1 << sim_pin.pin) > 0)
+        {
+            sim_pin.output = if levels & (1 << sim_pin.pin) > 0 {
+                Level::High
+            } else {
+                Level::Low
+            };
+            sim_pin.update();
+        }
+    }
+
+    fn set_pullupdown(&self, pin: u8, pud: PullUpDown) {
+        let sim_pin = &mut self.pins.lock().unwrap()[pin as usize];
+
+        sim_pin.pud = pud;
+        sim_pin.update();
+    }
+
+    fn pullupdown(&self, pin: u8) -> Option<PullUpDown> {
+        Some(self.pins.lock().unwrap()[pin as usize].pud)
+    }
+
+    fn event_request(
+        &self,
+        pin: u8,
+        trigger: Trigger,
+        _debounce: Option<Duration>,
+    ) -> Result<ioctl::EventSource> {
+        // Datagram sockets preserve message boundaries, so each event is either
+        // received in its entirety, or not at all.
+        let (source, sink) = UnixDatagram::pair()?;
+
+        // Only a single event request can be active for each pin. Any previous
+        // request is replaced, which closes its socket.
+        let sim_pin = &mut self.pins.lock().unwrap()[pin as usize];
+        sim_pin.event_sink = Some((trigger, sink));
+        sim_pin.seqno = 0;
+
+        Ok(ioctl::EventSource::from_file(
+            unsafe { File::from_raw_fd(source.into_raw_fd()) },
+            ioctl::Abi::V2,
+            pin,
+        ))
+    }
+
+    fn set_edge_detect(&self, pin: u8, detect: Detect) -> Result<()> {
+        let sim_pin = &mut self.pins.lock().unwrap()[pin as usize];
+
+        sim_pin.edge_detect = detect;
+        sim_pin.edge_detected = sim_pin.detects_level();
+
+        Ok(())
+    }
+
+    fn take_edge_detected(&self, pin: u8) -> bool {
+        let sim_pin = &mut self.pins.lock().unwrap()[pin as usize];
+
+        // Level detection sets the event again immediately after it's cleared
+        let edge_detected = sim_pin.edge_detected;
+        sim_pin.edge_detected = sim_pin.detects_level();
+
+        edge_detected
+    }
+
+    // Simulated lines are never in use by other consumers
+    fn line_info(&self, pin: u8) -> Result<LineInfo> {
+        let sim_pin = &self.pins.lock().unwrap()[pin as usize];
+
+        Ok(LineInfo {
+            pin,
+            name: sim_pin.name.clone(),
+            consumer: String::new(),
+            direction: if sim_pin.mode == Mode::Output {
+                Mode::Output
+            } else {
+                Mode::Input
+            },
+            used: false,
+            active_low: false,
+            drive: Drive::PushPull,
+            bias: Some(sim_pin.pud),
+        })
+    }
+
+    fn pad_config(&self, bank: Bank) -> Result<PadConfig> {
+        Ok(self.pads.lock().unwrap()[bank as usize])
+    }
+
+    fn set_pad_config(&self, bank: Bank, config: PadConfig) -> Result<()> {
+        self.pads.lock().unwrap()[bank as usize] = config;
+
+        Ok(())
+    }
+}
+
+/// In-memory simulation of the GPIO peripheral.
+///
+/// A `Simulator` replaces the GPIO registers and the `gpiochip` character device with
+/// a simulated register file, which allows code that uses [`Pin`], [`InputPin`],
+/// [`OutputPin`] or [`IoPin`] to run on systems without a Raspberry Pi, such as a CI server.
+/// Use [`Gpio::with_backend`] to construct a [`Gpio`] instance that's connected to
+/// the simulator.
+///
+/// External signals are simulated by calling [`set_level`], which changes the
+/// logic level read by any pin that isn't configured as an output. Level changes
+/// generate interrupt trigger events for pins configured through
+/// [`InputPin::set_interrupt`] or [`InputPin::set_async_interrupt`].
+///
+/// Cloning a `Simulator` returns a handle to the same simulated peripheral.
+///
+/// [`Pin`]: struct.Pin.html
+/// [`InputPin`]: struct.InputPin.html
+/// [`OutputPin`]: struct.OutputPin.html
+/// [`IoPin`]: struct.IoPin.html
+/// [`Gpio`]: struct.Gpio.html
+/// [`Gpio::with_backend`]: struct.Gpio.html#method.with_backend
+/// [`set_level`]: #method.set_level
+/// [`InputPin::set_interrupt`]: struct.InputPin.html#method.set_interrupt
+/// [`InputPin::set_async_interrupt`]: struct.InputPin.html#method.set_async_interrupt
+#[derive(Debug, Clone)]
+pub struct Simulator {
+    pub(crate) inner: Arc<SimBackend>,
+}
+
+impl Simulator {
+    /// Constructs a new `Simulator`.
+    ///
+    /// All pins start out configured as [`Input`], with their logic level set
+    /// to [`Low`] and the built-in pull-up/pull-down resistors disabled.
+    ///
+    /// [`Input`]: enum.Mode.html#variant.Input
+    /// [`Low`]: enum.Level.html#variant.Low
+    pub fn new() -> Simulator {
+        let mut pins = Vec::with_capacity(pin::MAX);
+        for pin in 0..pin::MAX {
+            pins.push(SimPin::new(pin as u8));
+        }
+
+        Simulator {
+            inner: Arc::new(SimBackend {
+                pins: Mutex::new(pins),
+                pads: Mutex::new([PadConfig::default(); 3]),
+                gpio_state: Mutex::new(Weak::new()),
+            }),
+        }
+    }
+
+    /// Returns the simulated pin's mode.
+    pub fn mode(&self, pin: u8) -> Result<Mode> {
+        Ok(self.inner.pins.lock().unwrap()[Self::index(pin)?].mode)
+    }
+
+    /// Returns the simulated pin's logic level.
+    ///
+    /// For pins configured as [`Output`], this is the pin's output state.
+    ///
+    /// [`Output`]: enum.Mode.html#variant.Output
+    pub fn level(&self, pin: u8) -> Result<Level> {
+        Ok(self.inner.pins.lock().unwrap()[Self::index(pin)?].level)
+    }
+
+    /// Returns the simulated pin's pull-up/pull-down resistor state.
+    pub fn pullupdown(&self, pin: u8) -> Result<PullUpDown> {
+        Ok(self.inner.pins.lock().unwrap()[Self::index(pin)?].pud)
+    }
+
+    /// Applies an external signal to the simulated pin.
+    ///
+    /// The pin reads `level` until [`release`] is called, unless it's configured as
+    /// an [`Output`]. If the logic level changes, a matching interrupt trigger event
+    /// is generated.
+    ///
+    /// [`release`]: #method.release
+    /// [`Output`]: enum.Mode.html#variant.Output
+    pub fn set_level(&self, pin: u8, level: Level) -> Result<()> {
+        let sim_pin = &mut self.inner.pins.lock().unwrap()[Self::index(pin)?];
+
+        sim_pin.driven = Some(level);
+        sim_pin.update();
+
+        Ok(())
+    }
+
+    /// Applies an external signal to the simulated pin, and uses `timestamp` for
+    /// any interrupt trigger event that's generated.
+    ///
+    /// `set_level_at` behaves the same as [`set_level`], but allows the timing of a
+    /// simulated signal to be controlled precisely, regardless of any scheduling delays.
+    /// The timestamp is reported through [`Event::timestamp`], and is used to calculate
+    /// the results of [`InputPin::measure_pulse`] and [`FrequencyCounter`].
+    ///
+    /// [`set_level`]: #method.set_level
+    /// [`Event::timestamp`]: struct.Event.html#structfield.timestamp
+    /// [`InputPin::measure_pulse`]: struct.InputPin.html#method.measure_pulse
+    /// [`FrequencyCounter`]: struct.FrequencyCounter.html
+    pub fn set_level_at(&self, pin: u8, level: Level, timestamp: Duration) -> Result<()> {
+        let sim_pin = &mut self.inner.pins.lock().unwrap()[Self::index(pin)?];
+
+        sim_pin.driven = Some(level);
+        sim_pin.update_at(Some(timestamp));
+
+        Ok(())
+    }
+
+    /// Removes the external signal previously applied with [`set_level`].
+    ///
+    /// If the built-in pull-up or pull-down resistor is enabled, the pin's
+    /// logic level changes accordingly. Otherwise, the pin keeps its last level.
+    ///
+    /// [`set_level`]: #method.set_level
+    pub fn release(&self, pin: u8) -> Result<()> {
+        let sim_pin = &mut self.inner.pins.lock().unwrap()[Self::index(pin)?];
+
+        sim_pin.driven = None;
+        sim_pin.update();
+
+        Ok(())
+    }
+
+    /// Sets the simulated pin's line name, which is reported through [`Gpio::line_info`]
+    /// and used by [`Gpio::get_by_name`].
+    ///
+    /// By default, pins are named `GPIOn`, where `n` is the BCM GPIO pin number.
+    ///
+    /// [`Gpio::line_info`]: struct.Gpio.html#method.line_info
+    /// [`Gpio::get_by_name`]: struct.Gpio.html#method.get_by_name
+    pub fn set_line_name(&self, pin: u8, name: &str) -> Result<()> {
+        self.inner.pins.lock().unwrap()[Self::index(pin)?].name = name.to_owned();
+
+        Ok(())
+    }
+
+    /// Returns the simulated pad configuration for the specified bank.
+    pub fn pad_config(&self, bank: Bank) -> PadConfig {
+        self.inner.pads.lock().unwrap()[bank as usize]
+    }
+
+    fn index(pin: u8) -> Result<usize> {
+        if (pin as usize) < pin::MAX {
+            Ok(pin as usize)
+        } else {
+            Err(Error::PinNotAvailable(pin))
+        }
+    }
+}
+
+impl Default for Simulator {
+    fn default() -> Simulator {
+        Simulator::new()
+    }
+}
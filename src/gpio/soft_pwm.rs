@@ -22,8 +22,10 @@
 #![allow(clippy::cast_lossless)]
 #![allow(dead_code)]
 
+use std::io;
 use std::ptr;
-use std::sync::mpsc::{self, Receiver, Sender};
+use std::result;
+use std::sync::mpsc::{self, Receiver, RecvError, RecvTimeoutError, Sender, TryRecvError};
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
@@ -32,14 +34,16 @@ use libc::{
     self, c_long, sched_param, time_t, timespec, CLOCK_MONOTONIC, PR_SET_TIMERSLACK, SCHED_RR,
 };
 
-use super::{Error, GpioState, Result};
+use super::dma::{DmaConfig, DmaPwm};
+use super::scheduler::{ScheduledPwm, SchedulerConfig};
+use super::{Drive, Error, GpioState, Level, Result};
 
 // Only call sleep_ns() if we have enough time remaining
-const SLEEP_THRESHOLD: i64 = 250_000;
+pub(crate) const SLEEP_THRESHOLD: i64 = 250_000;
 // Reserve some time for busy waiting
-const BUSYWAIT_MAX: i64 = 200_000;
+pub(crate) const BUSYWAIT_MAX: i64 = 200_000;
 // Subtract from the remaining busy wait time to account for get_time_ns() overhead
-const BUSYWAIT_REMAINDER: i64 = 100;
+pub(crate) const BUSYWAIT_REMAINDER: i64 = 100;
 
 const NANOS_PER_SEC: i64 = 1_000_000_000;
 
@@ -49,63 +53,129 @@ enum Msg {
     Stop,
 }
 
+/// Software-based PWM implementations.
+///
+/// More information on the differences between the available backends can be
+/// found [here].
+///
+/// [here]: index.html#software-based-pwm
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum PwmBackend {
+    /// Emulates the PWM signal using a dedicated busy-waiting thread for each pin.
+    Thread,
+    /// Emulates the PWM signals for all pins using this backend on a single shared thread.
+    Scheduler(SchedulerConfig),
+    /// Generates the PWM signal through a DMA channel, without any CPU involvement.
+    Dma(DmaConfig),
+}
+
 #[derive(Debug)]
-pub(crate) struct SoftPwm {
-    pwm_thread: Option<thread::JoinHandle<Result<()>>>,
-    sender: Sender<Msg>,
+pub(crate) enum SoftPwm {
+    Thread(PwmThread),
+    Scheduled(ScheduledPwm),
+    Dma(DmaPwm),
 }
 
 impl SoftPwm {
     pub(crate) fn new(
+        backend: PwmBackend,
         pin: u8,
+        drive: Drive,
+        active_low: bool,
         gpio_state: Arc<GpioState>,
         period: Duration,
         pulse_width: Duration,
-    ) -> SoftPwm {
-        let (sender, receiver): (Sender<Msg>, Receiver<Msg>) = mpsc::channel();
+    ) -> Result<SoftPwm> {
+        match backend {
+            PwmBackend::Thread => Ok(SoftPwm::Thread(PwmThread::new(
+                pin,
+                drive,
+                active_low,
+                gpio_state,
+                period,
+                pulse_width,
+            ))),
+            PwmBackend::Scheduler(config) => Ok(SoftPwm::Scheduled(ScheduledPwm::new(
+                config,
+                pin,
+                drive,
+                active_low,
+                gpio_state,
+                period,
+                pulse_width,
+            )?)),
+            PwmBackend::Dma(config) => Ok(SoftPwm::Dma(DmaPwm::new(
+                config,
+                pin,
+                drive,
+                active_low,
+                gpio_state,
+                period,
+                pulse_width,
+            )?)),
+        }
+    }
 
-        let pwm_thread = thread::spawn(move || -> Result<()> {
-            // Set the scheduling policy to real-time round robin at the highest priority. This
-            // will silently fail if we're not running as root.
-            #[cfg(target_env = "gnu")]
-            let params = sched_param {
-                sched_priority: unsafe { libc::sched_get_priority_max(SCHED_RR) },
-            };
+    pub(crate) fn backend(&self) -> PwmBackend {
+        match self {
+            SoftPwm::Thread(_) => PwmBackend::Thread,
+            SoftPwm::Scheduled(scheduled_pwm) => PwmBackend::Scheduler(scheduled_pwm.config()),
+            SoftPwm::Dma(dma_pwm) => PwmBackend::Dma(dma_pwm.config()),
+        }
+    }
 
-            #[cfg(target_env = "musl")]
-            let params = sched_param {
-                sched_priority: unsafe { libc::sched_get_priority_max(SCHED_RR) },
-                sched_ss_low_priority: 0,
-                sched_ss_repl_period: timespec {
-                    tv_sec: 0,
-                    tv_nsec: 0,
-                },
-                sched_ss_init_budget: timespec {
-                    tv_sec: 0,
-                    tv_nsec: 0,
-                },
-                sched_ss_max_repl: 0,
-            };
+    pub(crate) fn reconfigure(&mut self, period: Duration, pulse_width: Duration) {
+        match self {
+            SoftPwm::Thread(pwm_thread) => pwm_thread.reconfigure(period, pulse_width),
+            SoftPwm::Scheduled(scheduled_pwm) => scheduled_pwm.reconfigure(period, pulse_width),
+            SoftPwm::Dma(dma_pwm) => dma_pwm.reconfigure(period, pulse_width),
+        }
+    }
 
-            unsafe {
-                libc::sched_setscheduler(0, SCHED_RR, &params);
-            }
+    pub(crate) fn stop(&mut self) -> Result<()> {
+        match self {
+            SoftPwm::Thread(pwm_thread) => pwm_thread.stop(),
+            SoftPwm::Scheduled(scheduled_pwm) => scheduled_pwm.stop(),
+            SoftPwm::Dma(dma_pwm) => dma_pwm.stop(),
+        }
+    }
+}
 
-            // Set timer slack to 1 ns (default = 50 µs). This is only relevant if we're unable
-            // to set a real-time scheduling policy.
-            unsafe {
-                libc::prctl(PR_SET_TIMERSLACK, 1);
-            }
+#[derive(Debug)]
+pub(crate) struct PwmThread {
+    pwm_thread: Option<thread::JoinHandle<Result<()>>>,
+    sender: Sender<Msg>,
+}
+
+impl PwmThread {
+    pub(crate) fn new(
+        pin: u8,
+        drive: Drive,
+        active_low: bool,
+        gpio_state: Arc<GpioState>,
+        period: Duration,
+        pulse_width: Duration,
+    ) -> PwmThread {
+        let (sender, receiver): (Sender<Msg>, Receiver<Msg>) = mpsc::channel();
+
+        let pwm_thread = thread::spawn(move || -> Result<()> {
+            set_realtime_priority();
 
             let mut period_ns = period.as_nanos() as i64;
             let mut pulse_width_ns = pulse_width.as_nanos() as i64;
 
+            let (active, inactive) = if active_low {
+                (Level::Low, Level::High)
+            } else {
+                (Level::High, Level::Low)
+            };
+
             let mut start_ns = get_time_ns();
 
             loop {
                 // PWM active
                 if pulse_width_ns > 0 {
-                    gpio_state.gpio_mem.set_high(pin);
+                    gpio_state.backend.write(pin, active, drive);
                 }
 
                 // Sleep if we have enough time remaining, while reserving some time
@@ -123,7 +193,7 @@ impl SoftPwm {
                 }
 
                 // PWM inactive
-                gpio_state.gpio_mem.set_low(pin);
+                gpio_state.backend.write(pin, inactive, drive);
 
                 while let Ok(msg) = receiver.try_recv() {
                     match msg {
@@ -163,7 +233,7 @@ impl SoftPwm {
             }
         });
 
-        SoftPwm {
+        PwmThread {
             pwm_thread: Some(pwm_thread),
             sender,
         }
@@ -186,7 +256,7 @@ impl SoftPwm {
     }
 }
 
-impl Drop for SoftPwm {
+impl Drop for PwmThread {
     fn drop(&mut self) {
         // Don't wait for the pwm thread to exit if the main thread is panicking,
         // because we could potentially block indefinitely while unwinding if the
@@ -197,12 +267,79 @@ impl Drop for SoftPwm {
     }
 }
 
-// Required because Sender isn't Sync. Implementing Sync for SoftPwm is
+// Required because Sender isn't Sync. Implementing Sync for PwmThread is
 // safe because all usage of Sender::send() is locked behind &mut self.
-unsafe impl Sync for SoftPwm {}
+unsafe impl Sync for PwmThread {}
+
+// Set the scheduling policy for the current thread to real-time round robin at the
+// highest priority. This will silently fail if we're not running as root.
+pub(crate) fn set_realtime_priority() {
+    #[cfg(target_env = "gnu")]
+    let params = sched_param {
+        sched_priority: unsafe { libc::sched_get_priority_max(SCHED_RR) },
+    };
+
+    #[cfg(target_env = "musl")]
+    let params = sched_param {
+        sched_priority: unsafe { libc::sched_get_priority_max(SCHED_RR) },
+        sched_ss_low_priority: 0,
+        sched_ss_repl_period: timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        },
+        sched_ss_init_budget: timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        },
+        sched_ss_max_repl: 0,
+    };
+
+    unsafe {
+        libc::sched_setscheduler(0, SCHED_RR, &params);
+    }
+
+    // Set timer slack to 1 ns (default = 50 µs). This is only relevant if we're unable
+    // to set a real-time scheduling policy.
+    unsafe {
+        libc::prctl(PR_SET_TIMERSLACK, 1);
+    }
+}
+
+// Waits for a message until target_ns, while reserving some time for busy waiting to
+// compensate for waking up later than needed. Returns None once target_ns is reached.
+pub(crate) fn recv_until_ns<T>(
+    receiver: &Receiver<T>,
+    target_ns: i64,
+) -> result::Result<Option<T>, RecvError> {
+    let remaining_ns = target_ns - get_time_ns();
+
+    if remaining_ns >= SLEEP_THRESHOLD {
+        match receiver.recv_timeout(Duration::from_nanos((remaining_ns - BUSYWAIT_MAX) as u64)) {
+            Ok(msg) => return Ok(Some(msg)),
+            Err(RecvTimeoutError::Disconnected) => return Err(RecvError),
+            Err(RecvTimeoutError::Timeout) => (),
+        }
+    } else {
+        match receiver.try_recv() {
+            Ok(msg) => return Ok(Some(msg)),
+            Err(TryRecvError::Disconnected) => return Err(RecvError),
+            Err(TryRecvError::Empty) => (),
+        }
+    }
+
+    // Busy-wait for the remaining time, minus BUSYWAIT_REMAINDER to account
+    // for get_time_ns() overhead
+    while target_ns - get_time_ns() > BUSYWAIT_REMAINDER {}
+
+    Ok(None)
+}
+
+pub(crate) fn invalid_input(msg: &str) -> Error {
+    Error::Io(io::Error::new(io::ErrorKind::InvalidInput, msg))
+}
 
 #[inline(always)]
-fn get_time_ns() -> i64 {
+pub(crate) fn get_time_ns() -> i64 {
     let mut ts = timespec {
         tv_sec: 0,
         tv_nsec: 0,
@@ -216,7 +353,7 @@ fn get_time_ns() -> i64 {
 }
 
 #[inline(always)]
-fn sleep_ns(ns: i64) {
+pub(crate) fn sleep_ns(ns: i64) {
     let ts = timespec {
         tv_sec: (ns / NANOS_PER_SEC) as time_t,
         tv_nsec: (ns % NANOS_PER_SEC) as c_long,
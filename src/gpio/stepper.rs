@@ -0,0 +1,623 @@
+// Copyright (c) 2017-2019 Rene van der Meer
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL
+// THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+//! Stepper motor control through STEP/DIR drivers.
+//!
+//! [`Stepper`] drives stepper motor drivers such as the A4988, DRV8825 and TMC2208,
+//! which advance the motor by a single (micro)step on every pulse on their STEP input,
+//! in the direction selected by their DIR input. An optional ENABLE input energizes
+//! the motor coils.
+//!
+//! Each move accelerates the motor to [`StepperConfig::max_speed`], cruises, and then
+//! decelerates to a stop at the target position, following the configured motion
+//! [`Profile`]. The step pulses are generated on a separate thread, using the same
+//! timing approach as the software-based PWM implementation, so the calling thread
+//! can continue while the motor is moving. The current position is updated after
+//! every step.
+//!
+//! Software-based step generation is subject to the same timing jitter as
+//! software-based PWM. Running your application as root allows the step thread to
+//! use a real-time scheduling policy, which reduces jitter.
+//!
+//! Most drivers expect an active-low ENABLE input. Call
+//! [`OutputPin::set_active_low`] on the enable pin before passing it to
+//! [`Stepper::new`] in that case. Similarly, the direction of rotation can be reversed
+//! by configuring the direction pin as active-low.
+//!
+//! ## Examples
+//!
+//! ```
+//! use rppal::gpio::stepper::{Profile, Stepper, StepperConfig};
+//! use rppal::gpio::{Backend, Gpio, Simulator};
+//!
+//! # fn main() -> rppal::gpio::Result<()> {
+//! let gpio = Gpio::with_backend(Backend::Simulated(Simulator::new()))?;
+//!
+//! let step = gpio.get(23)?.into_output();
+//! let dir = gpio.get(24)?.into_output();
+//!
+//! let mut stepper = Stepper::new(
+//!     step,
+//!     dir,
+//!     None,
+//!     StepperConfig {
+//!         max_speed: 2000.0,
+//!         acceleration: 8000.0,
+//!         profile: Profile::SCurve,
+//!         ..StepperConfig::default()
+//!     },
+//! )?;
+//!
+//! stepper.move_to(200)?;
+//! stepper.wait()?;
+//! assert_eq!(stepper.position(), 200);
+//!
+//! stepper.move_by(-50)?;
+//! stepper.wait()?;
+//! assert_eq!(stepper.position(), 150);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Stepper`]: struct.Stepper.html
+//! [`Stepper::new`]: struct.Stepper.html#method.new
+//! [`StepperConfig::max_speed`]: struct.StepperConfig.html#structfield.max_speed
+//! [`Profile`]: enum.Profile.html
+//! [`OutputPin::set_active_low`]: ../struct.OutputPin.html#method.set_active_low
+
+use std::f64::consts::PI;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use super::soft_pwm::{get_time_ns, invalid_input, recv_until_ns, set_realtime_priority};
+use super::{Error, OutputPin, Result};
+
+const NANOS_PER_SEC: f64 = 1_000_000_000.0;
+
+// Number of iterations used to solve the S-curve position for the step time, which
+// results in a precision well below a nanosecond for any practical ramp duration
+const SOLVER_ITERATIONS: usize = 48;
+
+/// Motion profiles used to accelerate and decelerate the motor.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Profile {
+    /// Constant acceleration, resulting in a trapezoidal velocity curve.
+    Trapezoidal,
+    /// Sinusoidal acceleration, resulting in an S-shaped velocity curve. The
+    /// acceleration gradually increases and decreases, which reduces vibrations
+    /// and the risk of missed steps, at the cost of slightly longer ramps.
+    SCurve,
+}
+
+/// Motion parameters for a [`Stepper`].
+///
+/// [`Stepper`]: struct.Stepper.html
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct StepperConfig {
+    /// Maximum speed in steps per second.
+    pub max_speed: f64,
+    /// Acceleration and deceleration in steps per second squared. For
+    /// [`Profile::SCurve`], this is the peak acceleration.
+    ///
+    /// [`Profile::SCurve`]: enum.Profile.html#variant.SCurve
+    pub acceleration: f64,
+    /// Motion profile used to accelerate and decelerate.
+    pub profile: Profile,
+    /// Duration of each pulse on the step pin.
+    pub pulse_width: Duration,
+    /// Delay between changing the direction pin's logic level and the first step pulse.
+    pub dir_setup: Duration,
+}
+
+impl Default for StepperConfig {
+    fn default() -> StepperConfig {
+        StepperConfig {
+            max_speed: 1000.0,
+            acceleration: 1000.0,
+            profile: Profile::Trapezoidal,
+            pulse_width: Duration::from_micros(5),
+            dir_setup: Duration::from_micros(5),
+        }
+    }
+}
+
+fn positive(value: f64) -> bool {
+    value.is_finite() && value > 0.0
+}
+
+fn validate(config: &StepperConfig) -> Result<()> {
+    if !positive(config.max_speed) {
+        return Err(invalid_input("Unsupported maximum speed"));
+    }
+
+    if !positive(config.acceleration) {
+        return Err(invalid_input("Unsupported acceleration"));
+    }
+
+    Ok(())
+}
+
+// Acceleration from standstill to the peak velocity
+#[derive(Debug, Copy, Clone)]
+struct Ramp {
+    profile: Profile,
+    // Peak velocity in steps/s
+    velocity: f64,
+    // Ramp duration in seconds
+    duration: f64,
+    // Number of steps covered by the ramp
+    distance: f64,
+}
+
+impl Ramp {
+    fn new(config: &StepperConfig, steps: u64) -> Ramp {
+        // Moves that are too short to reach the maximum speed accelerate for the
+        // first half, and decelerate for the second half
+        let steps = steps as f64;
+        let (velocity, duration) = match config.profile {
+            Profile::Trapezoidal => {
+                let velocity = config.max_speed.min((config.acceleration * steps).sqrt());
+
+                (velocity, velocity / config.acceleration)
+            }
+            Profile::SCurve => {
+                let velocity = config
+                    .max_speed
+                    .min((2.0 * config.acceleration * steps / PI).sqrt());
+
+                (velocity, PI * velocity / (2.0 * config.acceleration))
+            }
+        };
+
+        Ramp {
+            profile: config.profile,
+            velocity,
+            duration,
+            distance: velocity * duration / 2.0,
+        }
+    }
+
+    // Time in seconds at which the ramp reaches the specified position
+    fn time_at(&self, position: f64) -> f64 {
+        if position <= 0.0 {
+            return 0.0;
+        } else if position >= self.distance {
+            return self.duration;
+        }
+
+        match self.profile {
+            Profile::Trapezoidal => (2.0 * position * self.duration / self.velocity).sqrt(),
+            Profile::SCurve => {
+                // position(t) = v / 2 * (t - T / π * sin(π * t / T)) can't be solved for t
+                // directly, so we use bisection, which converges reliably because
+                // position(t) is monotonic
+                let mut low = 0.0;
+                let mut high = self.duration;
+                for _ in 0..SOLVER_ITERATIONS {
+                    let t = (low + high) / 2.0;
+                    let p = self.velocity / 2.0
+                        * (t - self.duration / PI * (PI * t / self.duration).sin());
+
+                    if p < position {
+                        low = t;
+                    } else {
+                        high = t;
+                    }
+                }
+
+                (low + high) / 2.0
+            }
+        }
+    }
+}
+
+// Step timing for a single move, consisting of an acceleration ramp, a cruise
+// phase at constant velocity, and a deceleration ramp that mirrors the acceleration
+#[derive(Debug, Copy, Clone)]
+struct Plan {
+    ramp: Ramp,
+    steps: u64,
+    // Number of steps covered by the acceleration and deceleration ramps
+    accel: f64,
+    decel: f64,
+}
+
+impl Plan {
+    fn new(config: &StepperConfig, steps: u64) -> Plan {
+        let ramp = Ramp::new(config, steps);
+
+        Plan {
+            ramp,
+            steps,
+            accel: ramp.distance,
+            decel: ramp.distance,
+        }
+    }
+
+    // Time in seconds at which the move reaches the specified position
+    fn time_at(&self, position: f64) -> f64 {
+        let cruise_end = self.steps as f64 - self.decel;
+
+        if position <= self.accel {
+            self.ramp.time_at(position)
+        } else if position <= cruise_end {
+            self.ramp.time_at(self.accel) + (position - self.accel) / self.ramp.velocity
+        } else {
+            self.time_at(cruise_end) + self.ramp.time_at(self.decel)
+                - self.ramp.time_at(self.steps as f64 - position)
+        }
+    }
+
+    // Returns a plan that decelerates to a stop as soon as possible, after the
+    // specified number of steps has been completed. The timing of the completed
+    // steps remains the same.
+    fn stop(&self, completed: u64) -> Plan {
+        let (steps, ramp_distance) = if completed as f64 <= self.accel {
+            // Still accelerating, so decelerate along the same part of the ramp
+            (completed * 2, completed as f64)
+        } else {
+            (completed + self.decel.ceil() as u64, self.decel)
+        };
+
+        // Already decelerating
+        if steps >= self.steps {
+            return *self;
+        }
+
+        Plan {
+            ramp: self.ramp,
+            steps,
+            accel: ramp_distance.min(self.accel),
+            decel: ramp_distance,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Msg {
+    // Decelerate to a stop
+    Stop,
+    // Stop immediately
+    Halt,
+}
+
+#[derive(Debug)]
+struct Pins {
+    step: OutputPin,
+    dir: OutputPin,
+}
+
+#[derive(Debug)]
+struct Shared {
+    position: Mutex<i64>,
+    moving: AtomicBool,
+}
+
+#[derive(Debug)]
+struct Motion {
+    motion_thread: thread::JoinHandle<()>,
+    sender: Sender<Msg>,
+}
+
+fn run(
+    pins: &mut Pins,
+    shared: &Shared,
+    receiver: &Receiver<Msg>,
+    mut plan: Plan,
+    direction: i64,
+    pulse_width_ns: i64,
+    dir_setup_ns: i64,
+) {
+    set_realtime_priority();
+
+    if direction > 0 {
+        pins.dir.set_high();
+    } else {
+        pins.dir.set_low();
+    }
+
+    let start_ns = get_time_ns() + dir_setup_ns;
+    let mut completed = 0;
+
+    while completed < plan.steps {
+        // Each step is scheduled halfway through its interval, relative to the start
+        // of the move, so any delays don't accumulate
+        let step_ns = start_ns + (plan.time_at(completed as f64 + 0.5) * NANOS_PER_SEC) as i64;
+
+        match recv_until_ns(receiver, step_ns) {
+            Ok(None) => (),
+            Ok(Some(Msg::Stop)) => {
+                plan = plan.stop(completed);
+                continue;
+            }
+            Ok(Some(Msg::Halt)) | Err(_) => break,
+        }
+
+        pins.step.set_high();
+        let pulse_end_ns = get_time_ns() + pulse_width_ns;
+
+        *shared.position.lock().unwrap() += direction;
+        completed += 1;
+
+        while get_time_ns() < pulse_end_ns {}
+
+        pins.step.set_low();
+    }
+}
+
+/// Stepper motor driver with a STEP/DIR interface.
+///
+/// More information can be found in the [module documentation].
+///
+/// Any moves in progress are halted immediately when `Stepper` goes out of scope.
+///
+/// [module documentation]: index.html
+#[derive(Debug)]
+pub struct Stepper {
+    config: StepperConfig,
+    pins: Arc<Mutex<Pins>>,
+    enable: Option<OutputPin>,
+    shared: Arc<Shared>,
+    motion: Option<Motion>,
+}
+
+impl Stepper {
+    /// Constructs a new `Stepper`.
+    ///
+    /// `step` and `dir` are connected to the driver's STEP and DIR inputs. If the
+    /// driver's ENABLE input is connected to a GPIO pin, `enable` can be used to
+    /// energize or release the motor coils with [`set_enabled`]. The driver is enabled
+    /// when `Stepper` is constructed.
+    ///
+    /// The current position starts at `0`.
+    ///
+    /// [`set_enabled`]: #method.set_enabled
+    pub fn new(
+        mut step: OutputPin,
+        mut dir: OutputPin,
+        enable: Option<OutputPin>,
+        config: StepperConfig,
+    ) -> Result<Stepper> {
+        validate(&config)?;
+
+        step.clear_pwm()?;
+        dir.clear_pwm()?;
+        step.set_low();
+
+        let mut stepper = Stepper {
+            config,
+            pins: Arc::new(Mutex::new(Pins { step, dir })),
+            enable,
+            shared: Arc::new(Shared {
+                position: Mutex::new(0),
+                moving: AtomicBool::new(false),
+            }),
+            motion: None,
+        };
+
+        stepper.set_enabled(true)?;
+
+        Ok(stepper)
+    }
+
+    /// Returns the motion parameters.
+    pub fn config(&self) -> StepperConfig {
+        self.config
+    }
+
+    /// Sets the motion parameters.
+    ///
+    /// The new parameters are applied to any subsequent moves. A move that's
+    /// currently in progress isn't affected.
+    pub fn set_config(&mut self, config: StepperConfig) -> Result<()> {
+        validate(&config)?;
+
+        self.config = config;
+
+        Ok(())
+    }
+
+    /// Returns the current position in steps.
+    pub fn position(&self) -> i64 {
+        *self.shared.position.lock().unwrap()
+    }
+
+    /// Sets the current position, without moving the motor.
+    ///
+    /// This can be used to define the home position after a homing sequence. Returns
+    /// `Err(`[`Error::Io`]`)` with `EBUSY` if the motor is currently moving.
+    ///
+    /// [`Error::Io`]: ../enum.Error.html#variant.Io
+    pub fn set_position(&mut self, position: i64) -> Result<()> {
+        self.check_idle()?;
+
+        *self.shared.position.lock().unwrap() = position;
+
+        Ok(())
+    }
+
+    /// Returns `true` if the driver is enabled.
+    ///
+    /// Always returns `true` if no enable pin was specified.
+    pub fn is_enabled(&self) -> bool {
+        match self.enable {
+            Some(ref enable) => enable.is_set_high(),
+            None => true,
+        }
+    }
+
+    /// Enables or disables the driver through the enable pin.
+    ///
+    /// Disabling the driver releases the motor coils, which allows the motor shaft to
+    /// turn freely. Returns `Err(`[`Error::Io`]`)` with `EBUSY` if the motor is
+    /// currently moving. Does nothing if no enable pin was specified.
+    ///
+    /// [`Error::Io`]: ../enum.Error.html#variant.Io
+    pub fn set_enabled(&mut self, enabled: bool) -> Result<()> {
+        if !enabled {
+            self.check_idle()?;
+        }
+
+        if let Some(ref mut enable) = self.enable {
+            if enabled {
+                enable.set_high();
+            } else {
+                enable.set_low();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Starts a move to the specified absolute position, and returns immediately.
+    ///
+    /// Call [`wait`] to block until the move is completed, or [`stop`] to end the
+    /// move early. Returns `Err(`[`Error::Io`]`)` with `EBUSY` if the motor is
+    /// already moving.
+    ///
+    /// [`wait`]: #method.wait
+    /// [`stop`]: #method.stop
+    /// [`Error::Io`]: ../enum.Error.html#variant.Io
+    pub fn move_to(&mut self, position: i64) -> Result<()> {
+        self.check_idle()?;
+
+        let steps = position - self.position();
+        self.start(steps)
+    }
+
+    /// Starts a move by the specified number of steps relative to the current
+    /// position, and returns immediately.
+    ///
+    /// Positive values move the motor forward, with the direction pin set high.
+    /// Negative values move the motor backward, with the direction pin set low.
+    ///
+    /// Call [`wait`] to block until the move is completed, or [`stop`] to end the
+    /// move early. Returns `Err(`[`Error::Io`]`)` with `EBUSY` if the motor is
+    /// already moving.
+    ///
+    /// [`wait`]: #method.wait
+    /// [`stop`]: #method.stop
+    /// [`Error::Io`]: ../enum.Error.html#variant.Io
+    pub fn move_by(&mut self, steps: i64) -> Result<()> {
+        self.check_idle()?;
+
+        self.start(steps)
+    }
+
+    /// Returns `true` if a move is in progress.
+    pub fn is_moving(&self) -> bool {
+        self.shared.moving.load(Ordering::SeqCst)
+    }
+
+    /// Blocks until the current move is completed.
+    ///
+    /// Returns immediately if the motor isn't moving.
+    pub fn wait(&mut self) -> Result<()> {
+        if let Some(motion) = self.motion.take() {
+            if motion.motion_thread.join().is_err() {
+                return Err(Error::ThreadPanic);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decelerates to a stop as soon as possible, and blocks until the motor has
+    /// stopped.
+    ///
+    /// The motor decelerates according to the configured motion profile, which
+    /// means it might take several steps to come to a stop.
+    pub fn stop(&mut self) -> Result<()> {
+        self.send(Msg::Stop)
+    }
+
+    /// Stops immediately, without decelerating, and blocks until the motor has
+    /// stopped.
+    ///
+    /// Stopping abruptly at high speeds can cause the motor to overshoot, which
+    /// means the position might no longer be accurate.
+    pub fn halt(&mut self) -> Result<()> {
+        self.send(Msg::Halt)
+    }
+
+    fn send(&mut self, msg: Msg) -> Result<()> {
+        if let Some(ref motion) = self.motion {
+            let _ = motion.sender.send(msg);
+        }
+
+        self.wait()
+    }
+
+    fn check_idle(&mut self) -> Result<()> {
+        if self.is_moving() {
+            return Err(Error::Io(io::Error::from_raw_os_error(libc::EBUSY)));
+        }
+
+        // Clean up after any previous move
+        self.wait()
+    }
+
+    fn start(&mut self, steps: i64) -> Result<()> {
+        if steps == 0 {
+            return Ok(());
+        }
+
+        let plan = Plan::new(&self.config, steps.wrapping_abs() as u64);
+        let direction = steps.signum();
+        let pulse_width_ns = self.config.pulse_width.as_nanos() as i64;
+        let dir_setup_ns = self.config.dir_setup.as_nanos() as i64;
+
+        let (sender, receiver): (Sender<Msg>, Receiver<Msg>) = mpsc::channel();
+        let pins = self.pins.clone();
+        let shared = self.shared.clone();
+
+        self.shared.moving.store(true, Ordering::SeqCst);
+
+        let motion_thread = thread::spawn(move || {
+            run(
+                &mut pins.lock().unwrap(),
+                &shared,
+                &receiver,
+                plan,
+                direction,
+                pulse_width_ns,
+                dir_setup_ns,
+            );
+
+            shared.moving.store(false, Ordering::SeqCst);
+        });
+
+        self.motion = Some(Motion {
+            motion_thread,
+            sender,
+        });
+
+        Ok(())
+    }
+}
+
+impl Drop for Stepper {
+    fn drop(&mut self) {
+        let _ = self.halt();
+    }
+}
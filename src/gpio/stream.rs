@@ -0,0 +1,324 @@
+// Copyright (c) 2017-2019 Rene van der Meer
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL
+// THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::marker::PhantomData;
+use std::pin::Pin as StdPin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use futures_core::Stream;
+
+use crate::gpio::epoll::{
+    self, epoll_event, Epoll, EventFd, EPOLLET, EPOLLIN, EPOLLONESHOT, EPOLLPRI,
+};
+use crate::gpio::interrupt::{time_until, Interrupt};
+use crate::gpio::pin::InputPin;
+use crate::gpio::{Error, Event, Result};
+
+// Epoll id used for the EventFd that wakes up the reactor thread
+const ID_NOTIFY: u64 = !0;
+
+#[derive(Debug, Default)]
+struct Registration {
+    waker: Option<Waker>,
+    // Time at which a debounced edge becomes stable
+    deadline: Option<Instant>,
+}
+
+#[derive(Debug)]
+struct ReactorInner {
+    poll: Epoll,
+    notify: EventFd,
+    stop: AtomicBool,
+    registrations: Mutex<HashMap<i32, Registration>>,
+    // OS error that stopped the reactor thread
+    error: Mutex<Option<i32>>,
+}
+
+impl ReactorInner {
+    // Returns the error that stopped the reactor thread, if any
+    fn check(&self) -> Result<()> {
+        match *self.error.lock().unwrap() {
+            Some(code) => Err(Error::Io(io::Error::from_raw_os_error(code))),
+            None => Ok(()),
+        }
+    }
+
+    fn run(&self) {
+        let mut events = [epoll_event { events: 0, u64: 0 }; 16];
+        let mut wakers = Vec::new();
+
+        loop {
+            let timeout = self
+                .registrations
+                .lock()
+                .unwrap()
+                .values()
+                .filter_map(|registration| registration.deadline)
+                .min()
+                .map(time_until);
+
+            let num_events = match self.poll.wait(&mut events, timeout) {
+                Ok(num_events) => num_events,
+                // Restart the wait if it was interrupted by a signal
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    // Any remaining errors are unrecoverable. The error is stored, and
+                    // all waiting tasks are woken up, so it can be returned by poll_next.
+                    *self.error.lock().unwrap() = Some(e.raw_os_error().unwrap_or(libc::EIO));
+
+                    let wakers: Vec<Waker> = self
+                        .registrations
+                        .lock()
+                        .unwrap()
+                        .values_mut()
+                        .filter_map(|registration| registration.waker.take())
+                        .collect();
+
+                    for waker in wakers {
+                        waker.wake();
+                    }
+
+                    return;
+                }
+            };
+
+            if self.stop.load(Ordering::SeqCst) {
+                return;
+            }
+
+            {
+                let mut registrations = self.registrations.lock().unwrap();
+
+                for event in &events[0..num_events] {
+                    if event.u64 == ID_NOTIFY {
+                        continue;
+                    }
+
+                    if let Some(registration) = registrations.get_mut(&(event.u64 as i32)) {
+                        registration.deadline = None;
+                        wakers.extend(registration.waker.take());
+                    }
+                }
+
+                let now = Instant::now();
+                for registration in registrations.values_mut() {
+                    let expired = match registration.deadline {
+                        Some(deadline) => deadline <= now,
+                        None => false,
+                    };
+
+                    if expired {
+                        registration.deadline = None;
+                        wakers.extend(registration.waker.take());
+                    }
+                }
+            }
+
+            // Wake the tasks after releasing the lock, in case an executor polls
+            // the stream on the current thread.
+            for waker in wakers.drain(..) {
+                waker.wake();
+            }
+        }
+    }
+}
+
+// Wakes up tasks that are waiting for interrupt events, using a single thread
+// for all EventStreams that share the same GpioState.
+#[derive(Debug)]
+pub(crate) struct Reactor {
+    inner: Arc<ReactorInner>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Reactor {
+    pub(crate) fn new() -> Result<Reactor> {
+        let inner = Arc::new(ReactorInner {
+            poll: Epoll::new()?,
+            notify: EventFd::new()?,
+            stop: AtomicBool::new(false),
+            registrations: Mutex::new(HashMap::new()),
+            error: Mutex::new(None),
+        });
+
+        inner
+            .poll
+            .add(inner.notify.fd(), ID_NOTIFY, EPOLLET | EPOLLIN)?;
+
+        let thread_inner = inner.clone();
+        let thread = thread::spawn(move || thread_inner.run());
+
+        Ok(Reactor {
+            inner,
+            thread: Some(thread),
+        })
+    }
+
+    fn register(&self, fd: i32) -> Result<()> {
+        self.inner
+            .registrations
+            .lock()
+            .unwrap()
+            .insert(fd, Registration::default());
+
+        // The fd is disarmed after it becomes readable once, and re-armed by
+        // wake_on().
+        self.inner
+            .poll
+            .add(fd, fd as u64, EPOLLIN | EPOLLPRI | EPOLLONESHOT)?;
+
+        Ok(())
+    }
+
+    fn deregister(&self, fd: i32) -> Result<()> {
+        self.inner.registrations.lock().unwrap().remove(&fd);
+        self.inner.poll.delete(fd)?;
+
+        Ok(())
+    }
+
+    // Wake the task when the fd becomes readable, or the deadline passes
+    fn wake_on(&self, fd: i32, waker: &Waker, deadline: Option<Instant>) -> Result<()> {
+        if let Some(registration) = self.inner.registrations.lock().unwrap().get_mut(&fd) {
+            registration.waker = Some(waker.clone());
+            registration.deadline = deadline;
+        }
+
+        // The waker is stored first, so it's either woken up by the reactor thread
+        // when it stops, or the error is returned here
+        self.inner.check()?;
+
+        self.inner
+            .poll
+            .modify(fd, fd as u64, EPOLLIN | EPOLLPRI | EPOLLONESHOT)?;
+
+        // Make sure the reactor thread picks up the new deadline
+        if deadline.is_some() {
+            self.inner.notify.notify()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Reactor {
+    fn drop(&mut self) {
+        self.inner.stop.store(true, Ordering::SeqCst);
+
+        if self.inner.notify.notify().is_ok() {
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+/// Stream of interrupt trigger events.
+///
+/// `EventStream` is constructed by calling [`InputPin::events`], and implements
+/// [`Stream`]`<Item = `[`Result`]`<`[`Event`]`>>`. The stream never ends, but
+/// the interrupt trigger is removed when it goes out of scope.
+///
+/// All `EventStream`s share a single background thread that wakes up the relevant
+/// task when an interrupt trigger event occurs, which means `EventStream` can be used
+/// with any async runtime.
+///
+/// `EventStream` is only available when the optional `async` feature is enabled.
+///
+/// [`InputPin::events`]: struct.InputPin.html#method.events
+/// [`Stream`]: https://docs.rs/futures-core/0.3/futures_core/stream/trait.Stream.html
+/// [`Result`]: type.Result.html
+/// [`Event`]: struct.Event.html
+pub struct EventStream<'a> {
+    interrupt: Interrupt,
+    reactor: Arc<Reactor>,
+    // An EventStream borrows the InputPin, so the pin's interrupt triggers can't be
+    // changed while the stream exists.
+    phantom: PhantomData<&'a mut InputPin>,
+}
+
+impl<'a> EventStream<'a> {
+    pub(crate) fn new(interrupt: Interrupt, reactor: Arc<Reactor>) -> Result<EventStream<'a>> {
+        reactor.register(interrupt.fd())?;
+
+        Ok(EventStream {
+            interrupt,
+            reactor,
+            phantom: PhantomData,
+        })
+    }
+
+    fn next_event(&mut self) -> Result<Option<Event>> {
+        // Read any queued events without blocking
+        while epoll::poll_readable(&[self.interrupt.fd()], Some(Duration::new(0, 0)))? {
+            if let Some(event) = self.interrupt.event()? {
+                return Ok(Some(event));
+            }
+        }
+
+        Ok(self.interrupt.poll_debounced())
+    }
+}
+
+impl<'a> Stream for EventStream<'a> {
+    type Item = Result<Event>;
+
+    fn poll_next(self: StdPin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Event>>> {
+        let stream = self.get_mut();
+
+        match stream.next_event() {
+            Ok(Some(event)) => return Poll::Ready(Some(Ok(event))),
+            Err(e) => return Poll::Ready(Some(Err(e))),
+            Ok(None) => (),
+        }
+
+        // The reactor wakes us up right away if the fd became readable in the meantime
+        match stream.reactor.wake_on(
+            stream.interrupt.fd(),
+            cx.waker(),
+            stream.interrupt.deadline(),
+        ) {
+            Ok(()) => Poll::Pending,
+            Err(e) => Poll::Ready(Some(Err(e))),
+        }
+    }
+}
+
+impl<'a> fmt::Debug for EventStream<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventStream")
+            .field("interrupt", &self.interrupt)
+            .field("reactor", &self.reactor)
+            .finish()
+    }
+}
+
+impl<'a> Drop for EventStream<'a> {
+    fn drop(&mut self) {
+        let _ = self.reactor.deregister(self.interrupt.fd());
+    }
+}
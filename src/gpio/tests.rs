@@ -0,0 +1,1007 @@
+// Copyright (c) 2017-2019 Rene van der Meer
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL
+// THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+// These tests run on the simulated backend or only use static pin tables, so
+// they don't require a Raspberry Pi.
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::header::{Header, PinType};
+use super::stepper::{Stepper, StepperConfig};
+use super::{
+    alt_function, function_pins, Backend, Detect, Error, Event, Function, Gpio, InputPort, Level,
+    Mode, OutputPort, PwmBackend, SchedulerConfig, Simulator, Trigger,
+};
+use crate::system::{Model, SoC};
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+fn simulated() -> (Simulator, Gpio) {
+    let simulator = Simulator::new();
+    let gpio = Gpio::with_backend(Backend::Simulated(simulator.clone())).unwrap();
+
+    (simulator, gpio)
+}
+
+fn millis(ms: u64) -> Duration {
+    // Injected timestamps don't need to match the current time
+    Duration::from_secs(1000) + Duration::from_millis(ms)
+}
+
+// Waits until the condition is true, or panics after TIMEOUT
+fn wait_until<F: Fn() -> bool>(condition: F) {
+    let mut elapsed = Duration::new(0, 0);
+    while !condition() {
+        assert!(elapsed < TIMEOUT, "timed out");
+
+        thread::sleep(Duration::from_millis(1));
+        elapsed += Duration::from_millis(1);
+    }
+}
+
+#[test]
+fn poll_interrupt() {
+    let (simulator, gpio) = simulated();
+    let mut pin = gpio.get(17).unwrap().into_input();
+
+    pin.set_interrupt(Trigger::RisingEdge).unwrap();
+    assert_eq!(
+        pin.poll_interrupt(false, Some(Duration::from_millis(10)))
+            .unwrap(),
+        None
+    );
+
+    simulator.set_level(17, Level::High).unwrap();
+    assert_eq!(
+        pin.poll_interrupt(false, Some(TIMEOUT)).unwrap(),
+        Some(Level::High)
+    );
+
+    // Falling edges don't match the trigger
+    simulator.set_level(17, Level::Low).unwrap();
+    assert_eq!(
+        pin.poll_interrupt(false, Some(Duration::from_millis(10)))
+            .unwrap(),
+        None
+    );
+}
+
+#[test]
+fn unread_events_dont_block() {
+    let (simulator, gpio) = simulated();
+    let mut pin = gpio.get(17).unwrap().into_input();
+
+    // Fill up the socket's buffer without reading any events
+    pin.set_interrupt(Trigger::Both).unwrap();
+    for idx in 0..10_000 {
+        let level = if idx % 2 == 0 {
+            Level::High
+        } else {
+            Level::Low
+        };
+        simulator.set_level(17, level).unwrap();
+    }
+
+    assert_eq!(simulator.level(17).unwrap(), Level::Low);
+    assert!(pin.poll_interrupt(false, Some(TIMEOUT)).unwrap().is_some());
+}
+
+#[test]
+fn set_async_interrupt() {
+    let (simulator, gpio) = simulated();
+    let mut pin = gpio.get(17).unwrap().into_input();
+
+    let (sender, receiver) = mpsc::channel();
+    pin.set_async_interrupt(Trigger::Both, move |level| sender.send(level).unwrap())
+        .unwrap();
+
+    simulator.set_level(17, Level::High).unwrap();
+    assert_eq!(receiver.recv_timeout(TIMEOUT), Ok(Level::High));
+
+    simulator.set_level(17, Level::Low).unwrap();
+    assert_eq!(receiver.recv_timeout(TIMEOUT), Ok(Level::Low));
+
+    pin.clear_async_interrupt().unwrap();
+    simulator.set_level(17, Level::High).unwrap();
+    assert!(receiver.recv_timeout(Duration::from_millis(10)).is_err());
+}
+
+#[test]
+fn ports() {
+    let (simulator, gpio) = simulated();
+
+    let mut output = OutputPort::new(vec![
+        gpio.get(5).unwrap().into_output(),
+        gpio.get(6).unwrap().into_output(),
+        gpio.get(13).unwrap().into_output(),
+    ])
+    .unwrap();
+
+    output.write(0b101);
+    assert_eq!(output.read(), 0b101);
+    assert_eq!(simulator.level(5).unwrap(), Level::High);
+    assert_eq!(simulator.level(6).unwrap(), Level::Low);
+    assert_eq!(simulator.level(13).unwrap(), Level::High);
+
+    let input = InputPort::new(vec![
+        gpio.get(20).unwrap().into_input(),
+        gpio.get(21).unwrap().into_input(),
+    ])
+    .unwrap();
+
+    simulator.set_level(21, Level::High).unwrap();
+    assert_eq!(input.read(), 0b10);
+
+    assert!(InputPort::new(Vec::new()).is_err());
+
+    // Pins from different simulators don't share a backend
+    let (_, other_gpio) = simulated();
+    assert!(OutputPort::new(vec![
+        gpio.get(16).unwrap().into_output(),
+        other_gpio.get(16).unwrap().into_output(),
+    ])
+    .is_err());
+}
+
+#[test]
+fn edge_detect() {
+    let (simulator, gpio) = simulated();
+    let mut pin = gpio.get(17).unwrap().into_input();
+
+    pin.set_edge_detect(Detect::RisingEdge).unwrap();
+    assert!(!pin.take_edge_detected());
+
+    // A short pulse is latched until the event is cleared
+    simulator.set_level(17, Level::High).unwrap();
+    simulator.set_level(17, Level::Low).unwrap();
+    assert!(pin.take_edge_detected());
+    assert!(!pin.take_edge_detected());
+
+    // Level detection keeps reporting events while the level persists
+    pin.set_edge_detect(Detect::Low).unwrap();
+    assert!(pin.take_edge_detected());
+    assert!(pin.take_edge_detected());
+
+    simulator.set_level(17, Level::High).unwrap();
+    assert!(pin.take_edge_detected());
+    assert!(!pin.take_edge_detected());
+
+    // Active-low pins invert the detected edges and levels
+    pin.set_active_low(true);
+    pin.set_edge_detect(Detect::Low).unwrap();
+    assert!(pin.take_edge_detected());
+
+    pin.set_edge_detect(Detect::AsyncFallingEdge).unwrap();
+    simulator.set_level(17, Level::Low).unwrap();
+    assert!(!pin.take_edge_detected());
+    simulator.set_level(17, Level::High).unwrap();
+    assert!(pin.take_edge_detected());
+
+    pin.set_edge_detect(Detect::Disabled).unwrap();
+    simulator.set_level(17, Level::Low).unwrap();
+    assert!(!pin.take_edge_detected());
+}
+
+#[test]
+fn poll_event_timestamp() {
+    let (simulator, gpio) = simulated();
+    let mut pin = gpio.get(17).unwrap().into_input();
+
+    pin.set_interrupt(Trigger::Both).unwrap();
+    simulator.set_level_at(17, Level::High, millis(5)).unwrap();
+
+    assert_eq!(
+        pin.poll_event(false, Some(TIMEOUT)).unwrap(),
+        Some(Event {
+            pin: 17,
+            trigger: Trigger::RisingEdge,
+            timestamp: millis(5),
+            seqno: 1,
+        })
+    );
+}
+
+#[test]
+fn read_events() {
+    let (simulator, gpio) = simulated();
+    let mut pin = gpio.get(17).unwrap().into_input();
+
+    pin.set_interrupt(Trigger::Both).unwrap();
+    for idx in 0..40 {
+        let level = if idx % 2 == 0 {
+            Level::High
+        } else {
+            Level::Low
+        };
+        simulator.set_level_at(17, level, millis(idx)).unwrap();
+    }
+
+    let mut events = [Event {
+        pin: 0,
+        trigger: Trigger::Disabled,
+        timestamp: Duration::new(0, 0),
+        seqno: 0,
+    }; 32];
+    let events_read = pin.read_events(&mut events).unwrap();
+    assert_eq!(events_read.count, 32);
+    assert_eq!(events_read.lost, 0);
+    assert!(events_read.pending);
+
+    for (idx, event) in events.iter().enumerate() {
+        assert_eq!(event.seqno, idx as u32 + 1);
+        assert_eq!(event.timestamp, millis(idx as u64));
+    }
+
+    let events_read = pin.read_events(&mut events).unwrap();
+    assert_eq!(events_read.count, 8);
+    assert!(!events_read.pending);
+}
+
+#[test]
+fn debounce() {
+    let (simulator, gpio) = simulated();
+    let mut pin = gpio.get(17).unwrap().into_input();
+
+    pin.set_debounce(Some(Duration::from_millis(20)));
+    pin.set_interrupt(Trigger::Both).unwrap();
+
+    // Bouncing contact that settles on high
+    simulator.set_level_at(17, Level::High, millis(0)).unwrap();
+    simulator.set_level_at(17, Level::Low, millis(1)).unwrap();
+    simulator.set_level_at(17, Level::High, millis(2)).unwrap();
+
+    assert_eq!(
+        pin.poll_interrupt(false, Some(TIMEOUT)).unwrap(),
+        Some(Level::High)
+    );
+    assert_eq!(
+        pin.poll_interrupt(false, Some(Duration::from_millis(50)))
+            .unwrap(),
+        None
+    );
+
+    // Glitch that returns to the previous stable level
+    simulator.set_level_at(17, Level::Low, millis(100)).unwrap();
+    simulator
+        .set_level_at(17, Level::High, millis(101))
+        .unwrap();
+
+    assert_eq!(
+        pin.poll_interrupt(false, Some(Duration::from_millis(50)))
+            .unwrap(),
+        None
+    );
+}
+
+#[test]
+fn stepper() {
+    let (simulator, gpio) = simulated();
+
+    let config = StepperConfig {
+        max_speed: 20_000.0,
+        acceleration: 200_000.0,
+        ..StepperConfig::default()
+    };
+
+    let mut stepper = Stepper::new(
+        gpio.get(23).unwrap().into_output(),
+        gpio.get(24).unwrap().into_output(),
+        None,
+        config,
+    )
+    .unwrap();
+
+    stepper.move_to(200).unwrap();
+    stepper.wait().unwrap();
+    assert_eq!(stepper.position(), 200);
+    assert_eq!(simulator.level(24).unwrap(), Level::High);
+    assert_eq!(simulator.level(23).unwrap(), Level::Low);
+
+    stepper.move_by(-50).unwrap();
+    stepper.wait().unwrap();
+    assert_eq!(stepper.position(), 150);
+    assert_eq!(simulator.level(24).unwrap(), Level::Low);
+    assert!(!stepper.is_moving());
+}
+
+#[test]
+fn measure_pulse() {
+    let (simulator, gpio) = simulated();
+    let mut pin = gpio.get(17).unwrap().into_input();
+
+    let pulse = {
+        let simulator = simulator.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            simulator.set_level_at(17, Level::High, millis(10)).unwrap();
+            simulator.set_level_at(17, Level::Low, millis(25)).unwrap();
+        })
+    };
+
+    assert_eq!(
+        pin.measure_pulse(Level::High, Some(TIMEOUT)).unwrap(),
+        Some(Duration::from_millis(15))
+    );
+    pulse.join().unwrap();
+
+    assert_eq!(
+        pin.measure_pulse(Level::High, Some(Duration::from_millis(10)))
+            .unwrap(),
+        None
+    );
+}
+
+#[test]
+fn frequency_counter() {
+    let (simulator, gpio) = simulated();
+    let mut pin = gpio.get(17).unwrap().into_input();
+    let counter = pin.frequency_counter(Duration::from_secs(10)).unwrap();
+
+    // 100 Hz signal with a 30% duty cycle
+    for period in 0..11 {
+        simulator
+            .set_level_at(17, Level::High, millis(period * 10))
+            .unwrap();
+        simulator
+            .set_level_at(17, Level::Low, millis(period * 10 + 3))
+            .unwrap();
+    }
+
+    wait_until(|| counter.count() == 11 && counter.pulse_width().is_some());
+
+    assert!((counter.frequency() - 100.0).abs() < 1e-6);
+    assert_eq!(counter.period(), Some(Duration::from_millis(10)));
+    assert_eq!(counter.pulse_width(), Some(Duration::from_millis(3)));
+    assert!((counter.duty_cycle().unwrap() - 0.3).abs() < 1e-6);
+}
+
+#[test]
+fn frequency_counter_timestamps_backwards() {
+    let (simulator, gpio) = simulated();
+    let mut pin = gpio.get(17).unwrap().into_input();
+    let counter = pin.frequency_counter(Duration::from_secs(10)).unwrap();
+
+    // The realtime clock was stepped back between the two rising edges
+    simulator
+        .set_level_at(17, Level::High, millis(100))
+        .unwrap();
+    simulator.set_level_at(17, Level::Low, millis(103)).unwrap();
+    simulator.set_level_at(17, Level::High, millis(50)).unwrap();
+
+    wait_until(|| counter.count() == 2);
+
+    assert_eq!(counter.frequency(), 0.0);
+    assert_eq!(counter.period(), None);
+}
+
+#[test]
+fn shared_dispatcher() {
+    let (simulator, gpio) = simulated();
+    let (sender, receiver) = mpsc::channel();
+
+    let mut pins = Vec::new();
+    for &pin in &[17, 22, 27] {
+        let mut input = gpio.get(pin).unwrap().into_input();
+        let sender = sender.clone();
+        input
+            .set_async_interrupt(Trigger::RisingEdge, move |_| sender.send(pin).unwrap())
+            .unwrap();
+        pins.push(input);
+    }
+
+    for &pin in &[27, 17, 22] {
+        simulator.set_level(pin, Level::High).unwrap();
+        assert_eq!(receiver.recv_timeout(TIMEOUT), Ok(pin));
+    }
+
+    // Removing one trigger doesn't affect the others
+    pins[1].clear_async_interrupt().unwrap();
+    simulator.set_level(22, Level::Low).unwrap();
+    simulator.set_level(22, Level::High).unwrap();
+    simulator.set_level(17, Level::Low).unwrap();
+    simulator.set_level(17, Level::High).unwrap();
+    assert_eq!(receiver.recv_timeout(TIMEOUT), Ok(17));
+    assert!(receiver.recv_timeout(Duration::from_millis(10)).is_err());
+}
+
+#[test]
+fn clear_async_interrupt_waits_for_callback() {
+    let (simulator, gpio) = simulated();
+    let mut pin = gpio.get(17).unwrap().into_input();
+
+    let (started_sender, started) = mpsc::channel();
+    let finished = Arc::new(AtomicBool::new(false));
+    let callback_finished = finished.clone();
+    pin.set_async_interrupt(Trigger::RisingEdge, move |_| {
+        started_sender.send(()).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        callback_finished.store(true, Ordering::SeqCst);
+    })
+    .unwrap();
+
+    simulator.set_level(17, Level::High).unwrap();
+    started.recv_timeout(TIMEOUT).unwrap();
+
+    pin.clear_async_interrupt().unwrap();
+    assert!(finished.load(Ordering::SeqCst));
+}
+
+#[test]
+fn header_for_model() {
+    assert!(Header::for_model(Model::RaspberryPiComputeModule).is_none());
+    assert!(Header::for_model(Model::RaspberryPiComputeModule3).is_none());
+    assert!(Header::for_model(Model::RaspberryPiComputeModule3Plus).is_none());
+
+    for &(model, pins) in &[
+        (Model::RaspberryPiA, 26),
+        (Model::RaspberryPiBRev1, 26),
+        (Model::RaspberryPiBRev2, 26),
+        (Model::RaspberryPiAPlus, 40),
+        (Model::RaspberryPiBPlus, 40),
+        (Model::RaspberryPi2B, 40),
+        (Model::RaspberryPi3B, 40),
+        (Model::RaspberryPi4B, 40),
+        (Model::RaspberryPiZeroW, 40),
+    ] {
+        let header = Header::for_model(model).unwrap();
+        assert_eq!(header.model(), model);
+        assert_eq!(header.pins().len(), pins);
+    }
+}
+
+#[test]
+fn header_physical_to_bcm() {
+    let header = Header::for_model(Model::RaspberryPi3B).unwrap();
+
+    assert_eq!(header.pin_type(1), Some(PinType::Power3v3));
+    assert_eq!(header.pin_type(6), Some(PinType::Ground));
+    assert_eq!(header.physical_to_bcm(0), None);
+    assert_eq!(header.physical_to_bcm(1), None);
+    assert_eq!(header.physical_to_bcm(3), Some(2));
+    assert_eq!(header.physical_to_bcm(11), Some(17));
+    assert_eq!(header.physical_to_bcm(40), Some(21));
+    assert_eq!(header.physical_to_bcm(41), None);
+
+    assert_eq!(header.bcm_to_physical(2), Some(3));
+    assert_eq!(header.bcm_to_physical(17), Some(11));
+    assert_eq!(header.bcm_to_physical(21), Some(40));
+    assert_eq!(header.bcm_to_physical(28), None);
+
+    // Every GPIO pin on the header should round-trip
+    for physical in 1..=40 {
+        if let Some(pin) = header.physical_to_bcm(physical) {
+            assert_eq!(header.bcm_to_physical(pin), Some(physical));
+        }
+    }
+}
+
+#[test]
+fn header_rev1() {
+    let header = Header::for_model(Model::RaspberryPiBRev1).unwrap();
+
+    // Pins 3, 5 and 13 were connected to different GPIO pins on the Rev 1
+    assert_eq!(header.physical_to_bcm(3), Some(0));
+    assert_eq!(header.physical_to_bcm(5), Some(1));
+    assert_eq!(header.physical_to_bcm(13), Some(21));
+    assert_eq!(header.bcm_to_physical(2), None);
+    assert_eq!(header.bcm_to_physical(27), None);
+
+    for &physical in &[4, 9, 14, 17, 20, 25] {
+        assert_eq!(header.pin_type(physical), Some(PinType::DoNotConnect));
+    }
+
+    assert!(header.p5_pins().is_empty());
+}
+
+#[test]
+fn header_rev2_p5() {
+    let header = Header::for_model(Model::RaspberryPiBRev2).unwrap();
+
+    assert_eq!(header.physical_to_bcm(3), Some(2));
+    assert_eq!(header.physical_to_bcm(13), Some(27));
+    assert_eq!(header.pin_type(4), Some(PinType::Power5v));
+    assert_eq!(header.p5_pins().len(), 8);
+    assert_eq!(header.p5_pins()[2], PinType::Gpio(28));
+    assert_eq!(header.p5_pins()[5], PinType::Gpio(31));
+
+    // GPIO 28-31 are only available on P5, not on the main header
+    assert_eq!(header.bcm_to_physical(28), None);
+
+    let header = Header::for_model(Model::RaspberryPi3B).unwrap();
+    assert!(header.p5_pins().is_empty());
+}
+
+#[test]
+fn header_wiringpi() {
+    let header = Header::for_model(Model::RaspberryPiBRev1).unwrap();
+    assert_eq!(header.wiringpi_to_bcm(0), Some(17));
+    assert_eq!(header.wiringpi_to_bcm(2), Some(21));
+    assert_eq!(header.wiringpi_to_bcm(8), Some(0));
+    assert_eq!(header.wiringpi_to_bcm(16), Some(15));
+    assert_eq!(header.wiringpi_to_bcm(17), None);
+    assert_eq!(header.bcm_to_wiringpi(21), Some(2));
+    assert_eq!(header.bcm_to_wiringpi(27), None);
+
+    let header = Header::for_model(Model::RaspberryPiBRev2).unwrap();
+    assert_eq!(header.wiringpi_to_bcm(2), Some(27));
+    assert_eq!(header.wiringpi_to_bcm(8), Some(2));
+    assert_eq!(header.wiringpi_to_bcm(17), Some(28));
+    assert_eq!(header.wiringpi_to_bcm(20), Some(31));
+    // GPIO 5 is only available on the 40-pin header
+    assert_eq!(header.wiringpi_to_bcm(21), None);
+    assert_eq!(header.bcm_to_wiringpi(28), Some(17));
+
+    let header = Header::for_model(Model::RaspberryPi3B).unwrap();
+    assert_eq!(header.wiringpi_to_bcm(0), Some(17));
+    assert_eq!(header.wiringpi_to_bcm(17), None);
+    assert_eq!(header.wiringpi_to_bcm(21), Some(5));
+    assert_eq!(header.wiringpi_to_bcm(31), Some(1));
+    assert_eq!(header.wiringpi_to_bcm(32), None);
+    assert_eq!(header.bcm_to_wiringpi(5), Some(21));
+    assert_eq!(header.bcm_to_wiringpi(28), None);
+
+    for wiringpi in 0..32 {
+        if let Some(pin) = header.wiringpi_to_bcm(wiringpi) {
+            assert_eq!(header.bcm_to_wiringpi(pin), Some(wiringpi));
+        }
+    }
+}
+
+#[test]
+fn get_physical_with() {
+    let (_simulator, gpio) = simulated();
+    let header = Header::for_model(Model::RaspberryPi3B).unwrap();
+
+    assert_eq!(gpio.get_physical_with(&header, 11).unwrap().pin(), 17);
+
+    match gpio.get_physical_with(&header, 1) {
+        Err(Error::PhysicalPinNotAvailable(1)) => (),
+        _ => panic!("expected PhysicalPinNotAvailable"),
+    }
+    match gpio.get_physical_with(&header, 41) {
+        Err(Error::PhysicalPinNotAvailable(41)) => (),
+        _ => panic!("expected PhysicalPinNotAvailable"),
+    }
+}
+
+#[test]
+fn alt_function_bcm2835() {
+    for &soc in &[SoC::Bcm2835, SoC::Bcm2836, SoC::Bcm2837A1, SoC::Bcm2837B0] {
+        assert_eq!(alt_function(soc, 14, Mode::Alt0), Some(Function::Uart0Tx));
+        assert_eq!(alt_function(soc, 15, Mode::Alt5), Some(Function::Uart1Rx));
+        assert_eq!(
+            alt_function(soc, 18, Mode::Alt5),
+            Some(Function::Pwm0Channel0)
+        );
+        assert_eq!(
+            alt_function(soc, 45, Mode::Alt0),
+            Some(Function::Pwm0Channel1)
+        );
+        assert_eq!(alt_function(soc, 45, Mode::Alt4), Some(Function::Spi2Ce2));
+        assert_eq!(alt_function(soc, 46, Mode::Alt0), None);
+        assert_eq!(alt_function(soc, 14, Mode::Input), None);
+    }
+}
+
+#[test]
+fn alt_function_bcm2711() {
+    let soc = SoC::Bcm2711;
+
+    assert_eq!(alt_function(soc, 14, Mode::Alt0), Some(Function::Uart0Tx));
+    assert_eq!(alt_function(soc, 14, Mode::Alt3), Some(Function::Spi5Mosi));
+    assert_eq!(
+        alt_function(soc, 18, Mode::Alt5),
+        Some(Function::Pwm0Channel0)
+    );
+    assert_eq!(
+        alt_function(soc, 40, Mode::Alt0),
+        Some(Function::Pwm1Channel0)
+    );
+    assert_eq!(
+        alt_function(soc, 41, Mode::Alt0),
+        Some(Function::Pwm1Channel1)
+    );
+    assert_eq!(
+        alt_function(soc, 45, Mode::Alt0),
+        Some(Function::Pwm0Channel1)
+    );
+    assert_eq!(alt_function(soc, 45, Mode::Alt4), Some(Function::Spi0Ce2));
+    assert_eq!(
+        alt_function(soc, 53, Mode::Alt1),
+        Some(Function::Pwm0Channel1)
+    );
+    assert_eq!(alt_function(soc, 54, Mode::Alt0), None);
+
+    assert_eq!(format!("{}", Function::Spi0Ce2), "SPI0_CE2_N");
+    assert_eq!(format!("{}", Function::Pwm0Channel1), "PWM0_1");
+}
+
+#[test]
+fn function_pins_matches_alt_function() {
+    let functions = [
+        Function::Uart0Tx,
+        Function::Spi0Ce0,
+        Function::Spi0Ce2,
+        Function::Pwm0Channel0,
+        Function::Pwm0Channel1,
+        Function::Pwm1Channel0,
+        Function::Gpclk0,
+        Function::SmiData(7),
+        Function::DpiData(23),
+    ];
+    let modes = [
+        Mode::Alt0,
+        Mode::Alt1,
+        Mode::Alt2,
+        Mode::Alt3,
+        Mode::Alt4,
+        Mode::Alt5,
+    ];
+
+    for &soc in &[SoC::Bcm2835, SoC::Bcm2837B0, SoC::Bcm2711] {
+        for &function in functions.iter() {
+            let mut expected = Vec::new();
+            for pin in 0..64 {
+                for &mode in modes.iter() {
+                    if alt_function(soc, pin, mode) == Some(function) {
+                        expected.push((pin, mode));
+                    }
+                }
+            }
+
+            assert_eq!(function_pins(soc, function), expected);
+        }
+    }
+
+    assert_eq!(
+        function_pins(SoC::Bcm2711, Function::Spi0Ce2),
+        vec![(45, Mode::Alt4)]
+    );
+    assert!(function_pins(SoC::Bcm2835, Function::Pwm1Channel0).is_empty());
+}
+
+#[test]
+fn open_drain() {
+    let (simulator, gpio) = simulated();
+
+    // Leave the pin in output mode, so we can check the original mode is restored
+    let mut pin = gpio.get(17).unwrap().into_output();
+    pin.set_reset_on_drop(false);
+    drop(pin);
+
+    let mut pin = gpio.get(17).unwrap().into_output_open_drain();
+
+    // Released while high
+    assert_eq!(simulator.mode(17).unwrap(), Mode::Input);
+    assert!(pin.is_set_high());
+
+    // Driven while low
+    pin.set_low();
+    assert_eq!(simulator.mode(17).unwrap(), Mode::Output);
+    assert_eq!(simulator.level(17).unwrap(), Level::Low);
+    assert!(pin.is_set_low());
+    assert_eq!(pin.read(), Level::Low);
+
+    // Another device holds the line low while the pin is released
+    pin.set_high();
+    simulator.set_level(17, Level::Low).unwrap();
+    assert_eq!(simulator.mode(17).unwrap(), Mode::Input);
+    assert!(pin.is_set_high());
+    assert_eq!(pin.read(), Level::Low);
+
+    simulator.set_level(17, Level::High).unwrap();
+    assert_eq!(pin.read(), Level::High);
+
+    drop(pin);
+    assert_eq!(simulator.mode(17).unwrap(), Mode::Output);
+}
+
+#[test]
+fn open_source() {
+    let (simulator, gpio) = simulated();
+
+    let mut pin = gpio.get(17).unwrap().into_io(Mode::Alt0);
+    pin.set_reset_on_drop(false);
+    drop(pin);
+
+    let mut pin = gpio.get(17).unwrap().into_output_open_source();
+
+    // Released while low
+    assert_eq!(simulator.mode(17).unwrap(), Mode::Input);
+    assert!(pin.is_set_low());
+
+    // Driven while high
+    pin.set_high();
+    assert_eq!(simulator.mode(17).unwrap(), Mode::Output);
+    assert_eq!(simulator.level(17).unwrap(), Level::High);
+    assert!(pin.is_set_high());
+    assert_eq!(pin.read(), Level::High);
+
+    // Another device holds the line high while the pin is released
+    pin.set_low();
+    simulator.set_level(17, Level::High).unwrap();
+    assert_eq!(simulator.mode(17).unwrap(), Mode::Input);
+    assert!(pin.is_set_low());
+    assert_eq!(pin.read(), Level::High);
+
+    pin.set_high();
+    drop(pin);
+    assert_eq!(simulator.mode(17).unwrap(), Mode::Alt0);
+}
+
+#[test]
+fn active_low_output() {
+    let (simulator, gpio) = simulated();
+    let mut pin = gpio.get(17).unwrap().into_output();
+    pin.set_active_low(true);
+
+    pin.set_high();
+    assert_eq!(simulator.level(17).unwrap(), Level::Low);
+    assert!(pin.is_set_high());
+    assert_eq!(pin.read(), Level::High);
+
+    pin.write(Level::Low);
+    assert_eq!(simulator.level(17).unwrap(), Level::High);
+    assert!(pin.is_set_low());
+    assert_eq!(pin.read(), Level::Low);
+}
+
+#[test]
+fn active_low_input() {
+    let (simulator, gpio) = simulated();
+    let mut pin = gpio.get(17).unwrap().into_input();
+    pin.set_active_low(true);
+
+    simulator.set_level(17, Level::Low).unwrap();
+    assert_eq!(pin.read(), Level::High);
+    assert!(pin.is_high());
+
+    simulator.set_level(17, Level::High).unwrap();
+    assert_eq!(pin.read(), Level::Low);
+    assert!(pin.is_low());
+}
+
+#[test]
+fn active_low_poll_interrupt() {
+    let (simulator, gpio) = simulated();
+    let mut pin = gpio.get(17).unwrap().into_input();
+    pin.set_active_low(true);
+    pin.set_interrupt(Trigger::RisingEdge).unwrap();
+
+    // A physical rising edge is a falling edge for an active-low pin
+    simulator.set_level(17, Level::High).unwrap();
+    assert_eq!(
+        pin.poll_interrupt(false, Some(Duration::from_millis(10)))
+            .unwrap(),
+        None
+    );
+
+    simulator.set_level(17, Level::Low).unwrap();
+    assert_eq!(
+        pin.poll_interrupt(false, Some(TIMEOUT)).unwrap(),
+        Some(Level::High)
+    );
+}
+
+#[test]
+fn active_low_async_interrupt() {
+    let (simulator, gpio) = simulated();
+    let mut pin = gpio.get(17).unwrap().into_input();
+    pin.set_active_low(true);
+
+    let (sender, receiver) = mpsc::channel();
+    pin.set_async_event_interrupt(Trigger::FallingEdge, move |event| {
+        sender.send((event.trigger, event.level())).unwrap();
+    })
+    .unwrap();
+
+    simulator.set_level(17, Level::High).unwrap();
+    assert_eq!(
+        receiver.recv_timeout(TIMEOUT),
+        Ok((Trigger::FallingEdge, Level::Low))
+    );
+
+    simulator.set_level(17, Level::Low).unwrap();
+    assert!(receiver.recv_timeout(Duration::from_millis(10)).is_err());
+}
+
+#[test]
+fn active_low_ports() {
+    let (simulator, gpio) = simulated();
+
+    let mut inverted = gpio.get(6).unwrap().into_output();
+    inverted.set_active_low(true);
+    let mut output = OutputPort::new(vec![gpio.get(5).unwrap().into_output(), inverted]).unwrap();
+
+    output.write(0b11);
+    assert_eq!(output.read(), 0b11);
+    assert_eq!(simulator.level(5).unwrap(), Level::High);
+    assert_eq!(simulator.level(6).unwrap(), Level::Low);
+
+    output.write(0b00);
+    assert_eq!(simulator.level(5).unwrap(), Level::Low);
+    assert_eq!(simulator.level(6).unwrap(), Level::High);
+
+    let mut inverted = gpio.get(21).unwrap().into_input();
+    inverted.set_active_low(true);
+    let input = InputPort::new(vec![gpio.get(20).unwrap().into_input(), inverted]).unwrap();
+
+    simulator.set_level(20, Level::High).unwrap();
+    simulator.set_level(21, Level::High).unwrap();
+    assert_eq!(input.read(), 0b01);
+
+    simulator.set_level(21, Level::Low).unwrap();
+    assert_eq!(input.read(), 0b11);
+}
+
+#[test]
+fn get_by_name() {
+    let (simulator, gpio) = simulated();
+
+    // Simulated lines are named GPIOn by default
+    assert_eq!(gpio.get_by_name("GPIO17").unwrap().pin(), 17);
+
+    simulator.set_line_name(4, "STATUS_LED").unwrap();
+    simulator.set_line_name(22, "STATUS_LED").unwrap();
+    assert_eq!(gpio.get_by_name("STATUS_LED").unwrap().pin(), 4);
+
+    match gpio.get_by_name("status_led") {
+        Err(Error::LineNotFound(ref name)) if name == "status_led" => (),
+        _ => panic!("expected LineNotFound"),
+    }
+}
+
+#[test]
+fn lines() {
+    let (simulator, gpio) = simulated();
+    simulator.set_line_name(2, "SDA1").unwrap();
+    let _pin = gpio.get(17).unwrap().into_output();
+
+    let lines = gpio.lines().unwrap();
+    assert_eq!(lines.len(), 54);
+    for (idx, line) in lines.iter().enumerate() {
+        assert_eq!(line.pin as usize, idx);
+    }
+
+    assert_eq!(lines[2].name, "SDA1");
+    assert_eq!(lines[3].name, "GPIO3");
+    assert_eq!(lines[17].direction, Mode::Output);
+    assert_eq!(lines[18].direction, Mode::Input);
+    assert_eq!(gpio.line_info(17).unwrap(), lines[17]);
+}
+
+// The scheduler is shared by all pins, so both scenarios run in a single test to
+// prevent conflicting configurations
+#[test]
+fn scheduler() {
+    let (simulator, gpio) = simulated();
+    let backend = PwmBackend::Scheduler(SchedulerConfig {
+        max_channels: 2,
+        ..SchedulerConfig::default()
+    });
+
+    let mut pins: Vec<_> = [5, 6, 13]
+        .iter()
+        .map(|&pin| {
+            let mut pin = gpio.get(pin).unwrap().into_output();
+            pin.set_pwm_backend(backend);
+            pin
+        })
+        .collect();
+
+    pins[0]
+        .set_pwm(Duration::from_millis(10), Duration::from_millis(5))
+        .unwrap();
+    pins[1]
+        .set_pwm(Duration::from_millis(10), Duration::from_millis(5))
+        .unwrap();
+    match pins[2].set_pwm(Duration::from_millis(10), Duration::from_millis(5)) {
+        Err(Error::Io(ref e)) if e.raw_os_error() == Some(libc::EBUSY) => (),
+        _ => panic!("expected EBUSY"),
+    }
+
+    // Channels are released when PWM is disabled
+    pins[1].clear_pwm().unwrap();
+    pins[2]
+        .set_pwm(Duration::from_millis(10), Duration::from_millis(5))
+        .unwrap();
+
+    // Scheduled pins toggle until PWM is disabled
+    wait_until(|| simulator.level(5).unwrap() == Level::High);
+    wait_until(|| simulator.level(5).unwrap() == Level::Low);
+    wait_until(|| simulator.level(13).unwrap() == Level::High);
+    wait_until(|| simulator.level(13).unwrap() == Level::Low);
+
+    pins[0].clear_pwm().unwrap();
+    let level = simulator.level(5).unwrap();
+    thread::sleep(Duration::from_millis(30));
+    assert_eq!(simulator.level(5).unwrap(), level);
+}
+
+#[test]
+fn send_waveform() {
+    let (simulator, gpio) = simulated();
+    let mut pin = gpio.get(17).unwrap().into_output();
+
+    let waveform = [
+        (Level::High, Duration::from_millis(5)),
+        (Level::Low, Duration::from_millis(5)),
+        (Level::High, Duration::from_millis(5)),
+    ];
+
+    // send_waveform blocks until the last entry's duration has passed
+    let start = Instant::now();
+    pin.send_waveform(&waveform).unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(15));
+    assert_eq!(simulator.level(17).unwrap(), Level::High);
+
+    pin.set_active_low(true);
+    pin.send_waveform(&[(Level::High, Duration::from_millis(1))])
+        .unwrap();
+    assert_eq!(simulator.level(17).unwrap(), Level::Low);
+}
+
+#[test]
+fn send_waveform_repeat() {
+    let (simulator, gpio) = simulated();
+    let mut pin = gpio.get(17).unwrap().into_output();
+
+    let waveform = [
+        (Level::High, Duration::from_millis(5)),
+        (Level::Low, Duration::from_millis(5)),
+    ];
+
+    match pin.send_waveform_repeat(&[(Level::High, Duration::new(0, 0))], None) {
+        Err(Error::Io(ref e)) if e.kind() == io::ErrorKind::InvalidInput => (),
+        _ => panic!("expected InvalidInput"),
+    }
+
+    let start = Instant::now();
+    let handle = pin.send_waveform_repeat(&waveform, Some(3)).unwrap();
+    assert!(!handle.is_finished());
+    wait_until(|| handle.is_finished());
+    assert!(start.elapsed() >= Duration::from_millis(30));
+    handle.wait().unwrap();
+    assert_eq!(simulator.level(17).unwrap(), Level::Low);
+
+    // Waveforms that repeat indefinitely keep toggling the pin until they're stopped
+    let handle = pin.send_waveform_repeat(&waveform, None).unwrap();
+    wait_until(|| simulator.level(17).unwrap() == Level::High);
+    wait_until(|| simulator.level(17).unwrap() == Level::Low);
+    wait_until(|| simulator.level(17).unwrap() == Level::High);
+    assert!(!handle.is_finished());
+
+    handle.stop().unwrap();
+    let level = simulator.level(17).unwrap();
+    thread::sleep(Duration::from_millis(20));
+    assert_eq!(simulator.level(17).unwrap(), level);
+
+    // Dropping the handle stops the waveform as well
+    let handle = pin.send_waveform_repeat(&waveform, None).unwrap();
+    wait_until(|| simulator.level(17).unwrap() == Level::High);
+    drop(handle);
+    let level = simulator.level(17).unwrap();
+    thread::sleep(Duration::from_millis(20));
+    assert_eq!(simulator.level(17).unwrap(), level);
+}
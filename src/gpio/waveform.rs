@@ -0,0 +1,234 @@
+// Copyright (c) 2017-2019 Rene van der Meer
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL
+// THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use super::dma::DmaWaveform;
+use super::soft_pwm::{
+    get_time_ns, invalid_input, recv_until_ns, set_realtime_priority, PwmBackend,
+};
+use super::{Drive, Error, GpioState, Level, Result};
+
+// Generates a waveform on a dedicated thread, using the same sleep and
+// busy-wait approach as the software-based PWM implementation
+#[derive(Debug)]
+struct WaveformThread {
+    waveform_thread: Option<thread::JoinHandle<()>>,
+    sender: Sender<()>,
+    finished: Arc<AtomicBool>,
+}
+
+impl WaveformThread {
+    fn new(
+        pin: u8,
+        drive: Drive,
+        active_low: bool,
+        gpio_state: Arc<GpioState>,
+        waveform: &[(Level, Duration)],
+        count: Option<u32>,
+    ) -> WaveformThread {
+        let (sender, receiver): (Sender<()>, Receiver<()>) = mpsc::channel();
+        let finished = Arc::new(AtomicBool::new(false));
+
+        let waveform: Vec<(Level, i64)> = waveform
+            .iter()
+            .map(|&(level, duration)| {
+                let level = if active_low { !level } else { level };
+
+                (level, duration.as_nanos() as i64)
+            })
+            .collect();
+
+        let thread_finished = finished.clone();
+        let waveform_thread = thread::spawn(move || {
+            set_realtime_priority();
+
+            let mut remaining = count;
+            let mut edge_ns = get_time_ns();
+
+            while remaining != Some(0) {
+                for &(level, duration_ns) in &waveform {
+                    gpio_state.backend.write(pin, level, drive);
+
+                    // Schedule each edge relative to the start of the waveform, so
+                    // any delays don't accumulate
+                    edge_ns += duration_ns;
+
+                    // Any message indicates we should stop
+                    match recv_until_ns(&receiver, edge_ns) {
+                        Ok(None) => (),
+                        _ => return,
+                    }
+                }
+
+                remaining = remaining.map(|count| count - 1);
+            }
+
+            thread_finished.store(true, Ordering::SeqCst);
+        });
+
+        WaveformThread {
+            waveform_thread: Some(waveform_thread),
+            sender,
+            finished,
+        }
+    }
+
+    fn wait(&mut self) -> Result<()> {
+        if let Some(waveform_thread) = self.waveform_thread.take() {
+            if waveform_thread.join().is_err() {
+                return Err(Error::ThreadPanic);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        let _ = self.sender.send(());
+
+        self.wait()
+    }
+}
+
+#[derive(Debug)]
+enum Generator {
+    Thread(WaveformThread),
+    Dma(DmaWaveform),
+}
+
+/// Handle for a waveform that's generated in the background.
+///
+/// `WaveformHandle` is returned by [`OutputPin::send_waveform_repeat`] and
+/// [`IoPin::send_waveform_repeat`]. The pin can't be accessed while the handle
+/// is in scope.
+///
+/// The waveform is stopped when the `WaveformHandle` goes out of scope.
+///
+/// [`OutputPin::send_waveform_repeat`]: struct.OutputPin.html#method.send_waveform_repeat
+/// [`IoPin::send_waveform_repeat`]: struct.IoPin.html#method.send_waveform_repeat
+#[derive(Debug)]
+pub struct WaveformHandle<'a> {
+    generator: Generator,
+    // Mutably borrows the pin, so its state can't be changed while the
+    // waveform is generated
+    phantom: PhantomData<&'a mut ()>,
+}
+
+impl<'a> WaveformHandle<'a> {
+    pub(crate) fn new(
+        backend: PwmBackend,
+        pin: u8,
+        drive: Drive,
+        active_low: bool,
+        gpio_state: Arc<GpioState>,
+        waveform: &[(Level, Duration)],
+        count: Option<u32>,
+    ) -> Result<WaveformHandle<'a>> {
+        if count.is_none()
+            && waveform
+                .iter()
+                .all(|&(_, duration)| duration == Duration::new(0, 0))
+        {
+            return Err(invalid_input("Repeating waveform has no duration"));
+        }
+
+        let generator = match backend {
+            PwmBackend::Dma(config) => Generator::Dma(DmaWaveform::new(
+                config,
+                pin,
+                drive,
+                active_low,
+                &gpio_state,
+                waveform,
+                count,
+            )?),
+            _ => Generator::Thread(WaveformThread::new(
+                pin, drive, active_low, gpio_state, waveform, count,
+            )),
+        };
+
+        Ok(WaveformHandle {
+            generator,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Returns `true` if the waveform has completed.
+    ///
+    /// Waveforms that repeat indefinitely never complete.
+    pub fn is_finished(&self) -> bool {
+        match self.generator {
+            Generator::Thread(ref waveform_thread) => {
+                waveform_thread.finished.load(Ordering::SeqCst)
+            }
+            Generator::Dma(ref dma_waveform) => dma_waveform.is_finished(),
+        }
+    }
+
+    /// Blocks until the waveform has completed.
+    ///
+    /// If the waveform repeats indefinitely, `wait` never returns. Use [`stop`]
+    /// instead.
+    ///
+    /// [`stop`]: #method.stop
+    pub fn wait(mut self) -> Result<()> {
+        match self.generator {
+            Generator::Thread(ref mut waveform_thread) => waveform_thread.wait(),
+            Generator::Dma(ref dma_waveform) => {
+                dma_waveform.wait();
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Stops the waveform.
+    ///
+    /// The pin retains its current output state.
+    pub fn stop(mut self) -> Result<()> {
+        self.stop_generator()
+    }
+
+    fn stop_generator(&mut self) -> Result<()> {
+        match self.generator {
+            Generator::Thread(ref mut waveform_thread) => waveform_thread.stop(),
+            Generator::Dma(ref mut dma_waveform) => {
+                dma_waveform.stop();
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<'a> Drop for WaveformHandle<'a> {
+    fn drop(&mut self) {
+        // Don't wait for the waveform thread to exit if the main thread is panicking
+        if !thread::panicking() {
+            let _ = self.stop_generator();
+        }
+    }
+}
@@ -39,6 +39,15 @@
 //! use with other peripherals. Be careful not to enable two peripherals on the same pin
 //! at the same time.
 //!
+//! ## PWM chips
+//!
+//! The sysfs interface exposes each PWM controller as a separate PWM chip. The
+//! Raspberry Pi's PWM peripheral is usually listed as `pwmchip0`, which is used by
+//! [`new`]. Additional PWM chips, such as I2C PWM controllers with a kernel driver
+//! (PCA9685) or PWM controllers enabled through other device tree overlays, can be
+//! accessed with [`with_chip`]. [`chips`] lists the available PWM chips, and the
+//! number of channels supported by each chip.
+//!
 //! ## Using PWM without superuser privileges (`sudo`)
 //!
 //! As of kernel version 4.14.34, released on April 16 2018, it's possible to
@@ -78,6 +87,8 @@
 //!
 //! [patch]: https://github.com/raspberrypi/linux/issues/1983
 //! [`new`]: struct.Pwm.html#method.new
+//! [`with_chip`]: struct.Pwm.html#method.with_chip
+//! [`chips`]: struct.Pwm.html#method.chips
 
 use std::error;
 use std::fmt;
@@ -156,6 +167,23 @@ impl fmt::Display for Polarity {
     }
 }
 
+/// Information about a PWM chip, as reported by the `pwm` sysfs interface.
+///
+/// `ChipInfo` is returned by [`Pwm::chips`].
+///
+/// [`Pwm::chips`]: struct.Pwm.html#method.chips
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ChipInfo {
+    /// Chip number, as used in `/sys/class/pwm/pwmchipN`.
+    pub chip: u8,
+    /// Name of the device that registered the chip. The Raspberry Pi's PWM
+    /// peripheral is listed as `20c000.pwm` on the BCM2835, `3f20c000.pwm` on the
+    /// BCM2836 and BCM2837, and `fe20c000.pwm` on the BCM2711.
+    pub device: String,
+    /// Number of PWM channels, as reported by `npwm`.
+    pub channels: u32,
+}
+
 /// Provides access to the Raspberry Pi's PWM peripheral.
 ///
 /// Before using `Pwm`, make sure the selected PWM channel has been configured
@@ -174,11 +202,28 @@ impl fmt::Display for Polarity {
 /// [`Pwm`]: ../../embedded_hal/trait.Pwm.html
 #[derive(Debug)]
 pub struct Pwm {
-    channel: Channel,
+    chip: u8,
+    channel: u8,
     reset_on_drop: bool,
 }
 
 impl Pwm {
+    /// Returns information about all PWM chips exposed through
+    /// `/sys/class/pwm/pwmchipN`, sorted by their chip number.
+    pub fn chips() -> Result<Vec<ChipInfo>> {
+        let mut chips = Vec::new();
+
+        for chip in sysfs::chips()? {
+            chips.push(ChipInfo {
+                chip,
+                device: sysfs::device(chip)?,
+                channels: sysfs::npwm(chip)?,
+            });
+        }
+
+        Ok(chips)
+    }
+
     /// Constructs a new `Pwm`.
     ///
     /// `new` doesn't change the channel's period, pulse width or polarity. The channel
@@ -186,9 +231,31 @@ impl Pwm {
     ///
     /// [`enable`]: #method.enable
     pub fn new(channel: Channel) -> Result<Pwm> {
-        sysfs::export(channel as u8)?;
+        Pwm::with_chip(0, channel as u8)
+    }
+
+    /// Constructs a new `Pwm` for a channel on the specified PWM chip.
+    ///
+    /// `chip` and `channel` select the channel exposed through
+    /// `/sys/class/pwm/pwmchip<chip>/pwm<channel>`. Use [`chips`] to find the
+    /// available PWM chips and the number of channels they support. [`new`] is
+    /// equivalent to calling `with_chip` with `chip` set to `0`.
+    ///
+    /// `with_chip` returns an `io::ErrorKind::NotFound` error if the PWM chip doesn't
+    /// exist, and an `io::ErrorKind::InvalidInput` error if the chip doesn't support
+    /// the selected channel.
+    ///
+    /// `with_chip` doesn't change the channel's period, pulse width or polarity. The
+    /// channel will remain disabled until [`enable`] is called.
+    ///
+    /// [`chips`]: #method.chips
+    /// [`new`]: #method.new
+    /// [`enable`]: #method.enable
+    pub fn with_chip(chip: u8, channel: u8) -> Result<Pwm> {
+        sysfs::export(chip, channel)?;
 
         let pwm = Pwm {
+            chip,
             channel,
             reset_on_drop: true,
         };
@@ -226,20 +293,10 @@ impl Pwm {
         polarity: Polarity,
         enabled: bool,
     ) -> Result<Pwm> {
-        sysfs::export(channel as u8)?;
-
-        let pwm = Pwm {
-            channel,
-            reset_on_drop: true,
-        };
-
-        // Always reset "enable" to 0. The sysfs pwm interface has a bug where a previous
-        // export may have left "enable" as 1 after unexporting. On the next export,
-        // "enable" is still set to 1, even though the channel isn't enabled.
-        let _ = pwm.disable();
+        let pwm = Pwm::new(channel)?;
 
         // Set pulse width to 0 first in case the new period is shorter than the current pulse width
-        let _ = sysfs::set_pulse_width(channel as u8, 0);
+        let _ = sysfs::set_pulse_width(pwm.chip, pwm.channel, 0);
 
         pwm.set_period(period)?;
         pwm.set_pulse_width(pulse_width)?;
@@ -276,20 +333,10 @@ impl Pwm {
         polarity: Polarity,
         enabled: bool,
     ) -> Result<Pwm> {
-        sysfs::export(channel as u8)?;
-
-        let pwm = Pwm {
-            channel,
-            reset_on_drop: true,
-        };
-
-        // Always reset "enable" to 0. The sysfs pwm interface has a bug where a previous
-        // export may have left "enable" as 1 after unexporting. On the next export,
-        // "enable" is still set to 1, even though the channel isn't enabled.
-        let _ = pwm.disable();
+        let pwm = Pwm::new(channel)?;
 
         // Set pulse width to 0 first in case the new period is shorter than the current pulse width
-        let _ = sysfs::set_pulse_width(channel as u8, 0);
+        let _ = sysfs::set_pulse_width(pwm.chip, pwm.channel, 0);
 
         // Convert to nanoseconds
         let period = if frequency == 0.0 {
@@ -299,8 +346,8 @@ impl Pwm {
         };
         let pulse_width = period * duty_cycle.max(0.0).min(1.0);
 
-        sysfs::set_period(channel as u8, period as u64)?;
-        sysfs::set_pulse_width(channel as u8, pulse_width as u64)?;
+        sysfs::set_period(pwm.chip, pwm.channel, period as u64)?;
+        sysfs::set_pulse_width(pwm.chip, pwm.channel, pulse_width as u64)?;
         pwm.set_polarity(polarity)?;
         if enabled {
             pwm.enable()?;
@@ -309,9 +356,22 @@ impl Pwm {
         Ok(pwm)
     }
 
+    /// Returns the PWM chip number.
+    pub fn chip(&self) -> u8 {
+        self.chip
+    }
+
+    /// Returns the channel number on the PWM chip.
+    pub fn channel(&self) -> u8 {
+        self.channel
+    }
+
     /// Returns the period.
     pub fn period(&self) -> Result<Duration> {
-        Ok(Duration::from_nanos(sysfs::period(self.channel as u8)?))
+        Ok(Duration::from_nanos(sysfs::period(
+            self.chip,
+            self.channel,
+        )?))
     }
 
     /// Sets the period.
@@ -321,7 +381,8 @@ impl Pwm {
     /// This method will fail if `period` is shorter than the current pulse width.
     pub fn set_period(&self, period: Duration) -> Result<()> {
         sysfs::set_period(
-            self.channel as u8,
+            self.chip,
+            self.channel,
             u64::from(period.subsec_nanos())
                 .saturating_add(period.as_secs().saturating_mul(NANOS_PER_SEC as u64)),
         )?;
@@ -332,7 +393,8 @@ impl Pwm {
     /// Returns the pulse width.
     pub fn pulse_width(&self) -> Result<Duration> {
         Ok(Duration::from_nanos(sysfs::pulse_width(
-            self.channel as u8,
+            self.chip,
+            self.channel,
         )?))
     }
 
@@ -344,7 +406,8 @@ impl Pwm {
     /// This method will fail if `pulse_width` is longer than the current period.
     pub fn set_pulse_width(&self, pulse_width: Duration) -> Result<()> {
         sysfs::set_pulse_width(
-            self.channel as u8,
+            self.chip,
+            self.channel,
             u64::from(pulse_width.subsec_nanos())
                 .saturating_add(pulse_width.as_secs().saturating_mul(NANOS_PER_SEC as u64)),
         )?;
@@ -357,7 +420,7 @@ impl Pwm {
     /// `frequency` is a convenience method that calculates the frequency in hertz (Hz)
     /// based on the configured period.
     pub fn frequency(&self) -> Result<f64> {
-        let period = sysfs::period(self.channel as u8)? as f64;
+        let period = sysfs::period(self.chip, self.channel)? as f64;
 
         Ok(if period == 0.0 {
             0.0
@@ -376,7 +439,7 @@ impl Pwm {
     /// `duty_cycle` is specified as a floating point value between `0.0` (0%) and `1.0` (100%).
     pub fn set_frequency(&self, frequency: f64, duty_cycle: f64) -> Result<()> {
         // Set duty cycle to 0 first in case the new period is shorter than the current duty cycle
-        let _ = sysfs::set_pulse_width(self.channel as u8, 0);
+        let _ = sysfs::set_pulse_width(self.chip, self.channel, 0);
 
         // Convert to nanoseconds
         let period = if frequency == 0.0 {
@@ -386,8 +449,8 @@ impl Pwm {
         };
         let pulse_width = period * duty_cycle.max(0.0).min(1.0);
 
-        sysfs::set_period(self.channel as u8, period as u64)?;
-        sysfs::set_pulse_width(self.channel as u8, pulse_width as u64)?;
+        sysfs::set_period(self.chip, self.channel, period as u64)?;
+        sysfs::set_pulse_width(self.chip, self.channel, pulse_width as u64)?;
 
         Ok(())
     }
@@ -398,8 +461,8 @@ impl Pwm {
     /// floating point value between `0.0` (0%) and `1.0` (100%) based on the configured
     /// period and pulse width.
     pub fn duty_cycle(&self) -> Result<f64> {
-        let period = sysfs::period(self.channel as u8)? as f64;
-        let pulse_width = sysfs::pulse_width(self.channel as u8)? as f64;
+        let period = sysfs::period(self.chip, self.channel)? as f64;
+        let pulse_width = sysfs::pulse_width(self.chip, self.channel)? as f64;
 
         Ok(if period == 0.0 {
             0.0
@@ -415,17 +478,17 @@ impl Pwm {
     ///
     /// `duty_cycle` is specified as a floating point value between `0.0` (0%) and `1.0` (100%).
     pub fn set_duty_cycle(&self, duty_cycle: f64) -> Result<()> {
-        let period = sysfs::period(self.channel as u8)? as f64;
+        let period = sysfs::period(self.chip, self.channel)? as f64;
         let pulse_width = period * duty_cycle.max(0.0).min(1.0);
 
-        sysfs::set_pulse_width(self.channel as u8, pulse_width as u64)?;
+        sysfs::set_pulse_width(self.chip, self.channel, pulse_width as u64)?;
 
         Ok(())
     }
 
     /// Returns the polarity.
     pub fn polarity(&self) -> Result<Polarity> {
-        Ok(sysfs::polarity(self.channel as u8)?)
+        Ok(sysfs::polarity(self.chip, self.channel)?)
     }
 
     /// Sets the polarity.
@@ -436,26 +499,26 @@ impl Pwm {
     /// [`Normal`]: enum.Polarity.html#variant.Normal
     /// [`Inverse`]: enum.Polarity.html#variant.Inverse
     pub fn set_polarity(&self, polarity: Polarity) -> Result<()> {
-        sysfs::set_polarity(self.channel as u8, polarity)?;
+        sysfs::set_polarity(self.chip, self.channel, polarity)?;
 
         Ok(())
     }
 
     /// Returns `true` if the PWM channel is enabled.
     pub fn is_enabled(&self) -> Result<bool> {
-        Ok(sysfs::enabled(self.channel as u8)?)
+        Ok(sysfs::enabled(self.chip, self.channel)?)
     }
 
     /// Enables the PWM channel.
     pub fn enable(&self) -> Result<()> {
-        sysfs::set_enabled(self.channel as u8, true)?;
+        sysfs::set_enabled(self.chip, self.channel, true)?;
 
         Ok(())
     }
 
     /// Disables the PWM channel.
     pub fn disable(&self) -> Result<()> {
-        sysfs::set_enabled(self.channel as u8, false)?;
+        sysfs::set_enabled(self.chip, self.channel, false)?;
 
         Ok(())
     }
@@ -483,8 +546,8 @@ impl Pwm {
 impl Drop for Pwm {
     fn drop(&mut self) {
         if self.reset_on_drop {
-            let _ = sysfs::set_enabled(self.channel as u8, false);
-            let _ = sysfs::unexport(self.channel as u8);
+            let _ = sysfs::set_enabled(self.chip, self.channel, false);
+            let _ = sysfs::unexport(self.chip, self.channel);
         }
     }
 }
@@ -116,10 +116,62 @@ fn check_permissions(path: &str, gid: u32) -> bool {
     false
 }
 
-pub fn export(channel: u8) -> Result<()> {
+// Returns the numbers of all PWM chips exposed through sysfs
+pub fn chips() -> Result<Vec<u8>> {
+    let mut chips = Vec::new();
+
+    for entry in fs::read_dir("/sys/class/pwm")? {
+        let file_name = entry?.file_name();
+
+        if let Some(chip) = file_name.to_str().and_then(parse_chip_name) {
+            chips.push(chip);
+        }
+    }
+
+    chips.sort();
+
+    Ok(chips)
+}
+
+// Returns the chip number for a sysfs entry named pwmchipN
+fn parse_chip_name(name: &str) -> Option<u8> {
+    Some(name)
+        .filter(|name| name.starts_with("pwmchip"))
+        .and_then(|name| name["pwmchip".len()..].parse().ok())
+}
+
+// Number of PWM channels supported by the chip
+pub fn npwm(chip: u8) -> Result<u32> {
+    let npwm = fs::read_to_string(format!("/sys/class/pwm/pwmchip{}/npwm", chip))?;
+
+    parse_npwm(&npwm)
+}
+
+// Fails with InvalidData rather than reporting a chip without channels
+fn parse_npwm(npwm: &str) -> Result<u32> {
+    npwm.trim().parse().map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid npwm value: {:?}", npwm.trim()),
+        )
+    })
+}
+
+// Name of the device that registered the chip, for instance 20c000.pwm
+pub fn device(chip: u8) -> Result<String> {
+    let device = fs::read_link(format!("/sys/class/pwm/pwmchip{}/device", chip))?;
+
+    Ok(device
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default())
+}
+
+pub fn export(chip: u8, channel: u8) -> Result<()> {
     // Only export if the channel isn't already exported
-    if !Path::new(&format!("/sys/class/pwm/pwmchip0/pwm{}", channel)).exists() {
-        File::create("/sys/class/pwm/pwmchip0/export")?.write_fmt(format_args!("{}", channel))?;
+    if !Path::new(&format!("/sys/class/pwm/pwmchip{}/pwm{}", chip, channel)).exists() {
+        File::create(format!("/sys/class/pwm/pwmchip{}/export", chip))?
+            .write_fmt(format_args!("{}", channel))?;
     }
 
     // If we're logged in as root or effective root, skip the permission checks
@@ -144,11 +196,11 @@ pub fn export(channel: u8) -> Result<()> {
     };
 
     let paths = &[
-        format!("/sys/class/pwm/pwmchip0/pwm{}", channel),
-        format!("/sys/class/pwm/pwmchip0/pwm{}/period", channel),
-        format!("/sys/class/pwm/pwmchip0/pwm{}/duty_cycle", channel),
-        format!("/sys/class/pwm/pwmchip0/pwm{}/polarity", channel),
-        format!("/sys/class/pwm/pwmchip0/pwm{}/enable", channel),
+        format!("/sys/class/pwm/pwmchip{}/pwm{}", chip, channel),
+        format!("/sys/class/pwm/pwmchip{}/pwm{}/period", chip, channel),
+        format!("/sys/class/pwm/pwmchip{}/pwm{}/duty_cycle", chip, channel),
+        format!("/sys/class/pwm/pwmchip{}/pwm{}/polarity", chip, channel),
+        format!("/sys/class/pwm/pwmchip{}/pwm{}/enable", chip, channel),
     ];
 
     let mut counter = 0;
@@ -169,17 +221,21 @@ pub fn export(channel: u8) -> Result<()> {
     Ok(())
 }
 
-pub fn unexport(channel: u8) -> Result<()> {
+pub fn unexport(chip: u8, channel: u8) -> Result<()> {
     // Only unexport if the channel is actually exported
-    if Path::new(&format!("/sys/class/pwm/pwmchip0/pwm{}", channel)).exists() {
-        File::create("/sys/class/pwm/pwmchip0/unexport")?.write_fmt(format_args!("{}", channel))?;
+    if Path::new(&format!("/sys/class/pwm/pwmchip{}/pwm{}", chip, channel)).exists() {
+        File::create(format!("/sys/class/pwm/pwmchip{}/unexport", chip))?
+            .write_fmt(format_args!("{}", channel))?;
     }
 
     Ok(())
 }
 
-pub fn period(channel: u8) -> Result<u64> {
-    let period = fs::read_to_string(format!("/sys/class/pwm/pwmchip0/pwm{}/period", channel))?;
+pub fn period(chip: u8, channel: u8) -> Result<u64> {
+    let period = fs::read_to_string(format!(
+        "/sys/class/pwm/pwmchip{}/pwm{}/period",
+        chip, channel
+    ))?;
     if let Ok(period) = period.trim().parse() {
         Ok(period)
     } else {
@@ -187,18 +243,23 @@ pub fn period(channel: u8) -> Result<u64> {
     }
 }
 
-pub fn set_period(channel: u8, period: u64) -> Result<()> {
-    File::create(format!("/sys/class/pwm/pwmchip0/pwm{}/period", channel))?
-        .write_fmt(format_args!("{}", period))?;
+pub fn set_period(chip: u8, channel: u8, period: u64) -> Result<()> {
+    File::create(format!(
+        "/sys/class/pwm/pwmchip{}/pwm{}/period",
+        chip, channel
+    ))?
+    .write_fmt(format_args!("{}", period))?;
 
     Ok(())
 }
 
-pub fn pulse_width(channel: u8) -> Result<u64> {
+pub fn pulse_width(chip: u8, channel: u8) -> Result<u64> {
     // The sysfs PWM interface specifies the duty cycle in nanoseconds, which
     // means it's actually the pulse width.
-    let duty_cycle =
-        fs::read_to_string(format!("/sys/class/pwm/pwmchip0/pwm{}/duty_cycle", channel))?;
+    let duty_cycle = fs::read_to_string(format!(
+        "/sys/class/pwm/pwmchip{}/pwm{}/duty_cycle",
+        chip, channel
+    ))?;
 
     if let Ok(duty_cycle) = duty_cycle.trim().parse() {
         Ok(duty_cycle)
@@ -207,17 +268,23 @@ pub fn pulse_width(channel: u8) -> Result<u64> {
     }
 }
 
-pub fn set_pulse_width(channel: u8, pulse_width: u64) -> Result<()> {
+pub fn set_pulse_width(chip: u8, channel: u8, pulse_width: u64) -> Result<()> {
     // The sysfs PWM interface specifies the duty cycle in nanoseconds, which
     // means it's actually the pulse width.
-    File::create(format!("/sys/class/pwm/pwmchip0/pwm{}/duty_cycle", channel))?
-        .write_fmt(format_args!("{}", pulse_width))?;
+    File::create(format!(
+        "/sys/class/pwm/pwmchip{}/pwm{}/duty_cycle",
+        chip, channel
+    ))?
+    .write_fmt(format_args!("{}", pulse_width))?;
 
     Ok(())
 }
 
-pub fn polarity(channel: u8) -> Result<Polarity> {
-    let polarity = fs::read_to_string(format!("/sys/class/pwm/pwmchip0/pwm{}/polarity", channel))?;
+pub fn polarity(chip: u8, channel: u8) -> Result<Polarity> {
+    let polarity = fs::read_to_string(format!(
+        "/sys/class/pwm/pwmchip{}/pwm{}/polarity",
+        chip, channel
+    ))?;
 
     match polarity.trim() {
         "normal" => Ok(Polarity::Normal),
@@ -225,20 +292,26 @@ pub fn polarity(channel: u8) -> Result<Polarity> {
     }
 }
 
-pub fn set_polarity(channel: u8, polarity: Polarity) -> Result<()> {
+pub fn set_polarity(chip: u8, channel: u8, polarity: Polarity) -> Result<()> {
     let b_polarity: &[u8] = match polarity {
         Polarity::Normal => b"normal",
         Polarity::Inverse => b"inversed",
     };
 
-    File::create(format!("/sys/class/pwm/pwmchip0/pwm{}/polarity", channel))?
-        .write_all(b_polarity)?;
+    File::create(format!(
+        "/sys/class/pwm/pwmchip{}/pwm{}/polarity",
+        chip, channel
+    ))?
+    .write_all(b_polarity)?;
 
     Ok(())
 }
 
-pub fn enabled(channel: u8) -> Result<bool> {
-    let enabled = fs::read_to_string(format!("/sys/class/pwm/pwmchip0/pwm{}/enable", channel))?;
+pub fn enabled(chip: u8, channel: u8) -> Result<bool> {
+    let enabled = fs::read_to_string(format!(
+        "/sys/class/pwm/pwmchip{}/pwm{}/enable",
+        chip, channel
+    ))?;
 
     match enabled.trim() {
         "0" => Ok(false),
@@ -246,9 +319,41 @@ pub fn enabled(channel: u8) -> Result<bool> {
     }
 }
 
-pub fn set_enabled(channel: u8, enabled: bool) -> Result<()> {
-    File::create(format!("/sys/class/pwm/pwmchip0/pwm{}/enable", channel))?
-        .write_fmt(format_args!("{}", enabled as u8))?;
+pub fn set_enabled(chip: u8, channel: u8, enabled: bool) -> Result<()> {
+    File::create(format!(
+        "/sys/class/pwm/pwmchip{}/pwm{}/enable",
+        chip, channel
+    ))?
+    .write_fmt(format_args!("{}", enabled as u8))?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chip_name() {
+        assert_eq!(parse_chip_name("pwmchip0"), Some(0));
+        assert_eq!(parse_chip_name("pwmchip12"), Some(12));
+        assert_eq!(parse_chip_name("pwmchip"), None);
+        assert_eq!(parse_chip_name("pwmchipX"), None);
+        assert_eq!(parse_chip_name("pwmchip256"), None);
+        assert_eq!(parse_chip_name("gpiochip0"), None);
+        assert_eq!(parse_chip_name("xpwmchip0"), None);
+    }
+
+    #[test]
+    fn npwm_value() {
+        assert_eq!(parse_npwm("2\n").unwrap(), 2);
+        assert_eq!(
+            parse_npwm("").unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        assert_eq!(
+            parse_npwm("two\n").unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+}
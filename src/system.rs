@@ -33,7 +33,9 @@ use std::result;
 
 const PERIPHERAL_BASE_RPI: u32 = 0x2000_0000;
 const PERIPHERAL_BASE_RPI2: u32 = 0x3f00_0000;
+const PERIPHERAL_BASE_RPI4: u32 = 0xfe00_0000;
 const GPIO_OFFSET: u32 = 0x20_0000;
+const PADS_OFFSET: u32 = 0x10_0000;
 
 /// Errors that can occur when trying to identify the Raspberry Pi hardware.
 #[derive(Debug)]
@@ -309,6 +311,7 @@ pub struct DeviceInfo {
     soc: SoC,
     peripheral_base: u32,
     gpio_offset: u32,
+    pads_offset: u32,
 }
 
 impl DeviceInfo {
@@ -336,18 +339,21 @@ impl DeviceInfo {
                 soc: SoC::Bcm2835,
                 peripheral_base: PERIPHERAL_BASE_RPI,
                 gpio_offset: GPIO_OFFSET,
+                pads_offset: PADS_OFFSET,
             }),
             Model::RaspberryPi2B => Ok(DeviceInfo {
                 model,
                 soc: SoC::Bcm2836,
                 peripheral_base: PERIPHERAL_BASE_RPI2,
                 gpio_offset: GPIO_OFFSET,
+                pads_offset: PADS_OFFSET,
             }),
             Model::RaspberryPi3B | Model::RaspberryPiComputeModule3 => Ok(DeviceInfo {
                 model,
                 soc: SoC::Bcm2837A1,
                 peripheral_base: PERIPHERAL_BASE_RPI2,
                 gpio_offset: GPIO_OFFSET,
+                pads_offset: PADS_OFFSET,
             }),
             Model::RaspberryPi3BPlus
             | Model::RaspberryPi3APlus
@@ -356,12 +362,14 @@ impl DeviceInfo {
                 soc: SoC::Bcm2837B0,
                 peripheral_base: PERIPHERAL_BASE_RPI2,
                 gpio_offset: GPIO_OFFSET,
+                pads_offset: PADS_OFFSET,
             }),
             Model::RaspberryPi4B => Ok(DeviceInfo {
                 model,
                 soc: SoC::Bcm2711,
-                peripheral_base: PERIPHERAL_BASE_RPI2,
+                peripheral_base: PERIPHERAL_BASE_RPI4,
                 gpio_offset: GPIO_OFFSET,
+                pads_offset: PADS_OFFSET,
             }),
             Model::__Nonexhaustive => unreachable!(),
         }
@@ -386,4 +394,9 @@ impl DeviceInfo {
     pub(crate) fn gpio_offset(&self) -> u32 {
         self.gpio_offset
     }
+
+    /// Returns the offset from the base memory address for the pad control section.
+    pub(crate) fn pads_offset(&self) -> u32 {
+        self.pads_offset
+    }
 }